@@ -0,0 +1,122 @@
+use log::error;
+use regex::Regex;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct BranchNameSettings {
+    /// Regex branch names must match
+    pattern: String,
+}
+
+impl Default for BranchNameSettings {
+    fn default() -> Self {
+        BranchNameSettings {
+            pattern: "^(feature|fix|chore)/[a-z0-9-]+$".to_string(),
+        }
+    }
+}
+
+/// Fails if `branch` does not match the configured pattern
+pub fn check_branch_name(branch: &str, settings: &BranchNameSettings) -> anyhow::Result<()> {
+    let re = Regex::new(&settings.pattern).map_err(|e| {
+        anyhow::Error::msg(format!(
+            "invalid branch name pattern {}: {}",
+            settings.pattern, e
+        ))
+    })?;
+    if re.is_match(branch) {
+        Ok(())
+    } else {
+        error!(
+            "branch name \"{}\" does not match {}. Rename it with git branch -m <new-name>",
+            branch, settings.pattern
+        );
+        Err(anyhow::Error::msg("invalid branch name"))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ProtectedBranchesSettings {
+    /// Names of the protected branches. `*` matches any characters, as in `release/*`.
+    branches: Vec<String>,
+}
+
+impl Default for ProtectedBranchesSettings {
+    fn default() -> Self {
+        ProtectedBranchesSettings {
+            branches: vec!["main".to_string(), "master".to_string()],
+        }
+    }
+}
+
+/// Returns whether `branch` matches the glob `pattern`, in which `*` matches any characters
+fn glob_matches(pattern: &str, branch: &str) -> bool {
+    let re = format!(
+        "^{}$",
+        pattern
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<String>>()
+            .join(".*")
+    );
+    Regex::new(&re)
+        .unwrap_or_else(|e| panic!("invalid regex: {}", e))
+        .is_match(branch)
+}
+
+/// Fails if `branch` is one of the protected branches
+pub fn check_not_protected(
+    branch: &str,
+    settings: &ProtectedBranchesSettings,
+) -> anyhow::Result<()> {
+    match settings.branches.iter().find(|p| glob_matches(p, branch)) {
+        Some(pattern) => {
+            error!(
+                "committing directly to {} is not allowed (protected by \"{}\"). Create a branch with git switch -c <name>",
+                branch, pattern
+            );
+            Err(anyhow::Error::msg("commit to a protected branch"))
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::branch::{
+        check_branch_name, check_not_protected, glob_matches, BranchNameSettings,
+        ProtectedBranchesSettings,
+    };
+
+    #[test]
+    fn test_check_branch_name() {
+        let settings = BranchNameSettings::default();
+        assert!(check_branch_name("feature/add-thing", &settings).is_ok());
+        assert!(check_branch_name("my-branch", &settings).is_err());
+        let settings: BranchNameSettings = serde_yaml::from_str("pattern: ^JIRA-[0-9]+").unwrap();
+        assert!(check_branch_name("JIRA-12-fix", &settings).is_ok());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("main", "main"));
+        assert!(!glob_matches("main", "main2"));
+        assert!(glob_matches("release/*", "release/1.0"));
+        assert!(!glob_matches("release/*", "feature/release"));
+        assert!(glob_matches("v1.*", "v1.2"));
+        assert!(!glob_matches("v1.*", "v102"));
+    }
+
+    #[test]
+    fn test_check_not_protected() {
+        let settings = ProtectedBranchesSettings::default();
+        assert!(check_not_protected("master", &settings).is_err());
+        assert!(check_not_protected("feature/a", &settings).is_ok());
+        let settings: ProtectedBranchesSettings =
+            serde_yaml::from_str("branches: [develop, release/*]").unwrap();
+        assert!(check_not_protected("release/2", &settings).is_err());
+        assert!(check_not_protected("main", &settings).is_ok());
+    }
+}
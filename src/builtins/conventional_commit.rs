@@ -0,0 +1,152 @@
+use regex::Regex;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ConventionalCommitSettings {
+    /// Allowed commit types
+    types: Vec<String>,
+    /// Allowed scopes. Any scope is allowed if empty.
+    scopes: Vec<String>,
+    require_scope: bool,
+    max_subject_length: usize,
+    max_body_line_length: usize,
+}
+
+impl Default for ConventionalCommitSettings {
+    fn default() -> Self {
+        ConventionalCommitSettings {
+            types: [
+                "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert",
+                "style", "test",
+            ]
+            .iter()
+            .map(|t| t.to_string())
+            .collect(),
+            scopes: vec![],
+            require_scope: false,
+            max_subject_length: 72,
+            max_body_line_length: 100,
+        }
+    }
+}
+
+/// Returns the lines of the message git will actually record: comments and everything below the
+/// scissors line are left out
+pub fn message_lines(message: &str) -> Vec<&str> {
+    message
+        .lines()
+        .take_while(|l| !l.starts_with("# ------------------------ >8 ------------------------"))
+        .filter(|l| !l.starts_with('#'))
+        .collect()
+}
+
+/// Returns the problems found in the commit message, according to
+/// https://www.conventionalcommits.org/
+pub fn check_message(message: &str, settings: &ConventionalCommitSettings) -> Vec<String> {
+    let lines = message_lines(message);
+    let header = match lines.iter().find(|l| !l.trim().is_empty()) {
+        Some(header) => *header,
+        None => return vec!["the commit message is empty".to_string()],
+    };
+    // let git handle those
+    if ["Merge ", "fixup! ", "squash! ", "amend! "]
+        .iter()
+        .any(|p| header.starts_with(p))
+    {
+        return vec![];
+    }
+    let mut problems = Vec::new();
+    let header_re =
+        Regex::new(r"^(?P<type>[a-zA-Z]+)(\((?P<scope>[^()]+)\))?!?: (?P<subject>\S.*)$")
+            .unwrap_or_else(|e| panic!("invalid regex: {}", e));
+    match header_re.captures(header) {
+        None => problems.push(format!(
+            "\"{}\" does not follow the \"type(scope): subject\" format",
+            header
+        )),
+        Some(captures) => {
+            let commit_type = &captures["type"];
+            if !settings.types.iter().any(|t| t == commit_type) {
+                problems.push(format!(
+                    "unknown type \"{}\", expected one of: {}",
+                    commit_type,
+                    settings.types.join(", ")
+                ));
+            }
+            match captures.name("scope") {
+                Some(scope)
+                    if !settings.scopes.is_empty()
+                        && !settings.scopes.iter().any(|s| s == scope.as_str()) =>
+                {
+                    problems.push(format!(
+                        "unknown scope \"{}\", expected one of: {}",
+                        scope.as_str(),
+                        settings.scopes.join(", ")
+                    ))
+                }
+                None if settings.require_scope => problems.push("a scope is required".to_string()),
+                _ => {}
+            }
+            let subject_length = captures["subject"].chars().count();
+            if subject_length > settings.max_subject_length {
+                problems.push(format!(
+                    "the subject is {} characters long, the maximum is {}",
+                    subject_length, settings.max_subject_length
+                ));
+            }
+        }
+    }
+    let header_index = lines.iter().position(|l| *l == header).unwrap_or(0);
+    let body = &lines[header_index + 1..];
+    if let Some(first) = body.first() {
+        if !first.trim().is_empty() {
+            problems.push("the subject must be followed by a blank line".to_string());
+        }
+    }
+    for (i, line) in body.iter().enumerate() {
+        let length = line.chars().count();
+        if length > settings.max_body_line_length {
+            problems.push(format!(
+                "line {} of the message is {} characters long, the maximum is {}",
+                header_index + i + 2,
+                length,
+                settings.max_body_line_length
+            ));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::conventional_commit::{check_message, ConventionalCommitSettings};
+
+    #[test]
+    fn test_valid_messages() {
+        let settings = ConventionalCommitSettings::default();
+        assert!(check_message("feat: add a thing\n", &settings).is_empty());
+        assert!(check_message("fix(parser)!: handle EOF\n\nbody\n", &settings).is_empty());
+        assert!(
+            check_message("docs: typo\n# Please enter the commit message\n", &settings).is_empty()
+        );
+        assert!(check_message("Merge branch 'main'\n", &settings).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_messages() {
+        let settings = ConventionalCommitSettings::default();
+        assert_eq!(check_message("added a thing\n", &settings).len(), 1);
+        assert_eq!(check_message("feature: add a thing\n", &settings).len(), 1);
+        assert_eq!(check_message("feat: a\nbody\n", &settings).len(), 1);
+        assert_eq!(check_message("# only comments\n", &settings).len(), 1);
+        let long = format!("feat: {}\n", "a".repeat(80));
+        assert_eq!(check_message(&long, &settings).len(), 1);
+
+        let settings: ConventionalCommitSettings =
+            serde_yaml::from_str("scopes: [cli]\nrequire_scope: true").unwrap();
+        assert_eq!(check_message("feat: a\n", &settings).len(), 1);
+        assert_eq!(check_message("feat(core): a\n", &settings).len(), 1);
+        assert!(check_message("feat(cli): a\n", &settings).is_empty());
+    }
+}
@@ -0,0 +1,42 @@
+use log::error;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct LargeFilesSettings {
+    /// Maximum size of an added file, in kilobytes
+    max_size_kb: u64,
+}
+
+impl Default for LargeFilesSettings {
+    fn default() -> Self {
+        LargeFilesSettings { max_size_kb: 500 }
+    }
+}
+
+/// Fails if any added file is bigger than the configured threshold. `size` gives the size of the
+/// added blob, such as the staged one, rather than the worktree file, which may differ.
+pub fn check_added_large_files<F: Fn(&str) -> anyhow::Result<u64>>(
+    files: &[String],
+    size: F,
+    settings: &LargeFilesSettings,
+) -> anyhow::Result<()> {
+    let mut failed = false;
+    for file in files {
+        let size = size(file)?;
+        if size > settings.max_size_kb * 1024 {
+            error!(
+                "{} is {} KB, which exceeds the {} KB limit",
+                file,
+                size / 1024,
+                settings.max_size_kb
+            );
+            failed = true;
+        }
+    }
+    if failed {
+        Err(anyhow::Error::msg("some added files are too large"))
+    } else {
+        Ok(())
+    }
+}
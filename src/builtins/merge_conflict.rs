@@ -0,0 +1,32 @@
+const CONFLICT_MARKERS: &[&[u8]] = &[b"<<<<<<<", b">>>>>>>"];
+
+/// Returns the line numbers, starting at 1, of the merge conflict markers found in `content`
+pub fn find_conflict_markers(content: &[u8]) -> Vec<usize> {
+    content
+        .split(|b| *b == b'\n')
+        .enumerate()
+        .filter(|(_, line)| {
+            CONFLICT_MARKERS.iter().any(|marker| {
+                line.starts_with(marker)
+                    && matches!(line.get(marker.len()), None | Some(b' ') | Some(b'\r'))
+            })
+        })
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::merge_conflict::find_conflict_markers;
+
+    #[test]
+    fn test_find_conflict_markers() {
+        assert!(find_conflict_markers(b"a\nb\n").is_empty());
+        assert_eq!(
+            find_conflict_markers(b"a\n<<<<<<< HEAD\nb\n=======\nc\n>>>>>>> feature\n"),
+            vec![2, 6]
+        );
+        assert_eq!(find_conflict_markers(b"<<<<<<<\r\n"), vec![1]);
+        assert!(find_conflict_markers(b"<<<<<<<<<< not a marker\n").is_empty());
+    }
+}
@@ -0,0 +1,535 @@
+//! Hooks compiled into git-hooks, usable in `.hooks.yml` without any external repo, by naming
+//! them `builtin:<name>`.
+
+use std::path::{Path, PathBuf};
+
+use log::{debug, error, info};
+use serde::de::DeserializeOwned;
+
+use crate::context::RunContext;
+use crate::server::HOOKS_RUN_TRAILER;
+use crate::utils::matches;
+use crate::{git, Hook, HookEvent};
+
+mod branch;
+mod conventional_commit;
+mod large_files;
+mod merge_conflict;
+mod secrets;
+mod signoff;
+mod syntax;
+mod ticket;
+mod whitespace;
+
+/// Prefix of the names of the builtin hooks
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Builtin {
+    TrailingWhitespace,
+    EndOfFileFixer,
+    CheckMergeConflict,
+    CheckAddedLargeFiles,
+    DetectSecrets,
+    CheckYaml,
+    CheckJson,
+    CheckToml,
+    ConventionalCommit,
+    RequireSignoff,
+    BranchName,
+    NoCommitToBranch,
+    MarkHooksRun,
+    RequireTicket,
+}
+
+impl Builtin {
+    /// Returns the builtin designated by a hook name, such as `builtin:trailing-whitespace`
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        match name.strip_prefix(BUILTIN_PREFIX)? {
+            "trailing-whitespace" => Some(Builtin::TrailingWhitespace),
+            "end-of-file-fixer" => Some(Builtin::EndOfFileFixer),
+            "check-merge-conflict" => Some(Builtin::CheckMergeConflict),
+            "check-added-large-files" => Some(Builtin::CheckAddedLargeFiles),
+            "detect-secrets" => Some(Builtin::DetectSecrets),
+            "check-yaml" => Some(Builtin::CheckYaml),
+            "check-json" => Some(Builtin::CheckJson),
+            "check-toml" => Some(Builtin::CheckToml),
+            "conventional-commit" => Some(Builtin::ConventionalCommit),
+            "require-signoff" => Some(Builtin::RequireSignoff),
+            "branch-name" => Some(Builtin::BranchName),
+            "no-commit-to-branch" => Some(Builtin::NoCommitToBranch),
+            "mark-hooks-run" => Some(Builtin::MarkHooksRun),
+            "require-ticket" => Some(Builtin::RequireTicket),
+            _ => None,
+        }
+    }
+
+    /// Returns the id of the hook of pre-commit's own repo the builtin mirrors, if any
+    pub fn pre_commit_id(self) -> Option<&'static str> {
+        match self {
+            Builtin::TrailingWhitespace => Some("trailing-whitespace"),
+            Builtin::EndOfFileFixer => Some("end-of-file-fixer"),
+            Builtin::CheckMergeConflict => Some("check-merge-conflict"),
+            Builtin::CheckAddedLargeFiles => Some("check-added-large-files"),
+            Builtin::CheckYaml => Some("check-yaml"),
+            Builtin::CheckJson => Some("check-json"),
+            Builtin::CheckToml => Some("check-toml"),
+            Builtin::NoCommitToBranch => Some("no-commit-to-branch"),
+            _ => None,
+        }
+    }
+
+    /// Returns the events the builtin runs on when the hook has no `on_event`
+    pub fn default_events(self) -> Vec<HookEvent> {
+        match self {
+            Builtin::ConventionalCommit
+            | Builtin::RequireSignoff
+            | Builtin::MarkHooksRun
+            | Builtin::RequireTicket => vec![HookEvent::CommitMsg],
+            Builtin::BranchName => vec![HookEvent::PreCommit, HookEvent::PrePush],
+            _ => vec![HookEvent::PreCommit],
+        }
+    }
+
+    /// Returns the files the builtin applies to when the hook has no `on_file_regex`
+    fn default_file_regex(self) -> &'static str {
+        match self {
+            Builtin::CheckYaml => r"\.ya?ml$",
+            Builtin::CheckJson => r"\.json$",
+            Builtin::CheckToml => r"\.toml$",
+            _ => ".*",
+        }
+    }
+
+    /// Runs the builtin for the given hook configuration, on the files `ctx` is about: the staged
+    /// ones, or those changed in its range of commits.
+    pub fn run(self, hook: &Hook, ctx: &RunContext) -> anyhow::Result<()> {
+        let event = ctx
+            .event
+            .ok_or_else(|| anyhow::Error::msg("no event to run the builtin for"))?;
+        let args = &ctx.args;
+        let regexps = file_regexps(hook, self.default_file_regex());
+        let files = changed_files(ctx, &regexps)?;
+        match self {
+            Builtin::TrailingWhitespace => {
+                let fix = whitespace::fix_trailing_whitespace;
+                restage(ctx, &fix_files(&files, fix)?, fix)
+            }
+            Builtin::EndOfFileFixer => {
+                let fix = whitespace::fix_end_of_file;
+                restage(ctx, &fix_files(&files, fix)?, fix)
+            }
+            Builtin::CheckMergeConflict => check_contents(ctx, &git::root()?, &files, |content| {
+                merge_conflict::find_conflict_markers(content)
+                    .iter()
+                    .map(|line| format!("line {}: merge conflict marker", line))
+                    .collect()
+            }),
+            Builtin::CheckAddedLargeFiles => {
+                let added = match &ctx.range {
+                    Some(range) => git::range_files(&range.from, &range.to, "A")?,
+                    None => git::added_files()?,
+                };
+                let added: Vec<String> = added
+                    .into_iter()
+                    .filter(|f| matches(Path::new(f), &regexps))
+                    .collect();
+                large_files::check_added_large_files(
+                    &added,
+                    |file| match &ctx.range {
+                        Some(range) => git::size_at(&range.to, file),
+                        None => git::staged_size(file),
+                    },
+                    &settings(hook)?,
+                )
+            }
+            Builtin::DetectSecrets => {
+                let diff = match &ctx.range {
+                    Some(range) => git::range_diff(&range.from, &range.to)?,
+                    None => git::staged_diff()?,
+                };
+                let lines: Vec<secrets::AddedLine> = secrets::added_lines(&diff)
+                    .into_iter()
+                    .filter(|l| matches(Path::new(&l.file), &regexps))
+                    .collect();
+                secrets::detect_secrets(&git::root()?, &lines, &settings(hook)?)
+            }
+            Builtin::CheckYaml => check_contents(ctx, &git::root()?, &files, syntax::check_yaml),
+            Builtin::CheckJson => check_contents(ctx, &git::root()?, &files, syntax::check_json),
+            Builtin::CheckToml => check_contents(ctx, &git::root()?, &files, syntax::check_toml),
+            Builtin::ConventionalCommit => {
+                let message = read_commit_message(event, args)?;
+                let problems = conventional_commit::check_message(&message, &settings(hook)?);
+                for problem in &problems {
+                    error!("{}", problem);
+                }
+                if problems.is_empty() {
+                    Ok(())
+                } else {
+                    Err(anyhow::Error::msg(
+                        "the commit message does not follow the conventional commits format",
+                    ))
+                }
+            }
+            Builtin::RequireSignoff => {
+                let message = read_commit_message(event, args)?;
+                let ident = git::committer_ident()?;
+                let (name, email) = signoff::parse_ident(&ident).ok_or_else(|| {
+                    anyhow::Error::msg(format!("could not parse committer identity {}", ident))
+                })?;
+                if signoff::has_signoff(&message, &name, &email) {
+                    Ok(())
+                } else {
+                    error!(
+                        "missing \"Signed-off-by: {} <{}>\" trailer. Use git commit -s to add it",
+                        name, email
+                    );
+                    Err(anyhow::Error::msg("the commit is not signed off"))
+                }
+            }
+            Builtin::BranchName => match git::current_branch()? {
+                Some(branch) => branch::check_branch_name(&branch, &settings(hook)?),
+                None => {
+                    debug!("HEAD is detached, not checking the branch name");
+                    Ok(())
+                }
+            },
+            Builtin::NoCommitToBranch => match git::current_branch()? {
+                Some(branch) => branch::check_not_protected(&branch, &settings(hook)?),
+                None => Ok(()),
+            },
+            Builtin::RequireTicket => {
+                let message = read_commit_message(event, args)?;
+                ticket::check_ticket(
+                    &message,
+                    git::current_branch()?.as_deref(),
+                    &settings(hook)?,
+                )
+            }
+            // `git commit --no-verify` skips it, leaving the commit without the trailer
+            Builtin::MarkHooksRun => {
+                git::add_trailer(commit_message_file(event, args)?, HOOKS_RUN_TRAILER)
+            }
+        }
+    }
+}
+
+/// Returns the commit message file git gives to the commit-msg hook
+fn commit_message_file(event: HookEvent, args: &[String]) -> anyhow::Result<&str> {
+    if event != HookEvent::CommitMsg {
+        return Err(anyhow::Error::msg(format!(
+            "can only check commit messages on commit-msg, not on {}",
+            event.to_kebab_case()
+        )));
+    }
+    args.first().map(String::as_str).ok_or_else(|| {
+        anyhow::Error::msg("no commit message file given. Were the hooks installed with an older git-hooks? Try running git-hooks init")
+    })
+}
+
+/// Reads the commit message file git gives to the commit-msg hook
+fn read_commit_message(event: HookEvent, args: &[String]) -> anyhow::Result<String> {
+    Ok(std::fs::read_to_string(commit_message_file(event, args)?)?)
+}
+
+/// Reads the builtin-specific settings of the hook, falling back to the defaults
+fn settings<T: DeserializeOwned + Default>(hook: &Hook) -> anyhow::Result<T> {
+    match &hook.settings {
+        Some(value) => serde_yaml::from_value(value.clone())
+            .map_err(|e| anyhow::Error::msg(format!("invalid settings for {}: {}", hook.name, e))),
+        None => Ok(T::default()),
+    }
+}
+
+fn file_regexps(hook: &Hook, default: &str) -> Vec<String> {
+    hook.on_file_regex
+        .clone()
+        .unwrap_or_else(|| vec![default.to_string()])
+}
+
+/// Returns the absolute paths of the files `ctx` is about matching `regexps`
+fn changed_files(ctx: &RunContext, regexps: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let root = git::root()?;
+    Ok(ctx
+        .changed_files
+        .iter()
+        .map(|f| Path::new(&root).join(f))
+        .filter(|p| matches(p, regexps))
+        .collect())
+}
+
+/// Applies `fix` on the content of each text file, and writes back the files it changed.
+/// Returns the changed files.
+fn fix_files(files: &[PathBuf], fix: fn(&[u8]) -> Option<Vec<u8>>) -> anyhow::Result<Vec<PathBuf>> {
+    let mut fixed = Vec::new();
+    for file in files {
+        let content = std::fs::read(file)?;
+        if is_binary(&content) {
+            debug!("skipping binary file {}", file.display());
+            continue;
+        }
+        if let Some(new_content) = fix(&content) {
+            info!("Fixing {}", file.display());
+            std::fs::write(file, new_content)?;
+            fixed.push(file.clone());
+        }
+    }
+    Ok(fixed)
+}
+
+/// Returns the contents of `file`, relative to the repository at `root`, that `ctx` is about, with
+/// the name to report each by: its staged content, its content at the end of the range of commits,
+/// or without a worktree, in each of the pushed refs still holding it
+fn file_versions(
+    ctx: &RunContext,
+    root: &str,
+    file: &str,
+) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    if let Some(range) = &ctx.range {
+        let content = git::content_at(root, &range.to, file)?;
+        return Ok(content.map(|c| (file.to_string(), c)).into_iter().collect());
+    }
+    if ctx.bare {
+        let mut versions = Vec::new();
+        for update in ctx.updates.iter().filter(|u| !u.is_deletion()) {
+            if let Some(content) = git::content_at(root, &update.new, file)? {
+                versions.push((format!("{} of {}", file, update.refname), content));
+            }
+        }
+        return Ok(versions);
+    }
+    Ok(vec![(file.to_string(), git::staged_content(root, file)?)])
+}
+
+/// Applies `check` on the content `ctx` is about of each text file, rather than on the worktree,
+/// which may hold something else. `check` returns the problems it found. Fails if any problem was
+/// found.
+fn check_contents(
+    ctx: &RunContext,
+    root: &str,
+    files: &[PathBuf],
+    check: fn(&[u8]) -> Vec<String>,
+) -> anyhow::Result<()> {
+    let mut failed = false;
+    for file in files {
+        let file = file
+            .strip_prefix(root)
+            .unwrap_or(file)
+            .display()
+            .to_string();
+        for (name, content) in file_versions(ctx, root, &file)? {
+            if is_binary(&content) {
+                debug!("skipping binary file {}", name);
+                continue;
+            }
+            for problem in check(&content) {
+                error!("{}: {}", name, problem);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        Err(anyhow::Error::msg("some files did not pass the check"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Applies `fix` on the staged content of the `files` a builtin fixed in the worktree, rather than
+/// staging them whole, which would commit their unstaged changes too, or fails in strict mode
+fn restage(
+    ctx: &RunContext,
+    files: &[PathBuf],
+    fix: fn(&[u8]) -> Option<Vec<u8>>,
+) -> anyhow::Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    if ctx.strict {
+        let files: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        return Err(anyhow::Error::msg(format!(
+            "fixed {}: stage the files again",
+            files.join(", ")
+        )));
+    }
+    let root = git::root()?;
+    for file in files {
+        let file = file
+            .strip_prefix(&root)
+            .unwrap_or(file)
+            .display()
+            .to_string();
+        if let Some(fixed) = fix(&git::staged_content(&root, &file)?) {
+            git::stage_content(&root, &file, &fixed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Same heuristic as git: a file containing a NUL byte is binary
+fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|b| *b == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::process::Command;
+
+    use tempdir::TempDir;
+
+    use crate::builtins::{check_contents, is_binary, merge_conflict, syntax, Builtin};
+    use crate::context::{RefRange, RunContext};
+    use crate::server::RefUpdate;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            Builtin::from_name("builtin:trailing-whitespace"),
+            Some(Builtin::TrailingWhitespace)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:end-of-file-fixer"),
+            Some(Builtin::EndOfFileFixer)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:check-merge-conflict"),
+            Some(Builtin::CheckMergeConflict)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:check-added-large-files"),
+            Some(Builtin::CheckAddedLargeFiles)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:detect-secrets"),
+            Some(Builtin::DetectSecrets)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:check-toml"),
+            Some(Builtin::CheckToml)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:require-signoff"),
+            Some(Builtin::RequireSignoff)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:branch-name"),
+            Some(Builtin::BranchName)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:no-commit-to-branch"),
+            Some(Builtin::NoCommitToBranch)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:mark-hooks-run"),
+            Some(Builtin::MarkHooksRun)
+        );
+        assert_eq!(
+            Builtin::from_name("builtin:require-ticket"),
+            Some(Builtin::RequireTicket)
+        );
+        assert_eq!(Builtin::from_name("trailing-whitespace"), None);
+        assert_eq!(Builtin::from_name("builtin:nope"), None);
+    }
+
+    #[test]
+    fn test_check_contents() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let root = dir.path().display().to_string();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(&root)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {:?} failed", args);
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        let check: fn(&[u8]) -> Vec<String> = |content| {
+            merge_conflict::find_conflict_markers(content)
+                .iter()
+                .map(|line| format!("line {}", line))
+                .collect()
+        };
+        let conflicted = "<<<<<<< HEAD\na\n=======\nb\n>>>>>>> feature\n";
+        let file = dir.path().join("a.txt");
+        let files = [file.clone()];
+        git(&["init", "-q"]);
+        fs::write(&file, conflicted).unwrap();
+        git(&["add", "a.txt"]);
+        git(&["commit", "-q", "-m", "conflicted"]);
+        let conflicted_commit = git(&["rev-parse", "HEAD"]);
+        fs::write(&file, "a\n").unwrap();
+        git(&["commit", "-q", "-a", "-m", "resolved"]);
+
+        // the staged content, whatever the worktree holds
+        let staged = RunContext::default();
+        fs::write(&file, conflicted).unwrap();
+        assert!(check_contents(&staged, &root, &files, check).is_ok());
+        git(&["add", "a.txt"]);
+        fs::remove_file(&file).unwrap();
+        assert!(check_contents(&staged, &root, &files, check).is_err());
+        git(&["checkout", "-q", "HEAD", "--", "a.txt"]);
+
+        // the end of the range
+        let mut range = RunContext {
+            range: Some(RefRange {
+                from: "HEAD~1".to_string(),
+                to: "HEAD".to_string(),
+            }),
+            ..Default::default()
+        };
+        fs::write(&file, conflicted).unwrap();
+        assert!(check_contents(&range, &root, &files, check).is_ok());
+        range.range = Some(RefRange {
+            from: "HEAD~1".to_string(),
+            to: conflicted_commit.clone(),
+        });
+        fs::write(&file, "a\n").unwrap();
+        assert!(check_contents(&range, &root, &files, check).is_err());
+
+        // the pushed refs, without a worktree
+        let pushed = |new: &str| RunContext {
+            bare: true,
+            updates: vec![RefUpdate {
+                old: "0".repeat(40),
+                new: new.to_string(),
+                refname: "refs/heads/main".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(check_contents(&pushed("HEAD"), &root, &files, check).is_ok());
+        assert!(check_contents(&pushed(&conflicted_commit), &root, &files, check).is_err());
+        // nor when the pushed ref does not hold it
+        git(&["rm", "-q", "a.txt"]);
+        git(&["commit", "-q", "-m", "removed"]);
+        assert!(check_contents(&pushed("HEAD"), &root, &files, check).is_ok());
+
+        // the syntax checks alike
+        let config = dir.path().join("config.json");
+        fs::write(&config, "{\"valid\": true}\n").unwrap();
+        git(&["add", "config.json"]);
+        fs::write(&config, "{").unwrap();
+        let configs = [config.clone()];
+        assert!(check_contents(&staged, &root, &configs, syntax::check_json).is_ok());
+        fs::write(&config, "{\"valid\": true}\n").unwrap();
+        git(&["add", "config.json"]);
+        git(&["commit", "-q", "-m", "config"]);
+        fs::write(&config, "{").unwrap();
+        git(&["commit", "-q", "-a", "-m", "broken"]);
+        fs::remove_file(&config).unwrap();
+        range.range = Some(RefRange {
+            from: "HEAD~1".to_string(),
+            to: "HEAD".to_string(),
+        });
+        assert!(check_contents(&range, &root, &configs, syntax::check_json).is_err());
+        assert!(check_contents(&pushed("HEAD~1"), &root, &configs, syntax::check_json).is_ok());
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"\x7fELF\x00\x01"));
+        assert!(!is_binary(b"fn main() {}\n"));
+    }
+}
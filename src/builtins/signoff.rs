@@ -0,0 +1,46 @@
+use crate::builtins::conventional_commit::message_lines;
+
+/// Returns whether the message has a `Signed-off-by:` trailer for the given identity
+pub fn has_signoff(message: &str, name: &str, email: &str) -> bool {
+    let expected = format!("Signed-off-by: {} <{}>", name, email);
+    message_lines(message)
+        .iter()
+        .any(|l| l.trim_end().eq_ignore_ascii_case(&expected))
+}
+
+/// Parses the output of `git var GIT_COMMITTER_IDENT`, eg.
+/// `Jane Doe <jane@example.com> 1600000000 +0200`, into a name & an email
+pub fn parse_ident(ident: &str) -> Option<(String, String)> {
+    let open = ident.find('<')?;
+    let close = ident[open..].find('>')? + open;
+    Some((
+        ident[..open].trim().to_string(),
+        ident[open + 1..close].to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::signoff::{has_signoff, parse_ident};
+
+    #[test]
+    fn test_parse_ident() {
+        assert_eq!(
+            parse_ident("Jane Doe <jane@example.com> 1600000000 +0200"),
+            Some(("Jane Doe".to_string(), "jane@example.com".to_string()))
+        );
+        assert_eq!(parse_ident("nobody"), None);
+    }
+
+    #[test]
+    fn test_has_signoff() {
+        let message = "feat: a\n\nSigned-off-by: Jane Doe <jane@example.com>\n";
+        assert!(has_signoff(message, "Jane Doe", "jane@example.com"));
+        assert!(!has_signoff(message, "John Doe", "john@example.com"));
+        assert!(!has_signoff(
+            "feat: a\n# Signed-off-by: Jane Doe <jane@example.com>\n",
+            "Jane Doe",
+            "jane@example.com"
+        ));
+    }
+}
@@ -0,0 +1,62 @@
+use std::str;
+
+/// Returns a description of the syntax error of a YAML file, if any.
+/// Files containing several documents are supported.
+pub fn check_yaml(content: &[u8]) -> Vec<String> {
+    let content = match str::from_utf8(content) {
+        Ok(content) => content,
+        Err(e) => return vec![e.to_string()],
+    };
+    for document in serde_yaml::Deserializer::from_str(content) {
+        if let Err(e) = <serde_yaml::Value as serde::Deserialize>::deserialize(document) {
+            return vec![e.to_string()];
+        }
+    }
+    vec![]
+}
+
+/// Returns a description of the syntax error of a JSON file, if any
+pub fn check_json(content: &[u8]) -> Vec<String> {
+    match serde_json::from_slice::<serde_json::Value>(content) {
+        Ok(_) => vec![],
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+/// Returns a description of the syntax error of a TOML file, if any
+pub fn check_toml(content: &[u8]) -> Vec<String> {
+    match toml::from_slice::<toml::Value>(content) {
+        Ok(_) => vec![],
+        Err(e) => vec![e.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::syntax::{check_json, check_toml, check_yaml};
+
+    #[test]
+    fn test_check_yaml() {
+        assert!(check_yaml(b"a: 1\nb: [1, 2]\n").is_empty());
+        assert!(check_yaml(b"a: 1\n---\nb: 2\n").is_empty());
+        let errors = check_yaml(b"a: 1\nb: [1, 2\n");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("line"));
+    }
+
+    #[test]
+    fn test_check_json() {
+        assert!(check_json(b"{\"a\": [1, 2]}").is_empty());
+        let errors = check_json(b"{\n\"a\": [1, 2}\n");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("line 2"));
+    }
+
+    #[test]
+    fn test_check_toml() {
+        assert!(check_toml(b"[package]\nname = \"a\"\n").is_empty());
+        let errors = check_toml(b"[package]\nname = \n");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("line 2"));
+    }
+}
@@ -0,0 +1,112 @@
+use log::error;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::builtins::conventional_commit::message_lines;
+
+/// Where a ticket reference may be
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TicketSource {
+    /// The commit message, its subject or its body
+    Message,
+    /// The name of the current branch, eg. `feature/PROJ-1234-login`
+    Branch,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct TicketSettings {
+    /// Regex the ticket references match
+    pattern: String,
+    /// Where the reference is looked for: it may be in any of them
+    sources: Vec<TicketSource>,
+    /// Regexes of the subjects of the commits needing no reference, such as merges & reverts
+    allow: Vec<String>,
+}
+
+impl Default for TicketSettings {
+    fn default() -> Self {
+        TicketSettings {
+            pattern: r"\b[A-Z][A-Z0-9]+-[0-9]+\b".to_string(),
+            sources: vec![TicketSource::Message, TicketSource::Branch],
+            allow: ["^Merge ", "^Revert ", "^fixup! ", "^squash! ", "^amend! "]
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Fails if neither the commit `message`, nor the name of the current `branch`, references a
+/// ticket, among the configured sources
+pub fn check_ticket(
+    message: &str,
+    branch: Option<&str>,
+    settings: &TicketSettings,
+) -> anyhow::Result<()> {
+    let invalid = |pattern: &str, e: regex::Error| {
+        anyhow::Error::msg(format!("invalid ticket pattern {}: {}", pattern, e))
+    };
+    let lines = message_lines(message);
+    let subject = lines
+        .iter()
+        .find(|l| !l.trim().is_empty())
+        .copied()
+        .unwrap_or("");
+    for allowed in &settings.allow {
+        if Regex::new(allowed)
+            .map_err(|e| invalid(allowed, e))?
+            .is_match(subject)
+        {
+            return Ok(());
+        }
+    }
+    let re = Regex::new(&settings.pattern).map_err(|e| invalid(&settings.pattern, e))?;
+    let found = settings.sources.iter().any(|source| match source {
+        TicketSource::Message => lines.iter().any(|l| re.is_match(l)),
+        TicketSource::Branch => branch.is_some_and(|b| re.is_match(b)),
+    });
+    if found {
+        return Ok(());
+    }
+    let sources: Vec<&str> = settings
+        .sources
+        .iter()
+        .map(|source| match source {
+            TicketSource::Message => "the commit message",
+            TicketSource::Branch => "the branch name",
+        })
+        .collect();
+    error!(
+        "no ticket reference matching {} in {}. Mention the ticket, eg. PROJ-1234, in the commit message",
+        settings.pattern,
+        sources.join(" nor in ")
+    );
+    Err(anyhow::Error::msg("the commit references no ticket"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::ticket::{check_ticket, TicketSettings};
+
+    #[test]
+    fn test_check_ticket() {
+        let settings = TicketSettings::default();
+        assert!(check_ticket("PROJ-1234: fix login\n", None, &settings).is_ok());
+        assert!(check_ticket("fix login\n\nRefs: OPS-7\n", None, &settings).is_ok());
+        assert!(check_ticket("fix login\n", Some("feature/PROJ-12-login"), &settings).is_ok());
+        assert!(check_ticket("fix login\n", Some("feature/login"), &settings).is_err());
+        assert!(check_ticket("fix login\n# on PROJ-1\n", None, &settings).is_err());
+        assert!(check_ticket("proj-1: lowercase\n", None, &settings).is_err());
+        assert!(check_ticket("Merge branch 'main'\n", None, &settings).is_ok());
+        assert!(check_ticket("fixup! fix login\n", None, &settings).is_ok());
+        assert!(check_ticket("Revert \"fix login\"\n", None, &settings).is_ok());
+
+        let settings: TicketSettings =
+            serde_yaml::from_str("pattern: '#[0-9]+'\nsources: [message]\nallow: []").unwrap();
+        assert!(check_ticket("fix login (#42)\n", None, &settings).is_ok());
+        assert!(check_ticket("fix login\n", Some("fix/#42"), &settings).is_err());
+        assert!(check_ticket("Merge branch 'main'\n", None, &settings).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+/// Removes the spaces & tabs at the end of each line.
+/// Returns `None` if there is nothing to fix.
+pub fn fix_trailing_whitespace(content: &[u8]) -> Option<Vec<u8>> {
+    let mut fixed = Vec::with_capacity(content.len());
+    for line in content.split_inclusive(|b| *b == b'\n') {
+        let (body, eol): (&[u8], &[u8]) = if line.ends_with(b"\r\n") {
+            (&line[..line.len() - 2], b"\r\n")
+        } else if line.ends_with(b"\n") {
+            (&line[..line.len() - 1], b"\n")
+        } else {
+            (line, b"")
+        };
+        let end = body
+            .iter()
+            .rposition(|b| *b != b' ' && *b != b'\t')
+            .map_or(0, |i| i + 1);
+        fixed.extend_from_slice(&body[..end]);
+        fixed.extend_from_slice(eol);
+    }
+    if fixed == content {
+        None
+    } else {
+        Some(fixed)
+    }
+}
+
+/// Makes sure a non-empty file ends with exactly one newline.
+/// Returns `None` if there is nothing to fix.
+pub fn fix_end_of_file(content: &[u8]) -> Option<Vec<u8>> {
+    let end = content
+        .iter()
+        .rposition(|b| *b != b'\n' && *b != b'\r')
+        .map_or(0, |i| i + 1);
+    let mut fixed = content[..end].to_vec();
+    if !fixed.is_empty() {
+        // keep the line ending style of the file
+        if content[end..].starts_with(b"\r\n") {
+            fixed.extend_from_slice(b"\r\n");
+        } else {
+            fixed.push(b'\n');
+        }
+    }
+    if fixed == content {
+        None
+    } else {
+        Some(fixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::whitespace::{fix_end_of_file, fix_trailing_whitespace};
+
+    #[test]
+    fn test_fix_trailing_whitespace() {
+        assert_eq!(fix_trailing_whitespace(b"a\nb\n"), None);
+        assert_eq!(
+            fix_trailing_whitespace(b"a  \nb\t\r\n  \nc "),
+            Some(b"a\nb\r\n\nc".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_fix_end_of_file() {
+        assert_eq!(fix_end_of_file(b"a\n"), None);
+        assert_eq!(fix_end_of_file(b""), None);
+        assert_eq!(fix_end_of_file(b"a"), Some(b"a\n".to_vec()));
+        assert_eq!(fix_end_of_file(b"a\n\n\n"), Some(b"a\n".to_vec()));
+        assert_eq!(fix_end_of_file(b"a\r\n\r\n"), Some(b"a\r\n".to_vec()));
+        assert_eq!(fix_end_of_file(b"\n\n"), Some(b"".to_vec()));
+    }
+}
@@ -0,0 +1,259 @@
+//! The checksums of the programs the hooks run from their repos: the same revision of a repo, set
+//! up once, always runs the same programs, so that one changing means its checkout was tampered
+//! with.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::GitHooksError;
+use crate::utils::user_config_dir;
+use crate::{git, vendor};
+
+/// Where the checksums are kept, relative to the user configuration directory, where the hooks do
+/// not write unlike in the repositories
+const CHECKSUMS_LOCATION: &str = "checksums.yml";
+
+/// The checksum of a program the first time it ran, at a revision of its repo
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+struct Checksum {
+    /// The directory the repo is checked out in
+    repo: String,
+    revision: String,
+    /// The program, relative to the checkout
+    program: String,
+    sha256: String,
+}
+
+/// A setup which ran for a repo, for the programs it built or installed to be expected to change.
+/// The checkout records them as well, but there its hooks may write.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+struct Setup {
+    /// The directory the repo is checked out in
+    repo: String,
+    /// The key of the setup, starting with the revision of the repo it ran for
+    key: String,
+}
+
+/// The checksums of the programs the hooks ran from their repos
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct Checksums {
+    programs: Vec<Checksum>,
+    setups: Vec<Setup>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Checksums {
+    /// Loads the checksums of the user's
+    pub fn load() -> anyhow::Result<Checksums> {
+        Checksums::from_path(user_config_dir()?.join(CHECKSUMS_LOCATION))
+    }
+
+    /// Loads the checksums from `path`. A missing file holds none.
+    fn from_path(path: PathBuf) -> anyhow::Result<Checksums> {
+        let mut checksums: Checksums = if path.exists() {
+            serde_yaml::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Checksums::default()
+        };
+        checksums.path = path;
+        Ok(checksums)
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Records the checksum of `program` of `repo`, at `revision`, unless it ran before. The
+    /// checksums of the other revisions of the repo are forgotten. Returns the checksum it had
+    /// when it first ran, if it changed since.
+    fn record(
+        &mut self,
+        repo: &str,
+        revision: &str,
+        program: &str,
+        sha256: &str,
+    ) -> Option<String> {
+        self.programs
+            .retain(|c| c.repo != repo || c.revision == revision);
+        match self
+            .programs
+            .iter()
+            .find(|c| c.repo == repo && c.program == program)
+        {
+            Some(known) if known.sha256 == sha256 => None,
+            Some(known) => Some(known.sha256.clone()),
+            None => {
+                self.programs.push(Checksum {
+                    repo: repo.to_string(),
+                    revision: revision.to_string(),
+                    program: program.to_string(),
+                    sha256: sha256.to_string(),
+                });
+                None
+            }
+        }
+    }
+}
+
+/// Records that the setup `key` ran for the hook repo checked out in `repo_dir`. The setups which
+/// ran for the other revisions of the repo are forgotten.
+pub fn record_setup(repo_dir: &str, key: &str) -> anyhow::Result<()> {
+    let mut checksums = Checksums::load()?;
+    checksums.add_setup(repo_dir, key);
+    checksums.save()
+}
+
+impl Checksums {
+    fn add_setup(&mut self, repo: &str, key: &str) {
+        let revision = key.split(' ').next().unwrap_or_default();
+        self.setups
+            .retain(|s| s.repo != repo || s.key.split(' ').next() == Some(revision));
+        if !self.setups.iter().any(|s| s.repo == repo && s.key == key) {
+            self.setups.push(Setup {
+                repo: repo.to_string(),
+                key: key.to_string(),
+            });
+        }
+    }
+
+    /// Returns what the programs of the hook repo checked out in `dir` stay the same for: its
+    /// commit, or the vendored one it is a copy of, and the setups which ran for it, which may
+    /// build or install them again
+    fn revision(&self, dir: &str) -> anyhow::Result<String> {
+        let commit = git::head_if_any(dir)?
+            .or_else(|| vendor::checked_out_commit(dir))
+            .unwrap_or_default();
+        let setups: Vec<&str> = self
+            .setups
+            .iter()
+            .filter(|s| s.repo == dir)
+            .map(|s| s.key.as_str())
+            .collect();
+        Ok(format!(
+            "{} {:x}",
+            commit,
+            Sha256::digest(setups.join("\n"))
+        ))
+    }
+}
+
+/// Checks `program`, which `hook` runs from its repo checked out in `repo_dir`, against the
+/// checksum it had when it first ran at this revision of the repo. A program which changed is
+/// warned about, or fails in `strict` mode.
+pub fn verify(hook: &str, repo_dir: &Path, program: &Path, strict: bool) -> anyhow::Result<()> {
+    let relative = program.strip_prefix(repo_dir).unwrap_or(program);
+    let repo = repo_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let sha256 = format!("{:x}", Sha256::digest(fs::read(program)?));
+    let mut checksums = Checksums::load()?;
+    let revision = checksums.revision(&repo_dir.display().to_string())?;
+    let previous = checksums.record(
+        &repo_dir.display().to_string(),
+        &revision,
+        &relative.display().to_string(),
+        &sha256,
+    );
+    checksums.save()?;
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            debug!(
+                "{} of {} has the checksum {}",
+                relative.display(),
+                repo,
+                sha256
+            );
+            return Ok(());
+        }
+    };
+    let message = format!(
+        "{}: {} of {} changed since it first ran at this revision of the repo, from sha256 {} to {}: its checkout may have been tampered with",
+        hook,
+        relative.display(),
+        repo,
+        previous,
+        sha256
+    );
+    if strict {
+        return Err(GitHooksError::Config(format!(
+            "{}. Remove {} for it to be checked out again",
+            message,
+            repo_dir.display()
+        ))
+        .into());
+    }
+    // shown like the tampered checkouts, which it likely is
+    error!("{}", message);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    use tempdir::TempDir;
+
+    use crate::checksums::Checksums;
+    use crate::setup::SetupMarker;
+
+    #[test]
+    fn test_record() {
+        let mut checksums = Checksums::from_path(PathBuf::from("/nonexistent")).unwrap();
+        assert_eq!(checksums.record("hooks", "abc 1", "check.sh", "01"), None);
+        assert_eq!(checksums.record("hooks", "abc 1", "check.sh", "01"), None);
+        assert_eq!(checksums.record("other", "def 1", "check.sh", "02"), None);
+        // the first checksum is kept, for the change to be reported until it is looked into
+        assert_eq!(
+            checksums.record("hooks", "abc 1", "check.sh", "ff"),
+            Some("01".to_string())
+        );
+        assert_eq!(
+            checksums.record("hooks", "abc 1", "check.sh", "ff"),
+            Some("01".to_string())
+        );
+        // another revision, eg. once pulled or set up again, runs other programs
+        assert_eq!(checksums.record("hooks", "abc 2", "check.sh", "ff"), None);
+        assert_eq!(checksums.programs.len(), 2);
+    }
+
+    #[test]
+    fn test_revision() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let clone_dir = dir.path().display().to_string();
+        Command::new("git")
+            .args(["init", "-q", &clone_dir])
+            .status()
+            .unwrap();
+        let mut checksums = Checksums::from_path(PathBuf::from("/nonexistent")).unwrap();
+        let mut marker = SetupMarker::load(&clone_dir).unwrap();
+        let key = marker.key(&clone_dir, "setup.sh").unwrap();
+        checksums.add_setup(&clone_dir, &key);
+        marker.mark_done(key);
+        marker.save().unwrap();
+        let set_up = checksums.revision(&clone_dir).unwrap();
+        // the setups the checkout records, eg. appended to by a hook, are not the ones which ran
+        marker.mark_done(marker.key(&clone_dir, "build.sh").unwrap());
+        marker.save().unwrap();
+        assert_eq!(checksums.revision(&clone_dir).unwrap(), set_up);
+        // setting it up again is no new setup
+        checksums.add_setup(&clone_dir, &marker.key(&clone_dir, "setup.sh").unwrap());
+        assert_eq!(checksums.revision(&clone_dir).unwrap(), set_up);
+        checksums.add_setup(&clone_dir, &marker.key(&clone_dir, "build.sh").unwrap());
+        assert_ne!(checksums.revision(&clone_dir).unwrap(), set_up);
+        // nor are the setups of another revision
+        checksums.add_setup(&clone_dir, "abc - setup.sh");
+        assert_eq!(checksums.setups.len(), 1);
+    }
+}
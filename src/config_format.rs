@@ -0,0 +1,232 @@
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The default configuration files, looked for in this order
+pub const DEFAULT_CONFIG_FILES: &[&str] =
+    &[".hooks.yml", ".hooks.yaml", ".hooks.toml", ".hooks.json"];
+
+/// The formats a configuration file may be written in
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Guesses the format of a file from its extension, YAML being the default
+    pub fn from_path<P: AsRef<Path>>(path: P) -> ConfigFormat {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("toml") => ConfigFormat::Toml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+
+    /// Guesses the format of a file from its extension, then from its content: YAML files
+    /// holding a JSON object are read as JSON.
+    pub fn detect<P: AsRef<Path>>(path: P, content: &str) -> ConfigFormat {
+        match ConfigFormat::from_path(path) {
+            ConfigFormat::Yaml if content.trim_start().starts_with('{') => ConfigFormat::Json,
+            format => format,
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> anyhow::Result<T> {
+        Ok(match self {
+            ConfigFormat::Yaml => serde_yaml::from_str(content)?,
+            ConfigFormat::Toml => toml::from_str(content)?,
+            ConfigFormat::Json => serde_json::from_str(content)?,
+        })
+    }
+}
+
+/// Returns the configuration file named `name` in `dir`, whatever its format, if any
+pub fn find_config_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    ["yml", "yaml", "toml", "json"]
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|p| p.is_file())
+}
+
+/// Returns the default configuration file of `dir`, if it has one
+pub fn default_config_file_in(dir: &Path) -> Option<PathBuf> {
+    DEFAULT_CONFIG_FILES
+        .iter()
+        .map(|f| dir.join(f))
+        .find(|p| p.is_file())
+}
+
+/// Writes `value` as YAML, leaving out the unset fields
+pub fn to_yaml<T: Serialize>(value: &T) -> anyhow::Result<String> {
+    let mut value = serde_yaml::to_value(value)?;
+    strip_nulls(&mut value);
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+/// Writes the configuration `value` as YAML for a file meant to be edited, leaving out the unset
+/// fields as well as the empty lists & maps
+pub fn to_config_yaml<T: Serialize>(value: &T) -> anyhow::Result<String> {
+    let mut value = serde_yaml::to_value(value)?;
+    strip_nulls(&mut value);
+    strip_empty(&mut value);
+    Ok(serde_yaml::to_string(&value)?)
+}
+
+fn strip_nulls(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            // rebuilt rather than removed from, which would shuffle the keys
+            *mapping = std::mem::take(mapping)
+                .into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, mut v)| {
+                    strip_nulls(&mut v);
+                    (k, v)
+                })
+                .collect();
+        }
+        serde_yaml::Value::Sequence(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+fn strip_empty(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            *mapping = std::mem::take(mapping)
+                .into_iter()
+                .map(|(k, mut v)| {
+                    strip_empty(&mut v);
+                    (k, v)
+                })
+                .filter(|(_, v)| match v {
+                    serde_yaml::Value::Mapping(m) => !m.is_empty(),
+                    serde_yaml::Value::Sequence(s) => !s.is_empty(),
+                    _ => true,
+                })
+                .collect();
+        }
+        serde_yaml::Value::Sequence(values) => values.iter_mut().for_each(strip_empty),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config_format::{find_config_file, to_config_yaml, to_yaml, ConfigFormat};
+    use crate::{Action, HookConfig, HookEvent};
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(ConfigFormat::from_path(".hooks.toml"), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path(".hooks.yml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("hooks"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path(".hooks.json"), ConfigFormat::Json);
+        assert_eq!(
+            ConfigFormat::detect(".hooks.yml", "\n {\"repos\": []}"),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::detect(".hooks.yml", "repos: []"),
+            ConfigFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn test_find_config_file() {
+        let dir = tempdir::TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        assert_eq!(find_config_file(dir.path(), "config"), None);
+        std::fs::write(dir.path().join("config.toml"), "").expect("could not write config");
+        assert_eq!(
+            find_config_file(dir.path(), "config"),
+            Some(dir.path().join("config.toml"))
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let conf: HookConfig = ConfigFormat::Json
+            .parse(
+                r#"{
+                    "repos": [{"url": "https://github.com/paulollivier/rust-hooks"}],
+                    "hooks": [{"name": "rustfmt", "action": ["cargo fmt", "cargo check"]}]
+                }"#,
+            )
+            .unwrap();
+        assert_eq!(conf.repos.len(), 1);
+        assert_eq!(
+            conf.hooks[0].action,
+            Some(Action::Sequence(vec![
+                "cargo fmt".to_string(),
+                "cargo check".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_toml_matches_yaml() {
+        let yaml: HookConfig = ConfigFormat::Yaml
+            .parse(
+                r#"
+repos:
+  - url: https://github.com/paulollivier/rust-hooks
+hooks:
+  - name: rustfmt
+    on_event: [pre-commit, pre-push]
+    action: "cargo fmt"
+    settings:
+      max_size_kb: 10
+"#,
+            )
+            .unwrap();
+        let toml: HookConfig = ConfigFormat::Toml
+            .parse(
+                r#"
+[[repos]]
+url = "https://github.com/paulollivier/rust-hooks"
+
+[[hooks]]
+name = "rustfmt"
+on_event = ["pre-commit", "pre-push"]
+action = "cargo fmt"
+settings = { max_size_kb = 10 }
+"#,
+            )
+            .unwrap();
+        assert_eq!(toml.repos[0].url, yaml.repos[0].url);
+        assert_eq!(toml.hooks[0].name, yaml.hooks[0].name);
+        assert_eq!(
+            toml.hooks[0].on_event,
+            Some(vec![HookEvent::PreCommit, HookEvent::PrePush])
+        );
+        assert_eq!(toml.hooks[0].on_event, yaml.hooks[0].on_event);
+        assert_eq!(
+            toml.hooks[0].action,
+            Some(Action::Single("cargo fmt".to_string()))
+        );
+        assert_eq!(toml.hooks[0].settings, yaml.hooks[0].settings);
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        let conf: HookConfig =
+            serde_yaml::from_str("hooks:\n- name: fmt\n  on_event: [pre-push]\n").unwrap();
+        assert_eq!(
+            to_yaml(&conf).unwrap(),
+            "---\ninclude: []\nmirrors: {}\ndisable: []\nrepos: []\nhooks:\n  - name: fmt\n    on_event:\n      - pre-push\n"
+        );
+    }
+
+    #[test]
+    fn test_to_config_yaml() {
+        let conf: HookConfig =
+            serde_yaml::from_str("repos:\n- url: https://example.com/hooks\n").unwrap();
+        assert_eq!(
+            to_config_yaml(&conf).unwrap(),
+            "---\nrepos:\n  - url: \"https://example.com/hooks\"\n"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::env;
+use std::path::Path;
+
+/// Where the repository is mounted inside the container
+const CONTAINER_SRC_DIR: &str = "/src";
+/// Where the hook repository is mounted inside the container
+const CONTAINER_HOOK_REPO_DIR: &str = "/hook-repo";
+
+/// Returns the container runtime to use, `docker` unless `$GIT_HOOKS_CONTAINER_RUNTIME` says
+/// otherwise (eg. `podman`)
+fn runtime() -> String {
+    env::var("GIT_HOOKS_CONTAINER_RUNTIME").unwrap_or_else(|_| "docker".to_string())
+}
+
+/// Translates a path on the host to its location inside the container, or the WASM sandbox.
+/// Paths outside of the repository are left untouched.
+pub fn translate_arg(arg: &str, root: &str) -> String {
+    match Path::new(arg).strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => CONTAINER_SRC_DIR.to_string(),
+        Ok(relative) => format!("{}/{}", CONTAINER_SRC_DIR, relative.display()),
+        Err(_) => arg.to_string(),
+    }
+}
+
+/// Returns where `path`, on the host, is inside the container: under the hook repository's mount
+/// for its files, else as `translate_arg` does
+fn translate_path(path: &str, root: &str, hook_repo_path: &str) -> String {
+    match Path::new(path).strip_prefix(hook_repo_path) {
+        Ok(relative) if relative.as_os_str().is_empty() => CONTAINER_HOOK_REPO_DIR.to_string(),
+        Ok(relative) => format!("{}/{}", CONTAINER_HOOK_REPO_DIR, relative.display()),
+        Err(_) => translate_arg(path, root),
+    }
+}
+
+/// Returns the program to run inside the container for `cmd`: the scripts of the hook repository,
+/// which the hooks name as they are in the `PATH` on the host, run from its mount, unlike the
+/// programs of the image
+fn translate_program(cmd: &str, root: &str, hook_repo_path: &str) -> String {
+    if !cmd.contains('/') && Path::new(hook_repo_path).join(cmd).is_file() {
+        return format!("{}/{}", CONTAINER_HOOK_REPO_DIR, cmd);
+    }
+    translate_path(cmd, root, hook_repo_path)
+}
+
+/// Wraps `cmd` & `args` in a command running them inside `image`, with the repository at `root`
+/// mounted in the container's working directory, and the hook repository mounted read-only, its
+/// scripts run from there.
+/// The command runs as the owner of the repository, so that files it writes are not owned by root.
+/// `no_network` runs the container without network access.
+pub fn wrap_command(
+    image: &str,
+    root: &str,
+    hook_repo_path: &str,
+    cmd: &str,
+    args: &[String],
+    no_network: bool,
+) -> anyhow::Result<(String, Vec<String>)> {
+    let mut container_args = vec!["run".to_string(), "--rm".to_string()];
+    if no_network {
+        container_args.extend(["--network".to_string(), "none".to_string()]);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let meta = Path::new(root).metadata()?;
+        container_args.push("--user".to_string());
+        container_args.push(format!("{}:{}", meta.uid(), meta.gid()));
+    }
+    container_args.extend([
+        "--volume".to_string(),
+        format!("{}:{}", root, CONTAINER_SRC_DIR),
+        "--volume".to_string(),
+        format!("{}:{}:ro", hook_repo_path, CONTAINER_HOOK_REPO_DIR),
+        "--workdir".to_string(),
+        CONTAINER_SRC_DIR.to_string(),
+        image.to_string(),
+        translate_program(cmd, root, hook_repo_path),
+    ]);
+    container_args.extend(args.iter().map(|a| translate_path(a, root, hook_repo_path)));
+    Ok((runtime(), container_args))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::container::{translate_arg, wrap_command};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_translate_arg() {
+        assert_eq!(
+            translate_arg("/repo/src/main.rs", "/repo"),
+            "/src/src/main.rs"
+        );
+        assert_eq!(translate_arg("/repo", "/repo"), "/src");
+        assert_eq!(translate_arg("--check", "/repo"), "--check");
+        assert_eq!(translate_arg("/repository/a", "/repo"), "/repository/a");
+    }
+
+    #[test]
+    fn test_wrap_command() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let root = dir.path().display().to_string();
+        let r = wrap_command(
+            "rust:1.48",
+            &root,
+            "/tmp/hook-repo",
+            "rustfmt",
+            &[format!("{}/src/main.rs", root)],
+            true,
+        );
+        assert!(r.is_ok());
+        let (_runtime, args) = r.unwrap();
+        assert_eq!(args[0..4], ["run", "--rm", "--network", "none"]);
+        assert!(args.contains(&format!("{}:/src", root)));
+        assert_eq!(
+            &args[args.len() - 3..],
+            &["rust:1.48", "rustfmt", "/src/src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_hook_repo_script() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let root = dir.path().display().to_string();
+        let hook_repo = dir.path().join(".git/hook-repos/hooks");
+        fs::create_dir_all(hook_repo.join("lib")).unwrap();
+        fs::write(hook_repo.join("check.sh"), "#!/bin/sh\n").unwrap();
+        let hook_repo_path = hook_repo.display().to_string();
+        let (_runtime, args) = wrap_command(
+            "alpine:3",
+            &root,
+            &hook_repo_path,
+            "check.sh",
+            &[
+                format!("{}/lib/rules.txt", hook_repo_path),
+                format!("{}/src/main.rs", root),
+            ],
+            false,
+        )
+        .unwrap();
+        assert!(args.contains(&format!("{}:/hook-repo:ro", hook_repo_path)));
+        assert_eq!(
+            &args[args.len() - 4..],
+            &[
+                "alpine:3",
+                "/hook-repo/check.sh",
+                "/hook-repo/lib/rules.txt",
+                "/src/src/main.rs"
+            ]
+        );
+        // the programs the hook repo lacks are the image's
+        let (_runtime, args) =
+            wrap_command("alpine:3", &root, &hook_repo_path, "sh", &[], false).unwrap();
+        assert_eq!(args.last().unwrap(), "sh");
+    }
+}
@@ -0,0 +1,107 @@
+use std::env;
+use std::io::{stdin, IsTerminal, Read};
+use std::path::PathBuf;
+
+use crate::server::RefUpdate;
+use crate::{git, HookEvent};
+
+/// What a run of the hooks is about: the git event, and the files it concerns
+#[derive(Debug, Clone, Default)]
+pub struct RunContext {
+    pub event: Option<HookEvent>,
+    /// The arguments git gave to the hook
+    pub args: Vec<String>,
+    /// What git wrote on the hook's stdin, given to each hook in turn
+    pub input: Option<String>,
+    /// The refs being pushed, on a server or by `pre-push`
+    pub updates: Vec<RefUpdate>,
+    /// The files the event is about: the staged ones, or the pushed ones on a server
+    pub changed_files: Vec<String>,
+    /// Whether the repository is a bare one, without files to work on but the pushed ones
+    pub bare: bool,
+    /// The commits the run is about instead of the index, if any
+    pub range: Option<RefRange>,
+    /// Whether hooks modifying the files fail, instead of having the files staged again
+    pub strict: bool,
+    /// Whether the hooks print as they run, rather than having what they print captured
+    pub verbose: bool,
+    /// Directories of the hook repos to put in the `PATH` of the hooks, after their own repo, with
+    /// `shared_path`
+    pub shared_path: Vec<PathBuf>,
+}
+
+/// The changes of `to` since it forked from `from`, eg. all those of a branch, for CI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefRange {
+    pub from: String,
+    pub to: String,
+}
+
+impl RunContext {
+    /// Gathers the context of `event`, reading the ref updates git gives on stdin to the events
+    /// needing them.
+    pub fn new(event: HookEvent, args: &[String]) -> anyhow::Result<RunContext> {
+        let input = if event.reads_stdin() && !stdin().is_terminal() {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input)?;
+            Some(input)
+        } else {
+            None
+        };
+        let bare = git::is_bare()?;
+        let updates = match event {
+            HookEvent::PreReceive | HookEvent::PostReceive => {
+                RefUpdate::parse_lines(input.as_deref().unwrap_or(""))
+            }
+            HookEvent::Update => RefUpdate::from_update_args(args).into_iter().collect(),
+            HookEvent::PrePush => RefUpdate::parse_push_lines(input.as_deref().unwrap_or("")),
+            _ => vec![],
+        };
+        let changed_files = if event.is_server_side() {
+            let mut files = Vec::new();
+            for update in &updates {
+                files.extend(update.changed_files()?);
+            }
+            files.sort();
+            files.dedup();
+            files
+        } else if bare {
+            vec![]
+        } else {
+            git::changed_files(true)?
+        };
+        // git gives the message files relative to the root, where it runs the hooks, but they are
+        // given relative to the current directory when running git-hooks by hand
+        let mut args = args.to_vec();
+        if event.takes_message_file() {
+            if let Some(file) = args.first_mut() {
+                *file = env::current_dir()?.join(&file).display().to_string();
+            }
+        }
+        Ok(RunContext {
+            event: Some(event),
+            args,
+            input,
+            updates,
+            changed_files,
+            bare,
+            range: None,
+            strict: false,
+            verbose: false,
+            shared_path: vec![],
+        })
+    }
+
+    /// Makes the run about all the files of the repository instead of the staged ones
+    pub fn with_all_files(mut self) -> anyhow::Result<RunContext> {
+        self.changed_files = git::ls_files(&git::root()?)?;
+        Ok(self)
+    }
+
+    /// Makes the run about the files changed in `range` instead of the staged ones
+    pub fn with_range(mut self, range: RefRange) -> anyhow::Result<RunContext> {
+        self.changed_files = git::range_files(&range.from, &range.to, "ACM")?;
+        self.range = Some(range);
+        Ok(self)
+    }
+}
@@ -0,0 +1,439 @@
+//! Edition of the repository's configuration from the command line. The file is edited as text,
+//! so that its comments & formatting are kept.
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::builtins::Builtin;
+use crate::config_format::ConfigFormat;
+use crate::utils::ask_for_input;
+use crate::{Hook, HookConfig, HookEvent};
+
+/// A commented starter configuration
+pub const SAMPLE_CONFIG: &str = include_str!("sample.hooks.yml");
+
+/// Writes the starter configuration to `path`, unless there is a file there already
+pub fn write_sample_config(path: &Path) -> anyhow::Result<()> {
+    write_new_yaml(path, SAMPLE_CONFIG)
+}
+
+/// Writes the YAML configuration `content` to `path`, unless there is a file there already
+pub fn write_new_yaml(path: &Path, content: &str) -> anyhow::Result<()> {
+    if path.exists() {
+        return Err(anyhow::Error::msg(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    if ConfigFormat::from_path(path) != ConfigFormat::Yaml {
+        return Err(anyhow::Error::msg(format!(
+            "the configuration is written as YAML, it cannot be written to {}",
+            path.display()
+        )));
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// A list item to add to the configuration: its fields & their values, in order
+type Item<'a> = Vec<(&'a str, serde_json::Value)>;
+
+/// Adds the hook repo `url`, at `version` if given, to the configuration file `path`, creating it
+/// if needed
+pub fn add_repo(path: &Path, url: &str, version: Option<&str>) -> anyhow::Result<()> {
+    let (content, format, conf) = read(path)?;
+    if conf.repos.iter().any(|r| r.url == url) {
+        return Err(anyhow::Error::msg(format!(
+            "{} is already in {}",
+            url,
+            path.display()
+        )));
+    }
+    let mut item: Item = vec![("url", url.into())];
+    if let Some(version) = version {
+        item.push(("version", version.into()));
+    }
+    std::fs::write(path, add_item(&content, format, "repos", &item)?)?;
+    Ok(())
+}
+
+/// Enables `hook` in the configuration file `path`, creating it if needed
+pub fn add_hook(path: &Path, hook: &Hook) -> anyhow::Result<()> {
+    let (content, format, conf) = read(path)?;
+    if conf.hooks.iter().any(|h| h.name == hook.name) {
+        return Err(anyhow::Error::msg(format!(
+            "{} is already in {}",
+            hook.name,
+            path.display()
+        )));
+    }
+    let mut item: Item = vec![("name", hook.name.as_str().into())];
+    if let Some(events) = &hook.on_event {
+        let events: Vec<&str> = events.iter().map(|e| e.to_kebab_case()).collect();
+        item.push(("on_event", events.into()));
+    }
+    if let Some(regexes) = &hook.on_file_regex {
+        item.push(("on_file_regex", regexes.clone().into()));
+    }
+    if let Some(script) = &hook.script {
+        item.push(("script", script.as_str().into()));
+    }
+    std::fs::write(path, add_item(&content, format, "hooks", &item)?)?;
+    Ok(())
+}
+
+impl HookConfig {
+    /// Tells whether the hook `name` is a builtin one, or is defined by one of the hook repos, so
+    /// that enabling it needs no script
+    pub fn defines(&self, name: &str) -> bool {
+        Builtin::from_name(name).is_some()
+            || self
+                .repos
+                .iter()
+                .any(|r| r.hooks.iter().any(|h| h.name == name))
+    }
+}
+
+/// Asks on the terminal how the hook `name` should be run, validating the answers as it goes.
+/// Hooks already defined, see `HookConfig::defines`, only need to be enabled: they need no script.
+pub fn prompt_hook(name: &str, defined: bool) -> anyhow::Result<Hook> {
+    let mut hook = Hook {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let events = ask_until_valid(
+        if defined {
+            "Events to run it on, eg. pre-commit pre-push (Enter to keep its own)"
+        } else {
+            "Events to run it on, eg. pre-commit pre-push (Enter for pre-commit)"
+        },
+        parse_events,
+    )?;
+    if !events.is_empty() {
+        hook.on_event = Some(events);
+    }
+    let regexes = ask_until_valid(
+        "Regexes of the files it is about, eg. \\.rs$ (Enter for all of them)",
+        parse_regexes,
+    )?;
+    if !regexes.is_empty() {
+        hook.on_file_regex = Some(regexes);
+    }
+    if !defined {
+        let script = ask_until_valid("Command to run, eg. cargo fmt --check", |answer| {
+            if answer.trim().is_empty() {
+                Err(anyhow::Error::msg("a hook needs something to run."))
+            } else {
+                Ok(answer.trim().to_string())
+            }
+        })?;
+        hook.script = Some(script);
+    }
+    Ok(hook)
+}
+
+/// Asks `prompt` until `parse` accepts the answer
+fn ask_until_valid<T>(
+    prompt: &str,
+    parse: impl Fn(&str) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    loop {
+        match parse(&ask_for_input(prompt)?) {
+            Ok(value) => return Ok(value),
+            Err(e) => println!("{} Try again.", e),
+        }
+    }
+}
+
+/// Reads the events listed in `answer`, separated by spaces or commas
+pub fn parse_events(answer: &str) -> anyhow::Result<Vec<HookEvent>> {
+    answer
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            HookEvent::from_kebab_case(word)
+                .ok_or_else(|| anyhow::Error::msg(format!("{} is not a git hook event.", word)))
+        })
+        .collect()
+}
+
+/// Reads the regexes listed in `answer`, separated by spaces
+pub fn parse_regexes(answer: &str) -> anyhow::Result<Vec<String>> {
+    answer
+        .split_whitespace()
+        .map(|regex| match Regex::new(regex) {
+            Ok(_) => Ok(regex.to_string()),
+            Err(e) => Err(anyhow::Error::msg(format!(
+                "invalid regex {}: {}.",
+                regex, e
+            ))),
+        })
+        .collect()
+}
+
+/// Reads the configuration file `path`, empty if it does not exist yet
+fn read(path: &Path) -> anyhow::Result<(String, ConfigFormat, HookConfig)> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let format = ConfigFormat::detect(path, &content);
+    let conf = if content.trim().is_empty() {
+        HookConfig::default()
+    } else {
+        format.parse(&content)?
+    };
+    Ok((content, format, conf))
+}
+
+/// Adds `item` to the top-level list `key` of the configuration `content`
+fn add_item(content: &str, format: ConfigFormat, key: &str, item: &Item) -> anyhow::Result<String> {
+    Ok(match format {
+        ConfigFormat::Yaml => add_item_yaml(content, key, item),
+        ConfigFormat::Toml => add_item_toml(content, key, item)?,
+        ConfigFormat::Json => add_item_json(content, key, item)?,
+    })
+}
+
+/// Quotes `value` if YAML would not read it as a string as is, eg. a `1.0` version
+fn yaml_scalar(value: &str) -> String {
+    match serde_yaml::from_str::<serde_yaml::Value>(value) {
+        Ok(serde_yaml::Value::String(s)) if s == value => value.to_string(),
+        _ => format!("'{}'", value.replace('\'', "''")),
+    }
+}
+
+/// Writes `value` as YAML, lists in the flow style, eg. `[pre-commit, pre-push]`
+fn yaml_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => yaml_scalar(s),
+        serde_json::Value::Array(values) => format!(
+            "[{}]",
+            values.iter().map(yaml_value).collect::<Vec<_>>().join(", ")
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// Returns the line range of the top-level `key` block of a YAML document: its own line, and the
+/// ones up to the last non-blank line before the next top-level key
+fn yaml_block(lines: &[&str], key: &str) -> Option<(usize, usize)> {
+    let start = lines
+        .iter()
+        .position(|l| l.starts_with(&format!("{}:", key)))?;
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        let top_level = !line.starts_with([' ', '\t', '-', '#']) && !line.trim().is_empty();
+        if top_level {
+            break;
+        }
+        if !line.trim().is_empty() && !line.starts_with('#') {
+            end = i;
+        }
+    }
+    Some((start, end))
+}
+
+/// Tells whether the line of a YAML key has no value, which then follows on the next lines
+fn is_block_start(line: &str) -> bool {
+    line.split_once(':')
+        .is_some_and(|(_, value)| value.split('#').next().unwrap_or("").trim().is_empty())
+}
+
+/// Returns the indentation of the items of the YAML block starting at line `start`
+fn yaml_item_indent(lines: &[&str], start: usize, end: usize) -> String {
+    lines[start + 1..=end]
+        .iter()
+        .find(|l| l.trim_start().starts_with("- "))
+        .map(|l| l[..l.len() - l.trim_start().len()].to_string())
+        .unwrap_or_else(|| "  ".to_string())
+}
+
+fn add_item_yaml(content: &str, key: &str, item: &Item) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let block = yaml_block(&lines, key);
+    let indent = match block {
+        Some((start, end)) => yaml_item_indent(&lines, start, end),
+        None => "  ".to_string(),
+    };
+    let entry: Vec<String> = item
+        .iter()
+        .enumerate()
+        .map(|(i, (field, value))| {
+            let dash = if i == 0 { "- " } else { "  " };
+            match value {
+                serde_json::Value::String(s) if s.contains('\n') => {
+                    let body: Vec<String> =
+                        s.lines().map(|l| format!("{}    {}", indent, l)).collect();
+                    format!("{}{}{}: |\n{}", indent, dash, field, body.join("\n"))
+                }
+                _ => format!("{}{}{}: {}", indent, dash, field, yaml_value(value)),
+            }
+        })
+        .collect();
+    let mut result: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    let at = match block {
+        // eg. `repos: []`
+        Some((start, _)) if !is_block_start(lines[start]) => {
+            result[start] = format!("{}:", key);
+            start + 1
+        }
+        Some((_, end)) => end + 1,
+        // `repos` goes first, as in the documentation
+        None if key == "repos" => {
+            let first = result
+                .iter()
+                .position(|l| !l.trim().is_empty() && !l.starts_with('#') && l != "---")
+                .unwrap_or(result.len());
+            result.insert(first, format!("{}:", key));
+            first + 1
+        }
+        None => {
+            result.push(format!("{}:", key));
+            result.len()
+        }
+    };
+    for (i, line) in entry.into_iter().enumerate() {
+        result.insert(at + i, line);
+    }
+    result.join("\n") + "\n"
+}
+
+fn add_item_toml(content: &str, key: &str, item: &Item) -> anyhow::Result<String> {
+    let mut result = content.trim_end().to_string();
+    if !result.is_empty() {
+        result.push_str("\n\n");
+    }
+    result.push_str(&format!("[[{}]]\n", key));
+    for (field, value) in item {
+        result.push_str(&format!("{} = {}\n", field, toml::Value::try_from(value)?));
+    }
+    Ok(result)
+}
+
+fn add_item_json(content: &str, key: &str, item: &Item) -> anyhow::Result<String> {
+    let mut conf: serde_json::Value = if content.trim().is_empty() {
+        serde_json::json!({})
+    } else {
+        serde_json::from_str(content)?
+    };
+    let object = conf
+        .as_object_mut()
+        .ok_or_else(|| anyhow::Error::msg("the configuration is not a JSON object"))?;
+    let entry: serde_json::Map<String, serde_json::Value> = item
+        .iter()
+        .map(|(field, value)| (field.to_string(), value.clone()))
+        .collect();
+    match object.entry(key).or_insert_with(|| serde_json::json!([])) {
+        serde_json::Value::Array(items) => items.push(entry.into()),
+        _ => return Err(anyhow::Error::msg(format!("{} is not a JSON array", key))),
+    }
+    Ok(serde_json::to_string_pretty(&conf)? + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config_format::ConfigFormat;
+    use crate::edit::{add_item, parse_events, parse_regexes, Item, SAMPLE_CONFIG};
+    use crate::{HookConfig, HookEvent};
+
+    fn repo(url: &str, version: Option<&str>) -> Item<'static> {
+        let mut item: Item = vec![("url", url.to_string().into())];
+        if let Some(version) = version {
+            item.push(("version", version.to_string().into()));
+        }
+        item
+    }
+
+    #[test]
+    fn test_add_repo_yaml() {
+        let yaml = |content, item| add_item(content, ConfigFormat::Yaml, "repos", &item).unwrap();
+        let content = "# shared hooks\nrepos:\n- url: https://example.com/a\n  version: v1 # pinned\n\nhooks:\n- name: fmt\n";
+        let added = yaml(content, repo("https://example.com/b", Some("1.0")));
+        assert_eq!(
+            added,
+            "# shared hooks\nrepos:\n- url: https://example.com/a\n  version: v1 # pinned\n- url: https://example.com/b\n  version: '1.0'\n\nhooks:\n- name: fmt\n"
+        );
+        let conf: HookConfig = serde_yaml::from_str(&added).unwrap();
+        assert_eq!(conf.repos[1].version.as_deref(), Some("1.0"));
+
+        assert_eq!(
+            yaml("repos: # none yet\n", repo("https://example.com/b", None)),
+            "repos: # none yet\n  - url: https://example.com/b\n"
+        );
+        assert_eq!(
+            yaml(
+                "repos: []\nhooks: []\n",
+                repo("https://example.com/b", None)
+            ),
+            "repos:\n  - url: https://example.com/b\nhooks: []\n"
+        );
+        assert_eq!(
+            yaml(
+                "# mine\nhooks:\n  - name: fmt\n",
+                repo("https://example.com/b", None)
+            ),
+            "# mine\nrepos:\n  - url: https://example.com/b\nhooks:\n  - name: fmt\n"
+        );
+    }
+
+    #[test]
+    fn test_add_hook_yaml() {
+        let item: Item = vec![
+            ("name", "lint".into()),
+            ("on_event", vec!["pre-commit", "pre-push"].into()),
+            ("script", "set -e\n./lint.sh\n".into()),
+        ];
+        let added = add_item("repos: []\n", ConfigFormat::Yaml, "hooks", &item).unwrap();
+        assert_eq!(
+            added,
+            "repos: []\nhooks:\n  - name: lint\n    on_event: [pre-commit, pre-push]\n    script: |\n      set -e\n      ./lint.sh\n"
+        );
+        let conf: HookConfig = serde_yaml::from_str(&added).unwrap();
+        assert_eq!(conf.hooks[0].script.as_deref(), Some("set -e\n./lint.sh\n"));
+    }
+
+    #[test]
+    fn test_add_repo_toml_json() {
+        let toml = add_item(
+            "# mine\n[[hooks]]\nname = \"fmt\"\n",
+            ConfigFormat::Toml,
+            "repos",
+            &repo("https://example.com/b", Some("v1")),
+        )
+        .unwrap();
+        let conf: HookConfig = toml::from_str(&toml).unwrap();
+        assert_eq!(conf.repos[0].url, "https://example.com/b");
+        assert!(toml.starts_with("# mine\n"));
+
+        let json = add_item(
+            "{\"hooks\": []}",
+            ConfigFormat::Json,
+            "repos",
+            &repo("https://example.com/b", None),
+        )
+        .unwrap();
+        let conf: HookConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(conf.repos[0].url, "https://example.com/b");
+    }
+
+    #[test]
+    fn test_parse_answers() {
+        assert_eq!(
+            parse_events("pre-commit, pre-push").unwrap(),
+            vec![HookEvent::PreCommit, HookEvent::PrePush]
+        );
+        assert!(parse_events("pre-comit").is_err());
+        assert!(parse_events("").unwrap().is_empty());
+        assert_eq!(parse_regexes("\\.rs$ ^src/").unwrap().len(), 2);
+        assert!(parse_regexes("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_sample_config() {
+        let conf: HookConfig = serde_yaml::from_str(SAMPLE_CONFIG).unwrap();
+        assert!(conf.repos.is_empty());
+        assert!(conf.hooks.iter().all(|h| conf.defines(&h.name)));
+    }
+}
@@ -0,0 +1,77 @@
+//! The kinds of failures of `git-hooks`, each exiting with its own code, for the shims, CIs &
+//! wrappers to tell them apart.
+use thiserror::Error;
+
+/// Exit code of the failures of no known kind, eg. bugs or unexpected git errors
+pub const INTERNAL_EXIT_CODE: i32 = 3;
+
+/// A failure of a known kind. It travels as an `anyhow::Error`, possibly with some context:
+/// embedders find it with `downcast_ref`.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GitHooksError {
+    /// Hooks failed: exits with 1
+    #[error("{0}")]
+    HookFailure(String),
+    /// The configuration, or a file it refers to, could not be read or is invalid: exits with 2
+    #[error("{0}")]
+    Config(String),
+    /// Hook repos, included configurations or indexes could not be fetched: exits with 4
+    #[error("{0}")]
+    Network(String),
+}
+
+impl GitHooksError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GitHooksError::HookFailure(_) => 1,
+            GitHooksError::Config(_) => 2,
+            GitHooksError::Network(_) => 4,
+        }
+    }
+}
+
+/// A command which ran and failed, with what it printed, for the reports of the hooks to keep it.
+/// It travels as an `anyhow::Error` too.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("command invoked with errors")]
+pub struct CommandFailed {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Returns the code `git-hooks` exits with on `error`: the one of the first failure of a known
+/// kind it comes from, HTTP errors being network ones, or `INTERNAL_EXIT_CODE`
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<GitHooksError>() {
+            return e.exit_code();
+        }
+        if cause.is::<reqwest::Error>() {
+            return GitHooksError::Network(String::new()).exit_code();
+        }
+    }
+    INTERNAL_EXIT_CODE
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{exit_code, GitHooksError, INTERNAL_EXIT_CODE};
+
+    #[test]
+    fn test_exit_code() {
+        let config = anyhow::Error::new(GitHooksError::Config(
+            "invalid config .hooks.yml".to_string(),
+        ));
+        assert_eq!(exit_code(&config), 2);
+        assert_eq!(config.to_string(), "invalid config .hooks.yml");
+        assert_eq!(exit_code(&config.context("could not load the hooks")), 2);
+        let hooks = anyhow::Error::new(GitHooksError::HookFailure("a hook failed".to_string()));
+        assert_eq!(exit_code(&hooks), 1);
+        let network = anyhow::Error::new(GitHooksError::Network("could not fetch".to_string()));
+        assert_eq!(exit_code(&network), 4);
+        assert_eq!(
+            exit_code(&anyhow::Error::msg("command invoked with errors")),
+            INTERNAL_EXIT_CODE
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! `git-hooks exec`: runs a single hook of a hook repo on the spot, whether the configuration
+//! enables it or not, eg. to try it out or debug it.
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::GitHooksError;
+use crate::policy::Policies;
+use crate::trust::TrustStore;
+use crate::{ExternalHookRepo, Hook, HookConfig, HookEvent, LoadOptions};
+
+/// Splits `target`, `<repo>:<hook>`, into the repo, a name or a URL, and the name of the hook
+pub fn parse_target(target: &str) -> anyhow::Result<(&str, &str)> {
+    // URLs hold colons too, hook names do not
+    match target.rsplit_once(':') {
+        Some((repo, hook)) if !repo.is_empty() && !hook.is_empty() && !hook.contains('/') => {
+            Ok((repo, hook))
+        }
+        _ => Err(GitHooksError::Config(format!(
+            "{} is not <repo>:<hook>, eg. lints:shellcheck",
+            target
+        ))
+        .into()),
+    }
+}
+
+/// Returns the name of the repo at `url`, the last segment of its URL, without `.git`
+fn repo_name(url: &str) -> &str {
+    let name = url.trim_end_matches('/').rsplit('/').next().unwrap_or(url);
+    name.strip_suffix(".git").unwrap_or(name)
+}
+
+/// Finds the repo of the configuration whose URL, or name, is `repo`
+fn find_repo<'a>(conf: &'a HookConfig, repo: &str) -> Option<&'a ExternalHookRepo> {
+    conf.repos
+        .iter()
+        .find(|r| r.url == repo)
+        .or_else(|| conf.repos.iter().find(|r| repo_name(&r.url) == repo))
+}
+
+impl HookConfig {
+    /// Checks out & sets up the hook repo `repo`, one of the configuration, by URL or name, or
+    /// else the one at the URL `repo`, at its latest version. Returns it, and its hook `name`
+    /// with the overrides of the configuration, the events it runs on set.
+    pub fn exec_target(
+        &self,
+        repo: &str,
+        name: &str,
+        opts: &LoadOptions,
+    ) -> anyhow::Result<(ExternalHookRepo, Hook)> {
+        let mut repo = match find_repo(self, repo) {
+            Some(found) => ExternalHookRepo {
+                url: found.url.clone(),
+                version: found.version.clone(),
+                default_on_event: found.default_on_event.clone(),
+                ..Default::default()
+            },
+            None if repo.contains('/') || repo.contains(':') => ExternalHookRepo {
+                url: repo.to_string(),
+                ..Default::default()
+            },
+            None => {
+                return Err(GitHooksError::Config(format!(
+                    "no repo {} in the configuration, give its URL instead",
+                    repo
+                ))
+                .into())
+            }
+        };
+        // the repository's own hook repo is versioned with it
+        if !repo.is_project() {
+            Policies::load()?.check(&repo.url)?;
+            if !TrustStore::load()?.ensure_trusted(
+                &repo.url,
+                repo.version.as_deref(),
+                opts.trust_all,
+            )? {
+                return Err(GitHooksError::Config(format!("{} is not trusted", repo.url)).into());
+            }
+        }
+        repo.init(opts)?;
+        let mut hook = repo
+            .hooks
+            .iter()
+            .find(|h| h.name == name)
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<&str> = repo.hooks.iter().map(|h| h.name.as_str()).collect();
+                GitHooksError::Config(format!(
+                    "{} defines no hook {}, only: {}",
+                    repo.url,
+                    name,
+                    names.join(", ")
+                ))
+            })?;
+        if let Some(configured) = self.hooks.iter().find(|h| h.name == name) {
+            hook.override_with(configured);
+        }
+        if hook.on_event.is_none() {
+            hook.on_event = Some(
+                repo.default_on_event
+                    .clone()
+                    .unwrap_or_else(|| self.default_events()),
+            );
+        }
+        Ok((repo, hook))
+    }
+}
+
+/// Returns the event `hook` runs on by default: the first one it reacts to
+pub fn default_event(hook: &Hook) -> HookEvent {
+    hook.on_event
+        .as_ref()
+        .and_then(|events| events.first().copied())
+        .unwrap_or(HookEvent::PreCommit)
+}
+
+/// Returns the `files`, relative to the current directory `cwd`, relative to the repository
+/// `root` instead, as the hooks get them
+pub fn repo_files(root: &Path, cwd: &Path, files: &[String]) -> anyhow::Result<Vec<String>> {
+    files
+        .iter()
+        .map(|file| {
+            // the files may not exist, eg. when trying a hook on deletions
+            let mut path = PathBuf::new();
+            for component in cwd.join(file).components() {
+                match component {
+                    Component::ParentDir => {
+                        path.pop();
+                    }
+                    Component::CurDir => {}
+                    component => path.push(component),
+                }
+            }
+            path.strip_prefix(root)
+                .map(|p| p.display().to_string())
+                .map_err(|_| anyhow::Error::msg(format!("{} is not in the repository", file)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::exec::{find_repo, parse_target, repo_files};
+    use crate::HookConfig;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("lints:shellcheck").unwrap(),
+            ("lints", "shellcheck")
+        );
+        assert_eq!(
+            parse_target("https://example.com/lints.git:shellcheck").unwrap(),
+            ("https://example.com/lints.git", "shellcheck")
+        );
+        assert!(parse_target("lints").is_err());
+        assert!(parse_target("lints:").is_err());
+        // a URL without the hook
+        assert!(parse_target("git@example.com:org/lints").is_err());
+    }
+
+    #[test]
+    fn test_find_repo() {
+        let conf: HookConfig = serde_yaml::from_str(
+            "repos:\n  - url: https://example.com/org/lints.git\n  - url: https://example.com/org/fmt\n",
+        )
+        .unwrap();
+        let url = |repo| find_repo(&conf, repo).map(|r| r.url.as_str());
+        assert_eq!(url("lints"), Some("https://example.com/org/lints.git"));
+        assert_eq!(
+            url("https://example.com/org/fmt"),
+            Some("https://example.com/org/fmt")
+        );
+        assert_eq!(url("org"), None);
+    }
+
+    #[test]
+    fn test_repo_files() {
+        let files = ["src/main.rs".to_string(), "../README.md".to_string()];
+        assert_eq!(
+            repo_files(
+                Path::new("/nonexistent"),
+                Path::new("/nonexistent/app"),
+                &files[..1]
+            )
+            .unwrap(),
+            ["app/src/main.rs"]
+        );
+        assert!(repo_files(
+            Path::new("/nonexistent/app"),
+            Path::new("/nonexistent/app"),
+            &files
+        )
+        .is_err());
+    }
+}
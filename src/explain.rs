@@ -0,0 +1,221 @@
+//! `git-hooks explain`: what the hooks would do on an event, and why some of them would not run.
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::builtins::{Builtin, BUILTIN_PREFIX};
+use crate::context::RunContext;
+use crate::git;
+use crate::{
+    expand_command, hook_changed_files, hook_root, monorepo, remove_diff_file, wrap_command,
+    ExternalHookRepo, Hook, HookConfig, HookEvent,
+};
+
+impl HookConfig {
+    /// Describes, for each hook of the configuration, whether it would run on the event of `ctx`
+    /// or why it would not, the changed files it would get, and the command lines it would run.
+    /// Nothing is run.
+    pub fn explain(&self, ctx: &RunContext) -> anyhow::Result<String> {
+        let event = ctx
+            .event
+            .ok_or_else(|| anyhow::Error::msg("no event to explain"))?;
+        let mut out = String::new();
+        if let (Some(_), HookEvent::PreReceive | HookEvent::Update) = (&self.push_policy, event) {
+            writeln!(out, "push-policy: runs, checking the pushed refs")?;
+        }
+        for hook in &self.hooks {
+            // the repos definitions hold the overrides already
+            let definition = self.repos.iter().find_map(|repo| {
+                repo.hooks
+                    .iter()
+                    .find(|h| h.name == hook.name)
+                    .map(|h| (repo, h))
+            });
+            let source = match (Builtin::from_name(&hook.name), definition) {
+                (Some(_), _) => "builtin".to_string(),
+                (None, _) if hook.name.starts_with(BUILTIN_PREFIX) => {
+                    writeln!(out, "{}: does not run: unknown builtin hook", hook.name)?;
+                    continue;
+                }
+                (None, Some((repo, _))) => format!("from {}", repo.url),
+                (None, None) if hook.script.is_some() => "inline script".to_string(),
+                (None, None) if hook.is_local() => match self.unknown_repo_defining(&hook.name) {
+                    Some(url) => {
+                        writeln!(
+                            out,
+                            "{}: does not run: {} may define it, but could not be set up",
+                            hook.name, url
+                        )?;
+                        continue;
+                    }
+                    None => "local action".to_string(),
+                },
+                (None, None) => {
+                    writeln!(
+                        out,
+                        "{}: does not run: not defined by any trusted hook repo",
+                        hook.name
+                    )?;
+                    continue;
+                }
+            };
+            let hook = definition.map(|(_, h)| h).unwrap_or(hook);
+            let events = self.hook_events(hook);
+            let description = describe(hook);
+            if !events.contains(&event) {
+                let events: Vec<&str> = events.iter().map(|e| e.to_kebab_case()).collect();
+                writeln!(
+                    out,
+                    "{} ({}): does not run: only runs on {}{}",
+                    hook.name,
+                    source,
+                    events.join(", "),
+                    description
+                )?;
+                continue;
+            }
+            if let Some(dir) = &hook.root {
+                if monorepo::files_under(dir, &ctx.changed_files).is_empty() {
+                    writeln!(
+                        out,
+                        "{} ({}): does not run: no changed file under {}{}",
+                        hook.name, source, dir, description
+                    )?;
+                    continue;
+                }
+            }
+            writeln!(out, "{} ({}): runs{}", hook.name, source, description)?;
+            let files = hook_changed_files(hook, ctx)?;
+            if files.is_empty() {
+                writeln!(out, "  no changed file matches it")?;
+            } else {
+                writeln!(out, "  changed files matching it: {}", files.join(" "))?;
+            }
+            for line in command_lines(hook, definition.map(|(repo, _)| repo), ctx)? {
+                writeln!(out, "  {}", line)?;
+            }
+        }
+        let not_enabled: Vec<&str> = self
+            .repos
+            .iter()
+            .flat_map(|r| &r.hooks)
+            .filter(|h| !self.hooks.iter().any(|enabled| enabled.name == h.name))
+            .map(|h| h.name.as_str())
+            .collect();
+        if !not_enabled.is_empty() {
+            writeln!(
+                out,
+                "Defined by the hook repos, but not enabled: {}",
+                not_enabled.join(", ")
+            )?;
+        }
+        if !self.disable.is_empty() {
+            writeln!(out, "Disabled: {}", self.disable.join(", "))?;
+        }
+        if out.is_empty() {
+            writeln!(out, "No hook is configured.")?;
+        }
+        Ok(out)
+    }
+}
+
+/// Returns the description of `hook`, on a line of its own, if it has any
+fn describe(hook: &Hook) -> String {
+    match &hook.description {
+        Some(description) => format!("\n  {}", description.trim_end()),
+        None => String::new(),
+    }
+}
+
+/// Returns what `hook`, defined by the hook repo `repo` if any, would run
+fn command_lines(
+    hook: &Hook,
+    repo: Option<&ExternalHookRepo>,
+    ctx: &RunContext,
+) -> anyhow::Result<Vec<String>> {
+    if Builtin::from_name(&hook.name).is_some() {
+        return Ok(vec!["runs the builtin check".to_string()]);
+    }
+    if let Some(script) = &hook.script {
+        return Ok(vec![format!("script: {}", script.trim_end())]);
+    }
+    let Some(action) = &hook.action else {
+        return Ok(vec![]);
+    };
+    // the local hooks run from the repository, as if it were their hook repo
+    let hook_repo_path = match repo {
+        Some(repo) => repo.local_path()?,
+        None => git::root()?,
+    };
+    let root = hook_root(hook)?;
+    let mut lines = Vec::new();
+    for command in action.commands() {
+        let (cmd, args) = expand_command(hook, command, &root, ctx)?;
+        // nothing runs: the diff written for the `{diff}` token is not needed
+        remove_diff_file(hook);
+        let (cmd, args) = match &hook.wasm {
+            Some(module) => (
+                format!("wasm module {}:", module),
+                [vec![cmd], args].concat(),
+            ),
+            None => wrap_command(hook, &hook_repo_path, &root, cmd, args, &mut HashMap::new())?,
+        };
+        let words: Vec<String> = [cmd]
+            .iter()
+            .chain(&args)
+            .map(|w| shlex::quote(w).into_owned())
+            .collect();
+        lines.push(format!("command: {} (in {})", words.join(" "), root));
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::RunContext;
+    use crate::{HookConfig, HookEvent};
+
+    #[test]
+    fn test_explain() {
+        let conf: HookConfig = serde_yaml::from_str(
+            r#"
+hooks:
+  - name: "builtin:conventional-commit"
+  - name: "builtin:trailing-whitespace"
+    on_file_regex: ['\.rs$']
+  - name: rustfmt
+  - name: docs
+    root: docs
+    script: make
+  - name: lint
+    description: Lints the sources, run ./lint.sh --fix to fix them
+    script: ./lint.sh
+  - name: check
+    action: cargo check
+    on_event: [pre-push]
+disable: [slow]
+"#,
+        )
+        .unwrap();
+        let ctx = RunContext {
+            event: Some(HookEvent::PreCommit),
+            changed_files: vec!["src/main.rs".to_string(), "README.md".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            conf.explain(&ctx).unwrap(),
+            "builtin:conventional-commit (builtin): does not run: only runs on commit-msg
+builtin:trailing-whitespace (builtin): runs
+  changed files matching it: src/main.rs
+  runs the builtin check
+rustfmt: does not run: not defined by any trusted hook repo
+docs (inline script): does not run: no changed file under docs
+lint (inline script): runs
+  Lints the sources, run ./lint.sh --fix to fix them
+  changed files matching it: src/main.rs README.md
+  script: ./lint.sh
+check (local action): does not run: only runs on pre-push
+Disabled: slow
+"
+        );
+    }
+}
@@ -0,0 +1,317 @@
+//! `git-hooks export`: approximations of the configuration in the formats of other hooks
+//! managers, for the tools only understanding those.
+use serde::Serialize;
+
+use crate::builtins::Builtin;
+use crate::config_format::to_config_yaml;
+use crate::import::PRE_COMMIT_HOOKS_URL;
+use crate::utils::shell_quote;
+use crate::{Hook, HookConfig, HookEvent};
+
+/// The configuration written in another hooks manager's format, and what could not be written
+#[derive(Debug, Default)]
+pub struct Export {
+    pub content: String,
+    /// Describes the settings left out of `content`, or written loosely
+    pub notes: Vec<String>,
+}
+
+/// The version of pre-commit's own repo the builtin hooks are exported to
+const PRE_COMMIT_HOOKS_REV: &str = "v4.6.0";
+
+/// A `.pre-commit-config.yaml`
+#[derive(Serialize, Debug, Default)]
+struct PreCommitConfig {
+    /// The hook types `pre-commit install` installs
+    default_install_hook_types: Vec<&'static str>,
+    repos: Vec<PreCommitRepo>,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct PreCommitRepo {
+    repo: String,
+    rev: Option<String>,
+    hooks: Vec<PreCommitHook>,
+}
+
+#[derive(Serialize, Debug, Default)]
+struct PreCommitHook {
+    id: String,
+    name: Option<String>,
+    entry: Option<String>,
+    language: Option<&'static str>,
+    files: Option<String>,
+    stages: Vec<&'static str>,
+    args: Vec<String>,
+    pass_filenames: Option<bool>,
+}
+
+/// Writes the configuration as a pre-commit one, `.pre-commit-config.yaml`.
+/// The hook repos are kept, pinned to their `version`, with the hooks enabled from them. The
+/// builtin hooks mirroring pre-commit's own are taken from its repo, and the inline scripts
+/// become local hooks.
+pub fn pre_commit(conf: &HookConfig) -> anyhow::Result<Export> {
+    let mut notes = Vec::new();
+    if conf.push_policy.is_some() {
+        notes.push("the push policy has no pre-commit equivalent".to_string());
+    }
+    let mut builtins = PreCommitRepo {
+        repo: PRE_COMMIT_HOOKS_URL.to_string(),
+        rev: Some(PRE_COMMIT_HOOKS_REV.to_string()),
+        ..Default::default()
+    };
+    let mut repos: Vec<PreCommitRepo> = conf
+        .repos
+        .iter()
+        .map(|repo| {
+            notes.push(format!(
+                "{}: pre-commit only provides its hooks if it also has a .pre-commit-hooks.yaml",
+                repo.url
+            ));
+            if repo.version.is_none() {
+                notes.push(format!(
+                    "{}: pre-commit needs a rev, pin it to a tag instead of HEAD",
+                    repo.url
+                ));
+            }
+            PreCommitRepo {
+                repo: repo.url.clone(),
+                rev: Some(repo.version.clone().unwrap_or_else(|| "HEAD".to_string())),
+                ..Default::default()
+            }
+        })
+        .collect();
+    let mut local = PreCommitRepo {
+        repo: "local".to_string(),
+        ..Default::default()
+    };
+    for hook in &conf.hooks {
+        if hook.root.is_some() {
+            notes.push(format!(
+                "{}: its root has no pre-commit equivalent, it runs on all the files",
+                hook.name
+            ));
+        }
+        let mut exported = PreCommitHook {
+            id: hook.name.clone(),
+            files: hook
+                .on_file_regex
+                .as_ref()
+                .map(|regexes| match &regexes[..] {
+                    [regex] => regex.clone(),
+                    regexes => regexes
+                        .iter()
+                        .map(|r| format!("(?:{})", r))
+                        .collect::<Vec<_>>()
+                        .join("|"),
+                }),
+            stages: pre_commit_stages(&conf.hook_events(hook), &hook.name, &mut notes),
+            ..Default::default()
+        };
+        if let Some(builtin) = Builtin::from_name(&hook.name) {
+            match builtin.pre_commit_id() {
+                Some(id) => {
+                    exported.id = id.to_string();
+                    exported.args = builtin_args(builtin, hook, &mut notes);
+                    builtins.hooks.push(exported);
+                }
+                None => notes.push(format!("{}: has no pre-commit equivalent", hook.name)),
+            }
+        } else if let Some(i) = conf
+            .repos
+            .iter()
+            .position(|r| r.hooks.iter().any(|h| h.name == hook.name))
+        {
+            repos[i].hooks.push(exported);
+        } else if let Some(script) = &hook.script {
+            if script.contains("GIT_HOOKS_") {
+                notes.push(format!(
+                    "{}: its script reads variables of git-hooks which pre-commit does not set",
+                    hook.name
+                ));
+            }
+            exported.name = Some(hook.name.clone());
+            exported.entry = Some(format!("sh -ec {} --", shell_quote(script)));
+            exported.language = Some("system");
+            exported.pass_filenames = Some(false);
+            local.hooks.push(exported);
+        } else if hook.is_local() {
+            notes.push(format!(
+                "{}: its action has no pre-commit equivalent yet, left out",
+                hook.name
+            ));
+        } else {
+            notes.push(format!(
+                "{}: not defined by any trusted hook repo, left out",
+                hook.name
+            ));
+        }
+    }
+    let mut pre_commit = PreCommitConfig {
+        repos: std::iter::once(builtins)
+            .chain(repos)
+            .chain(std::iter::once(local))
+            .filter(|r| !r.hooks.is_empty())
+            .collect(),
+        ..Default::default()
+    };
+    for stage in pre_commit
+        .repos
+        .iter()
+        .flat_map(|r| &r.hooks)
+        .flat_map(|h| &h.stages)
+    {
+        // pre-commit installs no hook for the manual stage
+        if *stage != "manual" && !pre_commit.default_install_hook_types.contains(stage) {
+            pre_commit.default_install_hook_types.push(stage);
+        }
+    }
+    Ok(Export {
+        content: to_config_yaml(&pre_commit)?,
+        notes,
+    })
+}
+
+/// Returns the pre-commit stages of `events`, noting the events of `name` pre-commit has no stage
+/// for
+fn pre_commit_stages(
+    events: &[HookEvent],
+    name: &str,
+    notes: &mut Vec<String>,
+) -> Vec<&'static str> {
+    let mut stages = Vec::new();
+    for &event in events {
+        match event {
+            HookEvent::PreCommit
+            | HookEvent::PreMergeCommit
+            | HookEvent::PrePush
+            | HookEvent::PrepareCommitMsg
+            | HookEvent::CommitMsg
+            | HookEvent::PostCommit
+            | HookEvent::PreRebase
+            | HookEvent::Manual => stages.push(event.to_kebab_case()),
+            _ => notes.push(format!(
+                "{}: pre-commit does not run on {}",
+                name,
+                event.to_kebab_case()
+            )),
+        }
+    }
+    stages
+}
+
+/// Returns the arguments of the pre-commit hook the builtin mirrors matching the settings of `hook`
+fn builtin_args(builtin: Builtin, hook: &Hook, notes: &mut Vec<String>) -> Vec<String> {
+    let settings = match &hook.settings {
+        Some(settings) => settings,
+        None => return Vec::new(),
+    };
+    match builtin {
+        Builtin::CheckAddedLargeFiles => settings
+            .get("max_size_kb")
+            .and_then(|kb| kb.as_u64())
+            .map(|kb| vec![format!("--maxkb={}", kb)])
+            .unwrap_or_default(),
+        Builtin::NoCommitToBranch => settings
+            .get("branches")
+            .and_then(|b| b.as_sequence())
+            .into_iter()
+            .flatten()
+            .filter_map(|b| b.as_str())
+            .flat_map(|branch| {
+                if branch.contains('*') {
+                    let pattern: Vec<String> = branch.split('*').map(regex::escape).collect();
+                    vec!["--pattern".to_string(), format!("^{}$", pattern.join(".*"))]
+                } else {
+                    vec!["--branch".to_string(), branch.to_string()]
+                }
+            })
+            .collect(),
+        _ => {
+            notes.push(format!("{}: its settings are left out", hook.name));
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::pre_commit;
+    use crate::{ExternalHookRepo, Hook, HookConfig};
+
+    #[test]
+    fn test_pre_commit() {
+        let mut conf: HookConfig = serde_yaml::from_str(
+            r#"
+repos:
+  - url: https://github.com/doublify/pre-commit-rust
+    version: v1.0
+hooks:
+  - name: builtin:trailing-whitespace
+  - name: builtin:no-commit-to-branch
+    settings:
+      branches: [main, release/*]
+  - name: builtin:conventional-commit
+  - name: fmt
+    on_event: [pre-push]
+  - name: todo
+    on_file_regex: ['\.rs$', '\.md$']
+    script: grep -rn TODO src
+"#,
+        )
+        .unwrap();
+        // as if the repo was cloned
+        conf.repos[0] = ExternalHookRepo {
+            url: conf.repos[0].url.clone(),
+            version: conf.repos[0].version.clone(),
+            hooks: vec![Hook {
+                name: "fmt".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        conf.update_repos_config();
+        let export = pre_commit(&conf).unwrap();
+        assert_eq!(
+            export.content,
+            r#"---
+default_install_hook_types:
+  - pre-commit
+  - pre-push
+repos:
+  - repo: "https://github.com/pre-commit/pre-commit-hooks"
+    rev: v4.6.0
+    hooks:
+      - id: trailing-whitespace
+        stages:
+          - pre-commit
+      - id: no-commit-to-branch
+        stages:
+          - pre-commit
+        args:
+          - "--branch"
+          - main
+          - "--pattern"
+          - ^release/.*$
+  - repo: "https://github.com/doublify/pre-commit-rust"
+    rev: v1.0
+    hooks:
+      - id: fmt
+        stages:
+          - pre-push
+  - repo: local
+    hooks:
+      - id: todo
+        name: todo
+        entry: "sh -ec 'grep -rn TODO src' --"
+        language: system
+        files: "(?:\\.rs$)|(?:\\.md$)"
+        stages:
+          - pre-commit
+        pass_filenames: false
+"#
+        );
+        // the repo's .pre-commit-hooks.yaml & conventional-commit
+        assert_eq!(export.notes.len(), 2);
+    }
+}
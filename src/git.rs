@@ -1,115 +1,91 @@
 use std::ffi::OsStr;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
-use std::process::ExitStatus;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
 
 use log::{debug, error};
 
 use crate::utils;
 
-#[cfg(test)]
-mod tests {
-    use crate::git::{add, changed_files, checkout, clone, git_command, root};
-    use std::env::{current_dir, set_current_dir};
-    use std::fs::File;
-    use std::path::Path;
-    use tempdir::TempDir;
+fn git_command<T: AsRef<str> + AsRef<OsStr> + Debug>(
+    args: &[T],
+    repo: Option<&str>,
+) -> anyhow::Result<(ExitStatus, String, String)> {
+    utils::execute_cmd("git", args, repo, None)
+}
 
-    fn setup() -> TempDir {
-        let _ = pretty_env_logger::try_init();
-        TempDir::new("git-hooks-tests").expect("could not create temp dir")
-    }
+/// A version of git, eg. `2.39.2`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GitVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
 
-    #[test]
-    fn test_git_command() {
-        let _ = setup();
-        let r = git_command(&["--version"], None);
-        assert!(r.is_ok());
-        let (s, out, _err) = r.unwrap();
-        assert!(s.success());
-        assert!(out.starts_with("git version "));
+impl GitVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> GitVersion {
+        GitVersion {
+            major,
+            minor,
+            patch,
+        }
     }
 
-    #[test]
-    fn test_clone() {
-        let dir = setup();
-        let r = clone(".", dir.path().display().to_string());
-        assert!(r.is_ok());
-        let p = r.unwrap();
-        assert_eq!(p, dir.path().display().to_string());
-    }
-
-    #[test]
-    fn test_checkout() {
-        let dir = setup();
-        let _ = clone(".", dir.path().display().to_string());
-        let r = checkout(
-            "99586a59496151167dc730c62d5405d7a6401bf6",
-            dir.path().display().to_string().as_str(),
-        );
-        assert!(r.is_ok());
-        let r = git_command(
-            &["rev-parse", "HEAD"],
-            Some(dir.path().display().to_string().as_str()),
-        );
-        assert!(r.is_ok());
-        let (s, out, _err) = r.unwrap();
-        assert!(s.success());
-        assert_eq!(out.trim(), "99586a59496151167dc730c62d5405d7a6401bf6"); // hash of the v0.3.0 tag
+    /// Reads what `git --version` prints, such as `git version 2.45.1.windows.1` or
+    /// `git version 2.39.2 (Apple Git-143)`
+    pub fn parse(output: &str) -> Option<GitVersion> {
+        let version = output.trim().strip_prefix("git version ")?;
+        let mut numbers = version
+            .split(|c: char| c == '.' || c.is_whitespace())
+            .map(|n| n.parse::<u32>());
+        let major = numbers.next()?.ok()?;
+        let minor = numbers.next()?.ok()?;
+        // release candidates, such as `2.45.0-rc1`, have no patch number
+        let patch = numbers.next().and_then(|n| n.ok()).unwrap_or(0);
+        Some(GitVersion::new(major, minor, patch))
     }
+}
 
-    #[test]
-    fn test_adding_files() {
-        let dir = setup();
-        let _ = clone(".", dir.path().display().to_string());
-        let f = File::create(dir.path().join("tests.txt"));
-        assert!(f.is_ok());
-        let old_dir = current_dir().expect("could not unwrap current_dir");
-        set_current_dir(Path::new(dir.path().display().to_string().as_str()))
-            .expect("could not cd in temp cloned dir");
-        let r = changed_files(false);
-        assert!(r.is_ok());
-        let files = r.unwrap();
-        assert!(files.contains(&"tests.txt".to_string()));
-        let r = add(&["tests.txt"]);
-        assert!(r.is_ok());
-        let r = changed_files(true);
-        assert!(r.is_ok());
-        let files = r.unwrap();
-        assert!(files.contains(&"tests.txt".to_string()));
-        set_current_dir(old_dir).expect("could not cd back to old dir");
+impl fmt::Display for GitVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
     }
+}
 
-    #[test]
-    fn test_root() {
-        let dir = setup();
-        let _ = clone(
-            "https://github.com/paulollivier/git-hooks",
-            dir.path().display().to_string(),
-        );
-        let old_dir = current_dir().expect("could not get current dir");
-        set_current_dir(dir.path().join("src")).expect("could not change current dir");
-        let r = root();
-        assert!(r.is_ok());
-        let d = r.unwrap();
-        assert_eq!(dir.path().display().to_string(), d);
-        set_current_dir(old_dir).expect("could not cd back to old dir");
+/// Returns the version of the installed git, asked once
+pub fn version() -> anyhow::Result<GitVersion> {
+    static VERSION: OnceLock<GitVersion> = OnceLock::new();
+    if let Some(version) = VERSION.get() {
+        return Ok(*version);
     }
+    let (_status, stdout, _stderr) = git_command(&["--version"], None)?;
+    let version = GitVersion::parse(&stdout)
+        .ok_or_else(|| anyhow::Error::msg(format!("unknown git version: {}", stdout.trim())))?;
+    Ok(*VERSION.get_or_init(|| version))
 }
 
-fn git_command<T: AsRef<str> + AsRef<OsStr> + Debug>(
-    args: &[T],
-    repo: Option<&str>,
-) -> anyhow::Result<(ExitStatus, String, String)> {
-    utils::execute_cmd("git", &args, repo, None)
+/// Fails telling that `what` needs at least git `min`, if the installed one is older
+pub fn require_version(min: GitVersion, what: &str) -> anyhow::Result<()> {
+    let version = version()?;
+    if version < min {
+        return Err(anyhow::Error::msg(format!(
+            "{} needs git {} or newer, but git {} is installed",
+            what, min, version
+        )));
+    }
+    Ok(())
 }
 
-#[cfg(test)]
 /// inits a new git repo
 /// if `dir` is Some, the repo will be initiated in the given directory. Otherwise, in the current directory.
 pub fn init(dir: Option<&str>) -> anyhow::Result<()> {
-    git_command(&["init"], dir)?;
+    let (s, _, err) = git_command(&["init"], dir)?;
+    if !s.success() {
+        return Err(anyhow::Error::msg(err));
+    }
     Ok(())
 }
 
@@ -123,6 +99,49 @@ pub fn get_hash(reference: &str) -> anyhow::Result<String> {
     Ok(out.trim().to_string())
 }
 
+/// Returns the commit hash `HEAD` points to in the given repo
+pub fn head(repo: &str) -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) = git_command(&["rev-parse", "HEAD"], Some(repo))?;
+    Ok(stdout.trim().to_string())
+}
+
+/// Returns the commit `reference` points to in `repo`, or None if it does not exist there
+pub fn resolve(reference: &str, repo: &str) -> Option<String> {
+    let commit = format!("{}^{{commit}}", reference);
+    git_command(&["rev-parse", "--verify", "--quiet", &commit], Some(repo))
+        .ok()
+        .map(|(_status, stdout, _stderr)| stdout.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+}
+
+/// Returns the commit hash `HEAD` points to in the given repo, or None if it has no commit yet,
+/// eg. a new hook repo
+pub fn head_if_any(repo: &str) -> anyhow::Result<Option<String>> {
+    let (_status, stdout, _stderr) =
+        git_command(&["rev-list", "--max-count=1", "--all"], Some(repo))?;
+    if stdout.trim().is_empty() {
+        return Ok(None);
+    }
+    head(repo).map(Some)
+}
+
+/// Returns the last commit of `HEAD` changing the directory `dir`, or None if none did yet, eg. a
+/// new one
+pub fn last_commit_of(dir: &str) -> anyhow::Result<Option<String>> {
+    if head_if_any(dir)?.is_none() {
+        return Ok(None);
+    }
+    let (_status, stdout, _stderr) =
+        git_command(&["rev-list", "--max-count=1", "HEAD", "--", "."], Some(dir))?;
+    Ok(Some(stdout.trim().to_string()).filter(|commit| !commit.is_empty()))
+}
+
+/// Returns the hash git would give to the content of `file`, tracked or not
+pub fn hash_object(file: &str) -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) = git_command(&["hash-object", file], None)?;
+    Ok(stdout.trim().to_string())
+}
+
 /// Clones a git depot & returns the path to the cloned instance
 /// TODO:
 ///     - clone a shallow copy
@@ -178,8 +197,92 @@ pub fn add<T: AsRef<str>>(files: &[T]) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Returns the staged content of `file`, relative to the root of `repo`, byte for byte
+pub fn staged_content(repo: &str, file: &str) -> anyhow::Result<Vec<u8>> {
+    blob(repo, &format!(":{}", file))
+        .map_err(|e| anyhow::Error::msg(format!("could not read the staged {}: {}", file, e)))
+}
+
+/// Returns the content of `file`, relative to the root of `repo`, at `revision`, byte for byte,
+/// or `None` if the revision does not hold it, eg. as it was deleted since
+pub fn content_at(repo: &str, revision: &str, file: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    let object = format!("{}:{}", revision, file);
+    let exists = Command::new("git")
+        .args(["cat-file", "-e", &object])
+        .current_dir(repo)
+        .stderr(Stdio::null())
+        .status()?
+        .success();
+    if !exists {
+        return Ok(None);
+    }
+    blob(repo, &object)
+        .map(Some)
+        .map_err(|e| anyhow::Error::msg(format!("could not read {}: {}", object, e)))
+}
+
+/// Returns the content of the blob `object`, eg. `<revision>:<path>`, byte for byte
+fn blob(repo: &str, object: &str) -> anyhow::Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(["show", object])
+        .current_dir(repo)
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::Error::msg(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Stages `content` as the content of `file`, relative to the root of `repo`, keeping its mode,
+/// whatever its worktree holds
+pub fn stage_content(repo: &str, file: &str, content: &[u8]) -> anyhow::Result<()> {
+    // the staged content is already filtered, eg. its line endings converted
+    let mut hash_object = Command::new("git")
+        .args(["hash-object", "-w", "--stdin", "--no-filters"])
+        .current_dir(repo)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = hash_object.stdin.take() {
+        stdin.write_all(content)?;
+    }
+    let output = hash_object.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow::Error::msg(format!(
+            "could not store the fixed {}: {}",
+            file,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let hash = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let (_status, stdout, _stderr) = git_command(&["ls-files", "-s", "--", file], Some(repo))?;
+    let mode = stdout
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow::Error::msg(format!("{} is not staged", file)))?;
+    let (status, _stdout, stderr) = git_command(
+        &[
+            "update-index",
+            "--cacheinfo",
+            &format!("{},{},{}", mode, hash, file),
+        ],
+        Some(repo),
+    )?;
+    if !status.success() {
+        return Err(anyhow::Error::msg(format!(
+            "could not stage the fixed {}: {}",
+            file,
+            stderr.trim()
+        )));
+    }
+    Ok(())
+}
+
 pub fn changed_files(in_index: bool) -> anyhow::Result<Vec<String>> {
-    return if in_index {
+    if in_index {
         let (_status, stdout, _stderr) = git_command(
             &["diff", "--name-only", "--diff-filter=ACM", "--cached"],
             Some(&root()?),
@@ -191,17 +294,589 @@ pub fn changed_files(in_index: bool) -> anyhow::Result<Vec<String>> {
             Some(&root()?),
         )?;
         Ok(stdout.lines().map(|s| s.to_string()).collect())
-    };
+    }
+}
+
+/// Returns the files of the worktree modified since they were staged, or without being staged,
+/// relative to the root of the repository
+pub fn modified_files() -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(&["diff", "--name-only"], Some(&root()?))?;
+    Ok(stdout.lines().map(|s| s.to_string()).collect())
+}
+
+/// Returns the files newly added to the index, relative to the root of the repository
+pub fn added_files() -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(
+        &["diff", "--name-only", "--diff-filter=A", "--cached"],
+        Some(&root()?),
+    )?;
+    Ok(stdout.lines().map(|s| s.to_string()).collect())
+}
+
+/// Returns the size, in bytes, of the staged version of `file`, relative to the repository root
+pub fn staged_size(file: &str) -> anyhow::Result<u64> {
+    let (_status, stdout, _stderr) =
+        git_command(&["cat-file", "-s", &format!(":{}", file)], Some(&root()?))?;
+    Ok(stdout.trim().parse()?)
+}
+
+/// Returns the size, in bytes, of `file`, relative to the repository root, at `revision`
+pub fn size_at(revision: &str, file: &str) -> anyhow::Result<u64> {
+    let (_status, stdout, _stderr) = git_command(
+        &["cat-file", "-s", &format!("{}:{}", revision, file)],
+        Some(&root()?),
+    )?;
+    Ok(stdout.trim().parse()?)
+}
+
+/// Returns the diff of the index against `HEAD`, without context lines
+pub fn staged_diff() -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) = git_command(
+        &["diff", "--cached", "--no-color", "--no-ext-diff", "-U0"],
+        Some(&root()?),
+    )?;
+    Ok(stdout)
+}
+
+/// Returns the diff of `files`, relative to the root, between the index & `HEAD`, or the changes
+/// of `to` since it forked from `from` given a `range`
+pub fn diff<T: AsRef<str>>(range: Option<(&str, &str)>, files: &[T]) -> anyhow::Result<String> {
+    let revisions = range.map(|(from, to)| format!("{}...{}", from, to));
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+    match &revisions {
+        Some(revisions) => args.push(revisions),
+        None => args.push("--cached"),
+    }
+    args.push("--");
+    args.extend(files.iter().map(|f| f.as_ref()));
+    let (_status, stdout, _stderr) = git_command(&args, Some(&root()?))?;
+    Ok(stdout)
+}
+
+/// Returns the diff of the worktree against the index for `files`, relative to the root, with a
+/// single context line. `color` colors it as git does on a terminal.
+pub fn worktree_diff<T: AsRef<str>>(files: &[T], color: bool) -> anyhow::Result<String> {
+    let mut args = vec![
+        "diff",
+        if color {
+            "--color=always"
+        } else {
+            "--no-color"
+        },
+        "--no-ext-diff",
+        "-U1",
+        "--",
+    ];
+    args.extend(files.iter().map(|f| f.as_ref()));
+    let (_status, stdout, _stderr) = git_command(&args, Some(&root()?))?;
+    Ok(stdout)
+}
+
+/// Returns the diff of `to` since it forked from `from`, eg. the changes of a branch, without
+/// context lines
+pub fn range_diff(from: &str, to: &str) -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) = git_command(
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "-U0",
+            &format!("{}...{}", from, to),
+        ],
+        Some(&root()?),
+    )?;
+    Ok(stdout)
+}
+
+/// Returns the committer identity, as in `Jane Doe <jane@example.com> 1600000000 +0200`
+pub fn committer_ident() -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) = git_command(&["var", "GIT_COMMITTER_IDENT"], None)?;
+    Ok(stdout.trim().to_string())
+}
+
+/// Returns the name of the current branch, or `None` if `HEAD` is detached
+pub fn current_branch() -> anyhow::Result<Option<String>> {
+    let (_status, stdout, _stderr) = git_command(&["branch", "--show-current"], None)?;
+    let branch = stdout.trim();
+    Ok(if branch.is_empty() {
+        None
+    } else {
+        Some(branch.to_string())
+    })
 }
 
 /// Returns the root of the repository.
 /// If executed in /tmp/my-repo/src, returns /tmp/my-repo
 pub fn root() -> anyhow::Result<String> {
+    if is_bare()? {
+        // no worktree: the repository itself is the root
+        let (_status, stdout, _stderr) =
+            git_command(&["rev-parse", "--absolute-git-dir"] as &[&str], None)?;
+        return Ok(stdout.trim().to_string());
+    }
     let (_status, stdout, _stderr) =
         git_command(&["rev-parse", "--show-toplevel"] as &[&str], None)?;
-    let stdout = stdout
-        .strip_suffix("\n")
-        .expect("Could not strip git root output string. weird")
-        .to_string();
+    Ok(stdout.trim_end().to_string())
+}
+
+/// Tells whether the current repository is a bare one, such as those on servers
+pub fn is_bare() -> anyhow::Result<bool> {
+    let (_status, stdout, _stderr) =
+        git_command(&["rev-parse", "--is-bare-repository"] as &[&str], None)?;
+    Ok(stdout.trim() == "true")
+}
+
+/// Writes the index as a tree, and returns its hash: it changes along with the staged content
+pub fn write_tree() -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) = git_command(&["write-tree"], Some(&root()?))?;
+    Ok(stdout.trim().to_string())
+}
+
+/// Returns the files added, copied or modified between the commits `old` & `new`
+pub fn diff_files(old: &str, new: &str) -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(
+        &["diff", "--name-only", "--diff-filter=ACM", old, new],
+        None,
+    )?;
+    Ok(stdout.lines().map(|s| s.to_string()).collect())
+}
+
+/// Returns the files changed on `to` since it forked from `from`, eg. by a branch, relative to
+/// the root of the repository. `filter` selects the kinds of changes, as git's `--diff-filter`.
+pub fn range_files(from: &str, to: &str, filter: &str) -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(
+        &[
+            "diff",
+            "--name-only",
+            &format!("--diff-filter={}", filter),
+            &format!("{}...{}", from, to),
+        ],
+        Some(&root()?),
+    )?;
+    Ok(stdout.lines().map(|s| s.to_string()).collect())
+}
+
+/// Returns the files added, copied or modified by the commits reachable from `new` but from none
+/// of the remote-tracking branches, such as those of a branch pushed for the first time
+pub fn unpushed_files(new: &str) -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(
+        &[
+            "log",
+            "--name-only",
+            "--format=",
+            "--diff-filter=ACM",
+            new,
+            "--not",
+            "--remotes",
+        ],
+        Some(&root()?),
+    )?;
+    let mut files: Vec<String> = stdout
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|s| s.to_string())
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Returns the files added, copied or modified by the commits reachable from `new` only, such as
+/// those of a newly pushed branch
+pub fn new_commits_files(new: &str) -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(
+        &[
+            "log",
+            "--name-only",
+            "--format=",
+            "--diff-filter=ACM",
+            new,
+            "--not",
+            "--all",
+        ],
+        None,
+    )?;
+    let mut files: Vec<String> = stdout
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|s| s.to_string())
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Tells whether `ancestor` is an ancestor of `commit`, ie. whether going from the former to the
+/// latter is a fast-forward
+pub fn is_ancestor(ancestor: &str, commit: &str) -> anyhow::Result<bool> {
+    let (_status, stdout, _stderr) = git_command(
+        &["rev-list", "--count", &format!("{}..{}", commit, ancestor)],
+        None,
+    )?;
+    Ok(stdout.trim() == "0")
+}
+
+/// Returns the hash & signature status (`%G?`, eg. `G` for a good one, `N` for none) of the
+/// commits `revisions` designates
+pub fn signature_statuses<T: AsRef<str> + AsRef<OsStr> + Debug>(
+    revisions: &[T],
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut args = vec!["log", "--format=%H %G?"];
+    args.extend(revisions.iter().map(|r| AsRef::<str>::as_ref(r)));
+    let (_status, stdout, _stderr) = git_command(&args, None)?;
+    Ok(stdout
+        .lines()
+        .filter_map(|l| l.split_once(' '))
+        .map(|(hash, status)| (hash.to_string(), status.to_string()))
+        .collect())
+}
+
+/// Returns the hashes, author emails & messages of the commits designated by `revisions`
+pub fn commit_messages<T: AsRef<str> + AsRef<OsStr> + Debug>(
+    revisions: &[T],
+) -> anyhow::Result<Vec<(String, String, String)>> {
+    let mut args = vec!["log", "-z", "--format=%H%n%ae%n%B"];
+    args.extend(revisions.iter().map(|r| AsRef::<str>::as_ref(r)));
+    let (_status, stdout, _stderr) = git_command(&args, None)?;
+    Ok(stdout
+        .split('\0')
+        .filter_map(|commit| {
+            let mut lines = commit.splitn(3, '\n');
+            Some((
+                lines.next().filter(|h| !h.is_empty())?.to_string(),
+                lines.next()?.to_string(),
+                lines.next().unwrap_or("").to_string(),
+            ))
+        })
+        .collect())
+}
+
+/// Adds `trailer`, eg. `Signed-off-by: Jane Doe <jane@example.com>`, to the commit message in
+/// `file`, unless it has one with the same key already
+pub fn add_trailer(file: &str, trailer: &str) -> anyhow::Result<()> {
+    git_command(
+        &[
+            "interpret-trailers",
+            "--in-place",
+            "--if-exists",
+            "doNothing",
+            "--trailer",
+            trailer,
+            file,
+        ],
+        None,
+    )?;
+    Ok(())
+}
+
+/// Returns the content of `path` at `revision`
+pub fn show(revision: &str, path: &str) -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) =
+        git_command(&["show", &format!("{}:{}", revision, path)], None)?;
     Ok(stdout)
 }
+
+/// Returns the files tracked in `repo`, relative to it
+pub fn ls_files(repo: &str) -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(&["ls-files"], Some(repo))?;
+    Ok(stdout.lines().map(|s| s.to_string()).collect())
+}
+
+/// Returns the tags of `repo`
+pub fn tags(repo: &str) -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(&["tag", "--list"], Some(repo))?;
+    Ok(stdout.lines().map(|s| s.to_string()).collect())
+}
+
+/// Returns the paths of the submodules of `repo`, recursively, relative to it
+pub fn submodules(repo: &str) -> anyhow::Result<Vec<String>> {
+    let (_status, stdout, _stderr) = git_command(
+        &[
+            "submodule",
+            "--quiet",
+            "foreach",
+            "--recursive",
+            "echo \"$displaypath\"",
+        ],
+        Some(repo),
+    )?;
+    Ok(stdout.lines().map(|s| s.to_string()).collect())
+}
+
+/// Returns the git directory shared by all the worktrees of `repo`: its `.git` directory, even
+/// from a linked worktree
+pub fn common_dir(repo: &str) -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) = git_command(&["rev-parse", "--git-common-dir"], Some(repo))?;
+    Ok(Path::new(repo).join(stdout.trim()).display().to_string())
+}
+
+/// Returns the directory git looks for the hooks of `repo` in
+pub fn hooks_dir(repo: &str) -> anyhow::Result<String> {
+    let (_status, stdout, _stderr) =
+        git_command(&["rev-parse", "--git-path", "hooks"], Some(repo))?;
+    Ok(Path::new(repo).join(stdout.trim()).display().to_string())
+}
+
+/// Returns the value of the git option `key` for `repo`, or the global one without a repo
+pub fn get_config(repo: Option<&str>, key: &str) -> anyhow::Result<Option<String>> {
+    require_version(
+        GitVersion::new(2, 18, 0),
+        "reading the git options of git-hooks",
+    )?;
+    let mut args = vec!["config"];
+    if repo.is_none() {
+        args.push("--global");
+    }
+    // an unset option is not an error
+    args.extend(["--default", "", "--get", key]);
+    let (_status, stdout, _stderr) = git_command(&args, repo)?;
+    let value = stdout.trim();
+    Ok(if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    })
+}
+
+/// Sets the `key` option of `repo`'s configuration to `value`
+pub fn set_config(repo: &str, key: &str, value: &str) -> anyhow::Result<()> {
+    git_command(&["config", key, value], Some(repo))?;
+    Ok(())
+}
+
+/// Sets the `key` option of the user's global configuration to `value`
+pub fn set_global_config(key: &str, value: &str) -> anyhow::Result<()> {
+    git_command(&["config", "--global", key, value], None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::git::{
+        add, changed_files, checkout, clone, common_dir, git_command, hash_object, head, hooks_dir,
+        init, root, stage_content, staged_content, submodules, version, GitVersion,
+    };
+    use std::env::{current_dir, set_current_dir};
+    use std::fs::File;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    fn setup() -> TempDir {
+        let _ = pretty_env_logger::try_init();
+        TempDir::new("git-hooks-tests").expect("could not create temp dir")
+    }
+
+    #[test]
+    fn test_git_version() {
+        assert_eq!(
+            GitVersion::parse("git version 2.39.2 (Apple Git-143)\n"),
+            Some(GitVersion::new(2, 39, 2))
+        );
+        assert_eq!(
+            GitVersion::parse("git version 2.45.1.windows.1"),
+            Some(GitVersion::new(2, 45, 1))
+        );
+        assert_eq!(
+            GitVersion::parse("git version 2.45.0-rc1"),
+            Some(GitVersion::new(2, 45, 0))
+        );
+        assert_eq!(GitVersion::parse("hub version 2.14.2"), None);
+        assert!(GitVersion::new(2, 9, 0) < GitVersion::new(2, 24, 0));
+        assert!(version().unwrap() >= GitVersion::new(2, 0, 0));
+    }
+
+    #[test]
+    fn test_git_command() {
+        let _ = setup();
+        let r = git_command(&["--version"], None);
+        assert!(r.is_ok());
+        let (s, out, _err) = r.unwrap();
+        assert!(s.success());
+        assert!(out.starts_with("git version "));
+    }
+
+    #[test]
+    fn test_clone() {
+        let dir = setup();
+        let r = clone(".", dir.path().display().to_string());
+        assert!(r.is_ok());
+        let p = r.unwrap();
+        assert_eq!(p, dir.path().display().to_string());
+    }
+
+    #[test]
+    fn test_checkout() {
+        let dir = setup();
+        let _ = clone(".", dir.path().display().to_string());
+        let r = checkout(
+            "99586a59496151167dc730c62d5405d7a6401bf6",
+            dir.path().display().to_string().as_str(),
+        );
+        assert!(r.is_ok());
+        let r = git_command(
+            &["rev-parse", "HEAD"],
+            Some(dir.path().display().to_string().as_str()),
+        );
+        assert!(r.is_ok());
+        let (s, out, _err) = r.unwrap();
+        assert!(s.success());
+        assert_eq!(out.trim(), "99586a59496151167dc730c62d5405d7a6401bf6"); // hash of the v0.3.0 tag
+    }
+
+    #[test]
+    fn test_adding_files() {
+        let dir = setup();
+        let _ = clone(".", dir.path().display().to_string());
+        let f = File::create(dir.path().join("tests.txt"));
+        assert!(f.is_ok());
+        let old_dir = current_dir().expect("could not unwrap current_dir");
+        set_current_dir(Path::new(dir.path().display().to_string().as_str()))
+            .expect("could not cd in temp cloned dir");
+        let r = changed_files(false);
+        assert!(r.is_ok());
+        let files = r.unwrap();
+        assert!(files.contains(&"tests.txt".to_string()));
+        let r = add(&["tests.txt"]);
+        assert!(r.is_ok());
+        let r = changed_files(true);
+        assert!(r.is_ok());
+        let files = r.unwrap();
+        assert!(files.contains(&"tests.txt".to_string()));
+        set_current_dir(old_dir).expect("could not cd back to old dir");
+    }
+
+    #[test]
+    fn test_root() {
+        let dir = setup();
+        let _ = clone(
+            "https://github.com/paulollivier/git-hooks",
+            dir.path().display().to_string(),
+        );
+        let old_dir = current_dir().expect("could not get current dir");
+        set_current_dir(dir.path().join("src")).expect("could not change current dir");
+        let r = root();
+        assert!(r.is_ok());
+        let d = r.unwrap();
+        assert_eq!(dir.path().display().to_string(), d);
+        set_current_dir(old_dir).expect("could not cd back to old dir");
+    }
+
+    #[test]
+    fn test_head_and_hash_object() {
+        let dir = setup();
+        let repo = dir.path().display().to_string();
+        init(Some(&repo)).expect("could not init repo");
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, "hello\n").expect("could not write file");
+        let r = hash_object(&file.display().to_string());
+        assert!(r.is_ok());
+        // well-known hash of the "hello\n" blob
+        assert_eq!(r.unwrap(), "ce013625030ba8dba906f756967f9e9ca394464a");
+        git_command(&["add", "a.txt"], Some(&repo)).expect("could not add file");
+        git_command(
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-m",
+                "init",
+            ],
+            Some(&repo),
+        )
+        .expect("could not commit");
+        let r = head(&repo);
+        assert!(r.is_ok());
+        assert_eq!(r.unwrap().len(), 40);
+    }
+
+    #[test]
+    fn test_submodules() {
+        let dir = setup();
+        let lib = dir.path().join("lib").display().to_string();
+        let repo = dir.path().join("repo").display().to_string();
+        std::fs::create_dir(&lib).expect("could not create dir");
+        std::fs::create_dir(&repo).expect("could not create dir");
+        init(Some(&lib)).expect("could not init repo");
+        init(Some(&repo)).expect("could not init repo");
+        let identity = ["-c", "user.name=test", "-c", "user.email=test@example.com"];
+        let mut commit = identity.to_vec();
+        commit.extend(&["commit", "--allow-empty", "-m", "init"]);
+        git_command(&commit, Some(&lib)).expect("could not commit");
+        let mut add_submodule = identity.to_vec();
+        add_submodule.extend(&[
+            "-c",
+            "protocol.file.allow=always",
+            "submodule",
+            "add",
+            &lib,
+            "vendor/lib",
+        ]);
+        git_command(&add_submodule, Some(&repo)).expect("could not add submodule");
+        let r = submodules(&repo);
+        assert!(r.is_ok());
+        assert_eq!(r.unwrap(), vec!["vendor/lib"]);
+        let r = hooks_dir(&format!("{}/vendor/lib", repo));
+        assert!(r.is_ok());
+        assert!(Path::new(&r.unwrap()).ends_with(".git/modules/vendor/lib/hooks"));
+        assert!(Path::new(&hooks_dir(&repo).unwrap()).ends_with(".git/hooks"));
+    }
+
+    #[test]
+    fn test_worktree() {
+        let dir = setup();
+        let repo = dir.path().join("repo").display().to_string();
+        let worktree = dir.path().join("wt").display().to_string();
+        std::fs::create_dir(&repo).expect("could not create dir");
+        init(Some(&repo)).expect("could not init repo");
+        git_command(
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "--allow-empty",
+                "-m",
+                "init",
+            ],
+            Some(&repo),
+        )
+        .expect("could not commit");
+        git_command(&["worktree", "add", &worktree], Some(&repo)).expect("could not add worktree");
+        let git_dir = Path::new(&repo).join(".git").canonicalize().unwrap();
+        let r = common_dir(&worktree);
+        assert!(r.is_ok());
+        assert_eq!(Path::new(&r.unwrap()).canonicalize().unwrap(), git_dir);
+        let r = hooks_dir(&worktree);
+        assert!(r.is_ok());
+        assert_eq!(
+            Path::new(&r.unwrap()).canonicalize().ok(),
+            git_dir.join("hooks").canonicalize().ok()
+        );
+    }
+
+    #[test]
+    fn test_stage_content() {
+        let dir = setup();
+        let repo = dir.path().display().to_string();
+        init(Some(&repo)).unwrap();
+        std::fs::write(dir.path().join("a.sh"), "echo a  \n").unwrap();
+        crate::utils::set_mode(&dir.path().join("a.sh"), 0o755).unwrap();
+        git_command(&["add", "a.sh"], Some(&repo)).unwrap();
+        // not staged, and to stay so
+        std::fs::write(dir.path().join("a.sh"), "echo a  \necho b\n").unwrap();
+        assert_eq!(staged_content(&repo, "a.sh").unwrap(), b"echo a  \n");
+        stage_content(&repo, "a.sh", b"echo a\n").unwrap();
+        assert_eq!(staged_content(&repo, "a.sh").unwrap(), b"echo a\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.sh")).unwrap(),
+            "echo a  \necho b\n"
+        );
+        let (_status, stdout, _stderr) =
+            git_command(&["ls-files", "-s", "a.sh"], Some(&repo)).unwrap();
+        assert!(stdout.starts_with("100755 "));
+        assert!(staged_content(&repo, "missing.sh").is_err());
+    }
+}
@@ -0,0 +1,820 @@
+//! `git-hooks import`: conversion of the configurations of other hooks managers into a
+//! `.hooks.yml`.
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::builtins::{Builtin, BUILTIN_PREFIX};
+use crate::error::GitHooksError;
+use crate::utils::shell_quote;
+use crate::{ExternalHookRepo, Hook, HookConfig, HookEvent};
+
+/// The configuration converted from another hooks manager's, and what could not be converted
+#[derive(Debug, Default)]
+pub struct Import {
+    pub config: HookConfig,
+    /// Describes the settings left out of `config`, or converted loosely
+    pub notes: Vec<String>,
+}
+
+/// The default configuration file of pre-commit, at the root of the repository
+pub const PRE_COMMIT_CONFIG: &str = ".pre-commit-config.yaml";
+
+/// The repo of the pre-commit hooks most of the builtin ones mirror
+pub(crate) const PRE_COMMIT_HOOKS_URL: &str = "https://github.com/pre-commit/pre-commit-hooks";
+
+/// A `.pre-commit-config.yaml`
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct PreCommitConfig {
+    repos: Vec<PreCommitRepo>,
+    default_stages: Option<Vec<String>>,
+    files: Option<String>,
+    exclude: Option<String>,
+    fail_fast: bool,
+}
+
+/// A repo of a `.pre-commit-config.yaml`: a URL, or `local` or `meta`
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct PreCommitRepo {
+    repo: String,
+    rev: Option<String>,
+    hooks: Vec<PreCommitHook>,
+}
+
+/// A hook of a pre-commit repo. Only local hooks hold what they run.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct PreCommitHook {
+    id: String,
+    entry: Option<String>,
+    language: Option<String>,
+    files: Option<String>,
+    exclude: Option<String>,
+    types: Option<Vec<String>>,
+    types_or: Option<Vec<String>>,
+    stages: Option<Vec<String>>,
+    args: Option<Vec<String>>,
+    pass_filenames: Option<bool>,
+    always_run: Option<bool>,
+}
+
+/// Converts a pre-commit configuration, `.pre-commit-config.yaml`.
+/// Its repos become hook repos pinned to their `rev`, its hooks are enabled with their `files` &
+/// `stages`, and its local hooks become inline scripts. The hooks of pre-commit's own repo which
+/// git-hooks has builtin need no repo.
+pub fn pre_commit(content: &str) -> anyhow::Result<Import> {
+    let pre_commit: PreCommitConfig = serde_yaml::from_str(content)
+        .map_err(|e| GitHooksError::Config(format!("invalid pre-commit config: {}", e)))?;
+    let mut import = Import::default();
+    if let Some(files) = &pre_commit.files {
+        import.notes.push(format!(
+            "the global files regex {} is not supported, set it on the hooks",
+            files
+        ));
+    }
+    if let Some(exclude) = &pre_commit.exclude {
+        import.notes.push(format!(
+            "the global exclude regex {} is not supported",
+            exclude
+        ));
+    }
+    if pre_commit.fail_fast {
+        import
+            .notes
+            .push("fail_fast is not supported: all the hooks run".to_string());
+    }
+    for repo in &pre_commit.repos {
+        match repo.repo.as_str() {
+            "meta" => {
+                for hook in &repo.hooks {
+                    import
+                        .notes
+                        .push(format!("{}: the meta hooks are not supported", hook.id));
+                }
+                continue;
+            }
+            "local" => {}
+            url => {
+                if repo
+                    .hooks
+                    .iter()
+                    .any(|h| builtin_name(url, &h.id).is_none())
+                {
+                    import.notes.push(format!(
+                        "{}: pre-commit repos only provide hooks to git-hooks if they also have a hooks.yml",
+                        url
+                    ));
+                    import.config.repos.push(ExternalHookRepo {
+                        url: url.to_string(),
+                        version: repo.rev.clone(),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        for hook in &repo.hooks {
+            let converted = convert_pre_commit_hook(
+                &repo.repo,
+                hook,
+                pre_commit.default_stages.as_deref(),
+                &mut import.notes,
+            );
+            import.config.hooks.push(converted);
+        }
+    }
+    Ok(import)
+}
+
+/// Returns the name of the builtin hook mirroring the hook `id` of the pre-commit repo `url`,
+/// if there is one
+fn builtin_name(url: &str, id: &str) -> Option<String> {
+    let name = format!("{}{}", BUILTIN_PREFIX, id);
+    let mirrored = Builtin::from_name(&name).and_then(|b| b.pre_commit_id()) == Some(id);
+    if url.trim_end_matches(".git") == PRE_COMMIT_HOOKS_URL && mirrored {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Converts a hook of the pre-commit repo `repo`, noting what could not be converted
+fn convert_pre_commit_hook(
+    repo: &str,
+    hook: &PreCommitHook,
+    default_stages: Option<&[String]>,
+    notes: &mut Vec<String>,
+) -> Hook {
+    let mut converted = Hook {
+        name: builtin_name(repo, &hook.id).unwrap_or_else(|| hook.id.clone()),
+        on_file_regex: hook.files.as_ref().map(|files| vec![files.clone()]),
+        ..Default::default()
+    };
+    if let Some(stages) = hook.stages.as_deref().or(default_stages) {
+        let mut events = Vec::new();
+        for stage in stages {
+            match pre_commit_event(stage) {
+                Some(event) => events.push(event),
+                None => notes.push(format!(
+                    "{}: the {} stage has no git-hooks event",
+                    hook.id, stage
+                )),
+            }
+        }
+        if !events.is_empty() {
+            converted.on_event = Some(events);
+        }
+    }
+    if let Some(exclude) = &hook.exclude {
+        notes.push(format!(
+            "{}: the exclude regex {} is not supported",
+            hook.id, exclude
+        ));
+    }
+    for types in [&hook.types, &hook.types_or].iter().copied().flatten() {
+        notes.push(format!(
+            "{}: the file types {} are not supported, give it a files regex",
+            hook.id,
+            types.join(", ")
+        ));
+    }
+    let args = hook.args.clone().unwrap_or_default();
+    if repo != "local" {
+        if !args.is_empty() {
+            notes.push(format!(
+                "{}: the args {} are not supported on repo hooks",
+                hook.id,
+                args.join(" ")
+            ));
+        }
+        return converted;
+    }
+    match &hook.entry {
+        Some(entry) => {
+            if let Some(language) = hook
+                .language
+                .as_deref()
+                .filter(|l| !["system", "script"].contains(l))
+            {
+                notes.push(format!(
+                    "{}: its {} language is not set up, it runs with the tools of the system",
+                    hook.id, language
+                ));
+            }
+            let mut command = entry.clone();
+            for arg in &args {
+                command.push(' ');
+                command.push_str(&shell_quote(arg));
+            }
+            converted.script = Some(if hook.pass_filenames.unwrap_or(true) {
+                if hook.always_run.unwrap_or(false) {
+                    format!("{} $GIT_HOOKS_CHANGED_FILES", command)
+                } else {
+                    // pre-commit skips the hooks without matching files
+                    unless_no_changed_files(&format!("{} $GIT_HOOKS_CHANGED_FILES", command))
+                }
+            } else {
+                command
+            });
+        }
+        None => notes.push(format!("{}: local hook without an entry", hook.id)),
+    }
+    converted
+}
+
+/// Returns the event of the pre-commit stage `stage`, named after the events or, before
+/// pre-commit 3.2, without their `pre-` prefix
+fn pre_commit_event(stage: &str) -> Option<HookEvent> {
+    match stage {
+        "commit" => Some(HookEvent::PreCommit),
+        "merge-commit" => Some(HookEvent::PreMergeCommit),
+        "push" => Some(HookEvent::PrePush),
+        stage => HookEvent::from_kebab_case(stage),
+    }
+}
+
+/// Returns the shell command line running `command` only if there are changed files to run it on
+fn unless_no_changed_files(command: &str) -> String {
+    format!("[ -z \"$GIT_HOOKS_CHANGED_FILES\" ] || {}", command)
+}
+
+/// The directory of the husky hooks, at the root of the repository
+pub const HUSKY_DIR: &str = ".husky";
+
+/// A `package.json`, as far as husky 4 is concerned
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct PackageJson {
+    husky: Option<HuskyConfig>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct HuskyConfig {
+    /// The command line of each event
+    hooks: BTreeMap<String, String>,
+}
+
+/// Converts the husky hooks of the repository at `root`: the scripts of its `.husky` directory,
+/// then the `husky.hooks` of its `package.json`, used up to husky 4. Each of them becomes an
+/// inline script, named `husky-<event>`.
+pub fn husky(root: &Path) -> anyhow::Result<Import> {
+    let mut import = Import::default();
+    let dir = root.join(HUSKY_DIR);
+    if dir.is_dir() {
+        let mut scripts: Vec<_> = std::fs::read_dir(&dir)?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|entry| entry.path())
+            // `_` holds husky's own scripts
+            .filter(|path| path.is_file())
+            .collect();
+        scripts.sort();
+        for path in scripts {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if name.starts_with('.') {
+                continue;
+            }
+            let script: String = std::fs::read_to_string(&path)?
+                .lines()
+                // husky 5 to 8 scripts source husky's, which is not there anymore
+                .filter(|line| !(line.trim_start().starts_with('.') && line.contains("_/husky.sh")))
+                .map(|line| format!("{}\n", line))
+                .collect();
+            add_husky_hook(&mut import, &name, script);
+        }
+    }
+    let package_json = root.join("package.json");
+    if package_json.is_file() {
+        let package: PackageJson =
+            serde_json::from_str(&std::fs::read_to_string(&package_json)?)
+                .map_err(|e| GitHooksError::Config(format!("invalid package.json: {}", e)))?;
+        for (name, command) in package.husky.unwrap_or_default().hooks {
+            let script = if command.contains("HUSKY_GIT_PARAMS") {
+                // husky 4 gave the arguments of the hook through the environment
+                format!("export HUSKY_GIT_PARAMS=\"$*\"\n{}\n", command)
+            } else {
+                format!("{}\n", command)
+            };
+            add_husky_hook(&mut import, &name, script);
+        }
+    }
+    if import.config.hooks.is_empty() && import.notes.is_empty() {
+        return Err(anyhow::Error::msg(format!(
+            "no husky hooks in {} nor package.json",
+            dir.display()
+        )));
+    }
+    Ok(import)
+}
+
+/// Adds the husky hook of the event `name`, running `script`, unless it cannot be converted
+fn add_husky_hook(import: &mut Import, name: &str, script: String) {
+    let event = match HookEvent::from_kebab_case(name) {
+        Some(event) => event,
+        None => {
+            import
+                .notes
+                .push(format!("husky's {} hook has no git-hooks event", name));
+            return;
+        }
+    };
+    let hook_name = format!("husky-{}", name);
+    if import.config.hooks.iter().any(|h| h.name == hook_name) {
+        import.notes.push(format!(
+            "husky's {} hook of package.json is left out: .husky has one already",
+            name
+        ));
+        return;
+    }
+    if script
+        .lines()
+        .all(|l| l.trim().is_empty() || l.starts_with("#!"))
+    {
+        import
+            .notes
+            .push(format!("husky's {} hook runs nothing", name));
+        return;
+    }
+    import.config.hooks.push(Hook {
+        name: hook_name,
+        on_event: Some(vec![event]),
+        script: Some(script),
+        ..Default::default()
+    });
+}
+
+/// The configuration files of lefthook, at the root of the repository, by order of precedence
+pub const LEFTHOOK_CONFIGS: &[&str] = &[
+    "lefthook.yml",
+    ".lefthook.yml",
+    "lefthook.yaml",
+    ".lefthook.yaml",
+];
+
+/// The hooks of an event of a `lefthook.yml`
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct LefthookEvent {
+    parallel: bool,
+    piped: bool,
+    /// Run in the order of their names, as lefthook does
+    commands: BTreeMap<String, LefthookCommand>,
+    scripts: BTreeMap<String, LefthookScript>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct LefthookCommand {
+    run: Option<String>,
+    glob: Option<OneOrMany>,
+    root: Option<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// A script of lefthook's `source_dir`, `.lefthook/<event>/<name>`
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct LefthookScript {
+    runner: Option<String>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// A value lefthook reads from either a string or a list of them
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn values(&self) -> Vec<&str> {
+        match self {
+            OneOrMany::One(value) => vec![value.as_str()],
+            OneOrMany::Many(values) => values.iter().map(|v| v.as_str()).collect(),
+        }
+    }
+}
+
+/// Converts a lefthook configuration, `lefthook.yml`. Its commands & scripts become inline
+/// scripts, with their globs as file regexes and their `root`s kept; the settings git-hooks has
+/// no equivalent for, such as `parallel` or `exclude`, are noted.
+pub fn lefthook(content: &str) -> anyhow::Result<Import> {
+    let lefthook: BTreeMap<String, serde_yaml::Value> = serde_yaml::from_str(content)
+        .map_err(|e| GitHooksError::Config(format!("invalid lefthook config: {}", e)))?;
+    let mut import = Import::default();
+    let source_dir = lefthook
+        .get("source_dir")
+        .and_then(|d| d.as_str())
+        .unwrap_or(".lefthook/")
+        .trim_end_matches('/')
+        .to_string();
+    for (key, value) in lefthook {
+        let is_event = value.as_mapping().is_some_and(|m| {
+            m.contains_key(&"commands".into()) || m.contains_key(&"scripts".into())
+        });
+        if !is_event {
+            if key != "source_dir" {
+                import
+                    .notes
+                    .push(format!("the {} setting is not supported", key));
+            }
+            continue;
+        }
+        let event = match HookEvent::from_kebab_case(&key) {
+            Some(event) => event,
+            None => {
+                import
+                    .notes
+                    .push(format!("the {} hook has no git-hooks event", key));
+                continue;
+            }
+        };
+        let hooks: LefthookEvent = serde_yaml::from_value(value)
+            .map_err(|e| GitHooksError::Config(format!("invalid lefthook {} hook: {}", key, e)))?;
+        if hooks.parallel {
+            import.notes.push(format!(
+                "{}: parallel is not supported, the hooks run in sequence",
+                key
+            ));
+        }
+        if hooks.piped {
+            import.notes.push(format!(
+                "{}: piped is not supported, all the hooks run even if one fails",
+                key
+            ));
+        }
+        for option in hooks.other.keys() {
+            import
+                .notes
+                .push(format!("{}: the {} option is not supported", key, option));
+        }
+        for (name, command) in &hooks.commands {
+            let id = format!("{}.{}", key, name);
+            let run = match &command.run {
+                Some(run) => run,
+                None => {
+                    import.notes.push(format!("{}: command without a run", id));
+                    continue;
+                }
+            };
+            // stage_fixed is always on: git-hooks re-adds the staged files the hooks modify
+            for option in command.other.keys().filter(|o| *o != "stage_fixed") {
+                import
+                    .notes
+                    .push(format!("{}: the {} option is not supported", id, option));
+            }
+            let mut script = lefthook_command(run, &id, &mut import.notes);
+            if run.contains("{staged_files}") || run.contains("{push_files}") {
+                // lefthook skips the commands without files to run on
+                script = unless_no_changed_files(&script);
+            }
+            let hook = Hook {
+                name: name.clone(),
+                on_event: Some(vec![event]),
+                on_file_regex: command
+                    .glob
+                    .as_ref()
+                    .map(|glob| glob.values().into_iter().map(glob_regex).collect()),
+                script: Some(script),
+                root: command
+                    .root
+                    .as_ref()
+                    .map(|root| root.trim_end_matches('/').to_string()),
+                ..Default::default()
+            };
+            add_lefthook_hook(&mut import.config, hook, event);
+        }
+        for (name, script) in &hooks.scripts {
+            let id = format!("{}.{}", key, name);
+            for option in script.other.keys() {
+                import
+                    .notes
+                    .push(format!("{}: the {} option is not supported", id, option));
+            }
+            let path = shell_quote(&format!("{}/{}/{}", source_dir, key, name));
+            let hook = Hook {
+                name: name.clone(),
+                on_event: Some(vec![event]),
+                script: Some(match &script.runner {
+                    Some(runner) => format!("{} {} \"$@\"", runner, path),
+                    None => format!("{} \"$@\"", path),
+                }),
+                ..Default::default()
+            };
+            add_lefthook_hook(&mut import.config, hook, event);
+        }
+    }
+    Ok(import)
+}
+
+/// Replaces the templates of the lefthook command line `run` with the values git-hooks gives the
+/// inline scripts, noting the ones it has no equivalent for
+fn lefthook_command(run: &str, id: &str, notes: &mut Vec<String>) -> String {
+    let mut command = run
+        .replace("{staged_files}", "$GIT_HOOKS_CHANGED_FILES")
+        .replace("{all_files}", "$GIT_HOOKS_FILES")
+        .replace("{0}", "$*");
+    for i in 1..10 {
+        command = command.replace(&format!("{{{}}}", i), &format!("${}", i));
+    }
+    if command.contains("{push_files}") {
+        notes.push(format!(
+            "{}: {{push_files}} is given the staged files instead",
+            id
+        ));
+        command = command.replace("{push_files}", "$GIT_HOOKS_CHANGED_FILES");
+    }
+    if command.contains("{files}") {
+        notes.push(format!(
+            "{}: the files option is not supported, {{files}} is given the staged files instead",
+            id
+        ));
+        command = command.replace("{files}", "$GIT_HOOKS_CHANGED_FILES");
+    }
+    command
+}
+
+/// Adds `hook`, converted for `event`, to `config`. A hook of another event with the same name
+/// gets `event` too if it runs the same; otherwise `hook` is named after `event`.
+fn add_lefthook_hook(config: &mut HookConfig, mut hook: Hook, event: HookEvent) {
+    match config.hooks.iter_mut().find(|h| h.name == hook.name) {
+        Some(h)
+            if h.script == hook.script
+                && h.on_file_regex == hook.on_file_regex
+                && h.root == hook.root =>
+        {
+            h.on_event.get_or_insert_with(Vec::new).push(event);
+        }
+        Some(_) => {
+            hook.name = format!("{}-{}", event.to_kebab_case(), hook.name);
+            config.hooks.push(hook);
+        }
+        None => config.hooks.push(hook),
+    }
+}
+
+/// Converts a glob of lefthook, whose `*` also matches `/`, to a regex
+fn glob_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    let mut in_braces = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    // `**/` also matches no directory at all
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(.*/)?");
+                        continue;
+                    }
+                }
+                regex.push_str(".*");
+            }
+            '?' => regex.push('.'),
+            '{' => {
+                in_braces = true;
+                regex.push('(');
+            }
+            '}' if in_braces => {
+                in_braces = false;
+                regex.push(')');
+            }
+            ',' if in_braces => regex.push('|'),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    regex.push(c);
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::import::{glob_regex, husky, lefthook, pre_commit};
+    use crate::HookEvent;
+
+    #[test]
+    fn test_pre_commit() {
+        let import = pre_commit(
+            r#"
+default_stages: [commit]
+repos:
+  - repo: https://github.com/pre-commit/pre-commit-hooks
+    rev: v4.5.0
+    hooks:
+      - id: trailing-whitespace
+      - id: check-yaml
+        files: '\.ya?ml$'
+  - repo: https://github.com/doublify/pre-commit-rust
+    rev: v1.0
+    hooks:
+      - id: fmt
+        stages: [push, manual]
+        args: ['--', '--check']
+  - repo: local
+    hooks:
+      - id: shellcheck
+        name: shellcheck
+        entry: shellcheck -x
+        language: system
+        types: [shell]
+      - id: version
+        entry: ./check version.txt
+        language: script
+        pass_filenames: false
+"#,
+        )
+        .unwrap();
+        let conf = import.config;
+        assert_eq!(conf.repos.len(), 1);
+        assert_eq!(
+            conf.repos[0].url,
+            "https://github.com/doublify/pre-commit-rust"
+        );
+        assert_eq!(conf.repos[0].version, Some("v1.0".to_string()));
+        let names: Vec<&str> = conf.hooks.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "builtin:trailing-whitespace",
+                "builtin:check-yaml",
+                "fmt",
+                "shellcheck",
+                "version"
+            ]
+        );
+        assert_eq!(conf.hooks[0].on_event, Some(vec![HookEvent::PreCommit]));
+        assert_eq!(
+            conf.hooks[1].on_file_regex,
+            Some(vec![r"\.ya?ml$".to_string()])
+        );
+        assert_eq!(
+            conf.hooks[2].on_event,
+            Some(vec![HookEvent::PrePush, HookEvent::Manual])
+        );
+        assert_eq!(
+            conf.hooks[3].script.as_deref(),
+            Some("[ -z \"$GIT_HOOKS_CHANGED_FILES\" ] || shellcheck -x $GIT_HOOKS_CHANGED_FILES")
+        );
+        assert_eq!(conf.hooks[4].script.as_deref(), Some("./check version.txt"));
+        // the rust repo, fmt's args & shellcheck's types
+        assert_eq!(import.notes.len(), 3);
+    }
+
+    #[test]
+    fn test_pre_commit_other_hooks() {
+        let import = pre_commit(
+            r#"
+repos:
+  - repo: https://github.com/pre-commit/pre-commit-hooks
+    rev: v4.5.0
+    hooks:
+      - id: check-executables-have-shebangs
+      - id: end-of-file-fixer
+  - repo: meta
+    hooks:
+      - id: check-useless-excludes
+"#,
+        )
+        .unwrap();
+        // not all of its hooks are builtin
+        assert_eq!(import.config.repos.len(), 1);
+        assert_eq!(import.config.hooks[1].name, "builtin:end-of-file-fixer");
+        assert_eq!(import.config.hooks.len(), 2);
+        assert!(pre_commit("repos: {}").is_err());
+    }
+
+    #[test]
+    fn test_husky() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let husky_dir = dir.path().join(".husky");
+        std::fs::create_dir_all(husky_dir.join("_")).unwrap();
+        std::fs::write(husky_dir.join("_").join("husky.sh"), "").unwrap();
+        std::fs::write(
+            husky_dir.join("pre-commit"),
+            "#!/usr/bin/env sh\n. \"$(dirname -- \"$0\")/_/husky.sh\"\n\nnpx lint-staged\n",
+        )
+        .unwrap();
+        std::fs::write(husky_dir.join("post-checkout"), "npm ci\n").unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "app", "husky": {"hooks": {
+                "pre-commit": "npm test",
+                "commit-msg": "commitlint -E HUSKY_GIT_PARAMS"
+            }}}"#,
+        )
+        .unwrap();
+        let import = husky(dir.path()).unwrap();
+        let hooks = &import.config.hooks;
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(hooks[0].name, "husky-pre-commit");
+        assert_eq!(
+            hooks[0].script.as_deref(),
+            Some("#!/usr/bin/env sh\n\nnpx lint-staged\n")
+        );
+        assert_eq!(hooks[1].name, "husky-commit-msg");
+        assert_eq!(hooks[1].on_event, Some(vec![HookEvent::CommitMsg]));
+        assert!(hooks[1]
+            .script
+            .as_deref()
+            .unwrap()
+            .starts_with("export HUSKY_GIT_PARAMS=\"$*\"\n"));
+        // post-checkout, and package.json's pre-commit
+        assert_eq!(import.notes.len(), 2);
+        assert!(husky(&dir.path().join(".husky").join("_")).is_err());
+    }
+
+    #[test]
+    fn test_lefthook() {
+        let import = lefthook(
+            r#"
+colors: false
+pre-commit:
+  parallel: true
+  commands:
+    lint:
+      glob: "*.{js,ts}"
+      root: frontend/
+      run: npx eslint {staged_files}
+      stage_fixed: true
+    test:
+      run: npm test
+      tags: backend
+  scripts:
+    "check.sh":
+      runner: bash
+pre-push:
+  commands:
+    test:
+      run: npm test
+      tags: backend
+commit-msg:
+  commands:
+    commitlint:
+      run: npx commitlint --edit {1}
+post-checkout:
+  commands:
+    install:
+      run: npm ci
+"#,
+        )
+        .unwrap();
+        let hooks = &import.config.hooks;
+        let names: Vec<&str> = hooks.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["commitlint", "lint", "test", "check.sh"]);
+        assert_eq!(hooks[0].script.as_deref(), Some("npx commitlint --edit $1"));
+        assert_eq!(
+            hooks[1].on_file_regex,
+            Some(vec![r"^.*\.(js|ts)$".to_string()])
+        );
+        assert_eq!(hooks[1].root.as_deref(), Some("frontend"));
+        assert_eq!(
+            hooks[1].script.as_deref(),
+            Some("[ -z \"$GIT_HOOKS_CHANGED_FILES\" ] || npx eslint $GIT_HOOKS_CHANGED_FILES")
+        );
+        assert_eq!(
+            hooks[2].on_event,
+            Some(vec![HookEvent::PreCommit, HookEvent::PrePush])
+        );
+        assert_eq!(
+            hooks[3].script.as_deref(),
+            Some("bash .lefthook/pre-commit/check.sh \"$@\"")
+        );
+        // colors, parallel, the tags twice & post-checkout
+        assert_eq!(import.notes.len(), 5);
+    }
+
+    #[test]
+    fn test_glob_regex() {
+        assert_eq!(glob_regex("*.rs"), r"^.*\.rs$");
+        assert_eq!(glob_regex("src/**/*.rs"), r"^src/(.*/)?.*\.rs$");
+        assert_eq!(glob_regex("[!a]?.{md,txt}"), r"^[^a].\.(md|txt)$");
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::GitHooksError;
+
+/// A configuration fragment a configuration includes.
+///
+/// ```yaml
+/// include:
+///   - ci/base-hooks.yml
+///   - url: https://example.com/org-hooks.yml
+///     sha256: 5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Include {
+    /// A file, relative to the including one
+    Path(String),
+    /// A remote file, pinned by its checksum
+    Url { url: String, sha256: String },
+}
+
+/// Where a configuration was read from, to locate the fragments it includes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    File(PathBuf),
+    Url(String),
+}
+
+impl Source {
+    /// Returns what the including configuration at `self` designates with `include`
+    pub fn resolve(&self, include: &Include) -> anyhow::Result<Source> {
+        match (include, self) {
+            (Include::Url { url, .. }, _) => Ok(Source::Url(url.clone())),
+            (Include::Path(path), Source::File(file)) => Ok(Source::File(
+                file.parent().unwrap_or_else(|| Path::new("")).join(path),
+            )),
+            (Include::Path(path), Source::Url(url)) => Err(GitHooksError::Config(format!(
+                "{} cannot include the local file {}",
+                url, path
+            ))
+            .into()),
+        }
+    }
+
+    /// Returns the name of the source, for file format detection & error messages
+    pub fn name(&self) -> String {
+        match self {
+            Source::File(path) => path.display().to_string(),
+            Source::Url(url) => url.clone(),
+        }
+    }
+}
+
+/// Reads the fragment `include` designates, relative to `source`. Remote fragments must match
+/// their checksum.
+pub fn fetch(source: &Source, include: &Include) -> anyhow::Result<(Source, String)> {
+    let included = source.resolve(include)?;
+    debug!("including {} from {}", included.name(), source.name());
+    let content = match (&included, include) {
+        (Source::Url(url), Include::Url { sha256, .. }) => {
+            let content = reqwest::blocking::get(url)
+                .and_then(|r| r.error_for_status()?.text())
+                .map_err(|e| GitHooksError::Network(format!("could not include {}: {}", url, e)))?;
+            verify_checksum(url, &content, sha256)?;
+            content
+        }
+        (Source::File(path), _) => fs::read_to_string(path).map_err(|e| {
+            GitHooksError::Config(format!("could not include {}: {}", path.display(), e))
+        })?,
+        _ => unreachable!("resolve returns urls for url includes only"),
+    };
+    Ok((included, content))
+}
+
+fn verify_checksum(url: &str, content: &str, expected: &str) -> anyhow::Result<()> {
+    let actual = format!("{:x}", Sha256::digest(content.as_bytes()));
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(GitHooksError::Config(format!(
+            "checksum mismatch for {}: expected {}, got {}",
+            url, expected, actual
+        ))
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::include::{verify_checksum, Include, Source};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_resolve() {
+        let file = Source::File(PathBuf::from("/repo/.hooks.yml"));
+        assert_eq!(
+            file.resolve(&Include::Path("ci/base.yml".to_string()))
+                .unwrap(),
+            Source::File(PathBuf::from("/repo/ci/base.yml"))
+        );
+        let url = Include::Url {
+            url: "https://example.com/base.yml".to_string(),
+            sha256: "00".to_string(),
+        };
+        assert_eq!(
+            file.resolve(&url).unwrap(),
+            Source::Url("https://example.com/base.yml".to_string())
+        );
+        let remote = Source::Url("https://example.com/base.yml".to_string());
+        assert!(remote
+            .resolve(&Include::Path("other.yml".to_string()))
+            .is_err());
+        let include: Vec<Include> =
+            serde_yaml::from_str("- a.yml\n- url: https://example.com/base.yml\n  sha256: \"00\"")
+                .unwrap();
+        assert_eq!(include[0], Include::Path("a.yml".to_string()));
+        assert_eq!(include[1], url);
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let sha = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify_checksum("u", "hello", sha).is_ok());
+        assert!(verify_checksum("u", "hello", &sha.to_uppercase()).is_ok());
+        assert!(verify_checksum("u", "hello!", sha).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+//! Installation of the shims git calls, which hand the events over to `git-hooks`.
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use log::debug;
+
+use crate::config_format::default_config_file_in;
+use crate::utils::{is_executable, set_mode, user_config_dir};
+use crate::{git, HookEvent};
+
+/// Where the shims are installed
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum InstallMode {
+    /// In the repository's hooks directory, `.git/hooks`, overwriting the hooks there
+    #[default]
+    Hooks,
+    /// In a directory of their own, `.git/git-hooks/shims`, which `core.hooksPath` points to.
+    /// `.git/hooks` is left untouched, and unsetting `core.hooksPath` uninstalls `git-hooks`.
+    HooksPath,
+}
+
+impl FromStr for InstallMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hooks" => Ok(InstallMode::Hooks),
+            "hookspath" => Ok(InstallMode::HooksPath),
+            _ => Err(anyhow::Error::msg(format!("unknown install mode {}", s))),
+        }
+    }
+}
+
+/// The git option choosing the shell of the shims
+pub const SHIM_SHELL_OPTION: &str = "git-hooks.shimShell";
+
+/// What runs the shims
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum ShimShell {
+    /// `/bin/bash`
+    #[default]
+    Bash,
+    /// The `bash` of the `PATH`, eg. on NixOS
+    EnvBash,
+    /// `/bin/sh`, eg. on Alpine
+    Sh,
+    /// `/bin/sh`, executing `git-hooks` right away, without checking that it is installed
+    Exec,
+}
+
+impl FromStr for ShimShell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(ShimShell::Bash),
+            "env-bash" => Ok(ShimShell::EnvBash),
+            "sh" => Ok(ShimShell::Sh),
+            "exec" => Ok(ShimShell::Exec),
+            _ => Err(anyhow::Error::msg(format!("unknown shim shell {}", s))),
+        }
+    }
+}
+
+impl ShimShell {
+    /// Returns the shell `git-hooks.shimShell` chooses for `repo`, or globally without a repo
+    pub fn configured(repo: Option<&str>) -> anyhow::Result<ShimShell> {
+        match git::get_config(repo, SHIM_SHELL_OPTION)? {
+            Some(shell) => shell.parse(),
+            None => Ok(ShimShell::default()),
+        }
+    }
+
+    /// Returns the first lines of the shims, down to the `set -e` equivalent
+    fn header(self) -> &'static str {
+        match self {
+            ShimShell::Bash => "#!/bin/bash -e\n",
+            ShimShell::EnvBash => "#!/usr/bin/env bash\nset -e\n",
+            ShimShell::Sh | ShimShell::Exec => "#!/bin/sh -e\n",
+        }
+    }
+}
+
+/// Directory, relative to the git common directory, holding the shims in `hookspath` mode
+const SHIMS_DIR: &str = "git-hooks/shims";
+
+/// The first version of git reading `core.hooksPath`
+const CORE_HOOKS_PATH_VERSION: git::GitVersion = git::GitVersion::new(2, 9, 0);
+
+/// Installs the shims for `events` in the repository `repo`, the way `mode` says
+pub fn install(repo: &str, events: &[HookEvent], mode: InstallMode) -> anyhow::Result<()> {
+    match mode {
+        InstallMode::Hooks => install_shims(
+            &git::hooks_dir(repo)?,
+            events,
+            ShimShell::configured(Some(repo))?,
+        ),
+        InstallMode::HooksPath => {
+            git::require_version(CORE_HOOKS_PATH_VERSION, "core.hooksPath")?;
+            let dir = Path::new(&git::common_dir(repo)?).join(SHIMS_DIR);
+            install_shims(
+                &dir.display().to_string(),
+                events,
+                ShimShell::configured(Some(repo))?,
+            )?;
+            let dir = dir.canonicalize()?.display().to_string();
+            debug!("pointing core.hooksPath to {}", dir);
+            git::set_config(repo, "core.hooksPath", &dir)
+        }
+    }
+}
+
+/// Installs the shims for `events` for all the repositories of the user, the way `mode` says:
+/// - `hooks`: in a git template directory set as `init.templateDir`, which git copies in the
+///   repositories created or cloned from then on
+/// - `hookspath`: in a directory set as the global `core.hooksPath`, which also covers the
+///   existing repositories
+///
+/// Returns the directory holding the shims.
+pub fn install_global(events: &[HookEvent], mode: InstallMode) -> anyhow::Result<String> {
+    let config_dir = user_config_dir()?;
+    let (dir, key, value) = match mode {
+        InstallMode::Hooks => {
+            let template = config_dir.join("template");
+            (template.join("hooks"), "init.templateDir", template)
+        }
+        InstallMode::HooksPath => {
+            git::require_version(CORE_HOOKS_PATH_VERSION, "core.hooksPath")?;
+            let shims = config_dir.join("shims");
+            (shims.clone(), "core.hooksPath", shims)
+        }
+    };
+    let dir = dir.display().to_string();
+    install_shims(&dir, events, ShimShell::configured(None)?)?;
+    git::set_global_config(key, &value.display().to_string())?;
+    Ok(dir)
+}
+
+/// Installs, in the user's git template directory set as `init.templateDir`, a `post-checkout`
+/// hook offering to install `git-hooks` in the repositories cloned from then on which use it.
+/// Unlike [`install_global`], it leaves the other repositories alone. Returns the hook's path.
+pub fn install_bootstrap() -> anyhow::Result<String> {
+    let template = user_config_dir()?.join("template");
+    let hooks_dir = template.join("hooks");
+    std::fs::create_dir_all(&hooks_dir)?;
+    let path = hooks_dir.join("post-checkout").display().to_string();
+    // git gives the hook no stdin: the question is asked on the terminal, if any. A checkout
+    // must not fail because of it.
+    write_script(
+        &path,
+        "#!/bin/bash\n\
+         if { true < /dev/tty; } 2> /dev/null; then exec < /dev/tty; fi\n\
+         git-hooks bootstrap || true\n",
+    )?;
+    git::set_global_config("init.templateDir", &template.display().to_string())?;
+    Ok(path)
+}
+
+/// Tells whether `repo` uses `git-hooks`, having a configuration, without its hooks installed
+pub fn needs_bootstrap(repo: &str) -> anyhow::Result<bool> {
+    if default_config_file_in(Path::new(repo)).is_none() {
+        return Ok(false);
+    }
+    let shim = Path::new(&git::hooks_dir(repo)?).join(HookEvent::PreCommit.to_kebab_case());
+    // earlier versions wrote no marker
+    Ok(!std::fs::read_to_string(shim)
+        .is_ok_and(|content| content.contains(SHIM_MARKER) || content.contains("git-hooks run")))
+}
+
+/// Returns the problems of the shims installed in `repo` for `events`: missing, not executable,
+/// or not the ones this version of `git-hooks` writes, or never run by the installed git
+pub fn check(repo: &str, events: &[HookEvent]) -> anyhow::Result<Vec<String>> {
+    let dir = git::hooks_dir(repo)?;
+    let mut problems = unsupported_events(events)?;
+    for event in events {
+        let path = Path::new(&dir).join(event.to_kebab_case());
+        for problem in shim_problems(repo, *event)? {
+            problems.push(format!("{} is {}", path.display(), problem));
+        }
+    }
+    Ok(problems)
+}
+
+/// Returns the problems of the shim installed in `repo` for `event`, such as `missing`
+pub fn shim_problems(repo: &str, event: HookEvent) -> anyhow::Result<Vec<&'static str>> {
+    let path = Path::new(&git::hooks_dir(repo)?).join(event.to_kebab_case());
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Ok(vec!["missing"]),
+    };
+    let mut problems = Vec::new();
+    if !is_executable(&path)? {
+        problems.push("not executable");
+    }
+    if content != shim(event, ShimShell::configured(Some(repo))?) {
+        problems.push("outdated");
+    }
+    Ok(problems)
+}
+
+/// Comment of the shims telling which version of `git-hooks` wrote them
+const SHIM_MARKER: &str = "# installed by git-hooks";
+
+/// Returns the script git runs on `event` with `shell`, handing it over to this `git-hooks` binary.
+/// GUI clients often run the hooks without the user's `PATH`, so it is called by its absolute
+/// path, unless it moved since. Without any binary, it tells how to install one and fails, unless
+/// the `git-hooks.allowMissing` git option is set. The `exec` shims skip those checks.
+fn shim(event: HookEvent, shell: ShimShell) -> String {
+    // git for Windows runs the hooks with its own bash, which prefers forward slashes
+    let binary = env::current_exe()
+        .map(|path| path.display().to_string().replace('\\', "/"))
+        .map(|path| shlex::quote(&path).into_owned())
+        .unwrap_or_else(|_| "git-hooks".to_string());
+    let body = match shell {
+        ShimShell::Exec => include_str!("shim_exec.sh"),
+        _ => include_str!("shim.sh"),
+    };
+    format!("{}{}", shell.header(), body)
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
+        .replace("{binary}", &binary)
+        .replace("{event}", event.to_kebab_case())
+}
+
+/// Tells which of `events` the installed git never runs the hooks of, being too old
+pub fn unsupported_events(events: &[HookEvent]) -> anyhow::Result<Vec<String>> {
+    let version = git::version()?;
+    Ok(events
+        .iter()
+        .filter_map(|event| {
+            let min = event.min_git_version().filter(|min| version < *min)?;
+            Some(format!(
+                "git {} never runs the {} hooks: they need git {} or newer",
+                version,
+                event.to_kebab_case(),
+                min
+            ))
+        })
+        .collect())
+}
+
+/// Writes the scripts running `git-hooks` on `events` with `shell` in the hooks directory `dir`.
+/// The ones the installed git never runs are written anyway, for a newer git, with a warning.
+pub fn install_shims(dir: &str, events: &[HookEvent], shell: ShimShell) -> anyhow::Result<()> {
+    for problem in unsupported_events(events)? {
+        eprintln!("warning: {}", problem);
+    }
+    std::fs::create_dir_all(dir)?;
+    for event in events {
+        write_script(
+            &format!("{}/{}", dir, event.to_kebab_case()),
+            &shim(*event, shell),
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the executable script `path`
+fn write_script(path: &str, content: &str) -> anyhow::Result<()> {
+    let mut script = File::create(path)?;
+    script.write_all(content.as_bytes())?;
+    set_mode(Path::new(path), 0o755)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::git;
+    use crate::install::{
+        check, install, needs_bootstrap, InstallMode, ShimShell, SHIM_SHELL_OPTION,
+    };
+    use crate::HookEvent;
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_install_hookspath() {
+        let dir = TempDir::new("git-hooks-tests").unwrap();
+        let repo = dir.path().to_str().unwrap();
+        git::init(Some(repo)).unwrap();
+        std::fs::write(dir.path().join(".git/hooks/pre-commit"), "#!/bin/sh\n").unwrap();
+        install(repo, &[HookEvent::PreCommit], InstallMode::HooksPath).unwrap();
+        // the existing hooks are left alone
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(".git/hooks/pre-commit")).unwrap(),
+            "#!/bin/sh\n"
+        );
+        let hooks_dir = git::hooks_dir(repo).unwrap();
+        assert!(hooks_dir.ends_with(".git/git-hooks/shims"));
+        assert!(Path::new(&hooks_dir).join("pre-commit").exists());
+    }
+
+    #[test]
+    fn test_needs_bootstrap() {
+        let dir = TempDir::new("git-hooks-tests").unwrap();
+        let repo = dir.path().to_str().unwrap();
+        git::init(Some(repo)).unwrap();
+        assert!(!needs_bootstrap(repo).unwrap());
+        std::fs::write(dir.path().join(".hooks.yml"), "hooks: []\n").unwrap();
+        assert!(needs_bootstrap(repo).unwrap());
+        install(repo, &[HookEvent::PreCommit], InstallMode::Hooks).unwrap();
+        assert!(!needs_bootstrap(repo).unwrap());
+    }
+
+    #[test]
+    fn test_check() {
+        let dir = TempDir::new("git-hooks-tests").unwrap();
+        let repo = dir.path().to_str().unwrap();
+        git::init(Some(repo)).unwrap();
+        let events = [HookEvent::PreCommit, HookEvent::CommitMsg];
+        assert_eq!(check(repo, &events).unwrap().len(), 2);
+        install(repo, &events, InstallMode::Hooks).unwrap();
+        assert!(check(repo, &events).unwrap().is_empty());
+        std::fs::write(dir.path().join(".git/hooks/commit-msg"), "#!/bin/sh\n").unwrap();
+        assert_eq!(
+            check(repo, &events).unwrap(),
+            vec![format!("{}/.git/hooks/commit-msg is outdated", repo)]
+        );
+    }
+
+    #[test]
+    fn test_shim_shell() {
+        let dir = TempDir::new("git-hooks-tests").unwrap();
+        let repo = dir.path().to_str().unwrap();
+        git::init(Some(repo)).unwrap();
+        assert_eq!(ShimShell::configured(Some(repo)).unwrap(), ShimShell::Bash);
+        git::set_config(repo, SHIM_SHELL_OPTION, "exec").unwrap();
+        assert_eq!(ShimShell::configured(Some(repo)).unwrap(), ShimShell::Exec);
+        install(repo, &[HookEvent::PreCommit], InstallMode::Hooks).unwrap();
+        let shim = std::fs::read_to_string(dir.path().join(".git/hooks/pre-commit")).unwrap();
+        assert!(shim.starts_with("#!/bin/sh -e\n# installed by git-hooks"));
+        assert!(shim.ends_with("run pre-commit \"$@\"\n"));
+        assert!(check(repo, &[HookEvent::PreCommit]).unwrap().is_empty());
+        // a shim of another shell is outdated
+        git::set_config(repo, SHIM_SHELL_OPTION, "env-bash").unwrap();
+        assert_eq!(check(repo, &[HookEvent::PreCommit]).unwrap().len(), 1);
+        git::set_config(repo, SHIM_SHELL_OPTION, "zsh").unwrap();
+        assert!(ShimShell::configured(Some(repo)).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{execute_cmd, state_dir};
+
+/// Where the language environments are created, relative to the state directory of the hook repo,
+/// its `.git`, so that they do not dirty the clone's worktree.
+const ENVS_LOCATION: &str = "git-hooks-envs";
+
+/// Where a python virtualenv holds its binaries
+#[cfg(not(windows))]
+const VENV_BIN_DIR: &str = "bin";
+#[cfg(windows)]
+const VENV_BIN_DIR: &str = "Scripts";
+
+/// The toolchain a hook is written with.
+/// git-hooks prepares an isolated environment for each language used by a hook repo, and makes
+/// its binaries available to the hooks' actions.
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Copy, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Language {
+    /// Uses whatever is installed on the system. Nothing to prepare.
+    #[default]
+    System,
+    /// A virtualenv per hook repo, with the repo's requirements installed
+    Python,
+    /// The repo's npm packages, installed in its `node_modules`
+    Node,
+    /// The repo's binaries, or crates from crates.io, installed with `cargo install`
+    Rust,
+}
+
+impl Language {
+    pub fn to_kebab_case(self) -> &'static str {
+        match self {
+            Language::System => "system",
+            Language::Python => "python",
+            Language::Node => "node",
+            Language::Rust => "rust",
+        }
+    }
+
+    /// Returns the directory holding the environment of this language for the given hook repo
+    pub fn env_dir(self, clone_dir: &str) -> PathBuf {
+        state_dir(clone_dir)
+            .join(ENVS_LOCATION)
+            .join(self.to_kebab_case())
+    }
+
+    /// Returns the directories to prepend to `$PATH` when running an action in this language
+    pub fn bin_dirs(self, clone_dir: &str) -> Vec<PathBuf> {
+        match self {
+            Language::System => vec![],
+            Language::Python => vec![self.env_dir(clone_dir).join(VENV_BIN_DIR)],
+            Language::Node => vec![Path::new(clone_dir).join("node_modules").join(".bin")],
+            Language::Rust => vec![self.env_dir(clone_dir).join("bin")],
+        }
+    }
+
+    /// Creates the environment for the hook repo cloned in `clone_dir`, and installs
+    /// `dependencies` in it, on top of what the repo itself declares.
+    pub fn install(self, clone_dir: &str, dependencies: &[String]) -> anyhow::Result<()> {
+        let env_dir = self.env_dir(clone_dir).display().to_string();
+        match self {
+            Language::System => Ok(()),
+            Language::Python => {
+                debug!("creating virtualenv {}", env_dir);
+                execute_cmd("python3", &["-m", "venv", &env_dir], Some(clone_dir), None)?;
+                let pip = format!("{}/{}/pip", env_dir, VENV_BIN_DIR);
+                let repo = Path::new(clone_dir);
+                if repo.join("requirements.txt").is_file() {
+                    execute_cmd(
+                        &pip,
+                        &["install", "-r", "requirements.txt"],
+                        Some(clone_dir),
+                        None,
+                    )?;
+                }
+                if repo.join("setup.py").is_file() || repo.join("pyproject.toml").is_file() {
+                    execute_cmd(&pip, &["install", "."], Some(clone_dir), None)?;
+                }
+                if !dependencies.is_empty() {
+                    let mut args = vec!["install".to_string()];
+                    args.extend(dependencies.iter().cloned());
+                    execute_cmd(&pip, &args, Some(clone_dir), None)?;
+                }
+                Ok(())
+            }
+            Language::Node => {
+                let repo = Path::new(clone_dir);
+                if repo.join("package-lock.json").is_file() {
+                    execute_cmd("npm", &["ci"], Some(clone_dir), None)?;
+                } else if repo.join("package.json").is_file() {
+                    execute_cmd("npm", &["install"], Some(clone_dir), None)?;
+                }
+                if !dependencies.is_empty() {
+                    // --no-save, as to not modify the hook repo's package.json
+                    let mut args = vec!["install".to_string(), "--no-save".to_string()];
+                    args.extend(dependencies.iter().cloned());
+                    execute_cmd("npm", &args, Some(clone_dir), None)?;
+                }
+                Ok(())
+            }
+            Language::Rust => {
+                if Path::new(clone_dir).join("Cargo.toml").is_file() {
+                    execute_cmd(
+                        "cargo",
+                        &["install", "--path", ".", "--root", &env_dir],
+                        Some(clone_dir),
+                        None,
+                    )?;
+                }
+                for dependency in dependencies {
+                    let mut args = vec!["install", "--root", &env_dir];
+                    match dependency.split_once('@') {
+                        Some((name, version)) => args.extend(&[name, "--version", version]),
+                        None => args.push(dependency),
+                    }
+                    execute_cmd("cargo", &args, Some(clone_dir), None)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::Language;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_bin_dirs() {
+        assert!(Language::System.bin_dirs("/tmp/repo").is_empty());
+        assert_eq!(
+            Language::Python.bin_dirs("/tmp/repo"),
+            vec![PathBuf::from("/tmp/repo/.git/git-hooks-envs/python/bin")]
+        );
+        assert_eq!(
+            Language::Node.bin_dirs("/tmp/repo"),
+            vec![PathBuf::from("/tmp/repo/node_modules/.bin")]
+        );
+        assert_eq!(
+            Language::Rust.bin_dirs("/tmp/repo"),
+            vec![PathBuf::from("/tmp/repo/.git/git-hooks-envs/rust/bin")]
+        );
+        let l: Language = serde_yaml::from_str("python").unwrap();
+        assert_eq!(l, Language::Python);
+    }
+}
@@ -0,0 +1,2552 @@
+//! Resolution & execution of the hooks configured for a git repository.
+//!
+//! This is the library behind the `git-hooks` binary, for tools wanting to run hooks without
+//! shelling out to it:
+//!
+//! ```no_run
+//! use git_hooks_manager::{HookConfig, HookEvent, LoadOptions};
+//!
+//! let conf = HookConfig::from_file(None, &LoadOptions::default())?;
+//! let report = conf.run(HookEvent::PreCommit, &[])?;
+//! assert!(report.success());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::File;
+use std::io::{stderr, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shlex::Shlex;
+
+use crate::builtins::{Builtin, BUILTIN_PREFIX};
+use crate::config_format::{
+    default_config_file_in, find_config_file, ConfigFormat, DEFAULT_CONFIG_FILES,
+};
+use crate::context::RunContext;
+pub use crate::error::GitHooksError;
+use crate::include::{Include, Source};
+use crate::install::install;
+pub use crate::install::InstallMode;
+use crate::language::Language;
+use crate::policy::Policies;
+use crate::report::RunReport;
+use crate::server::PushPolicy;
+use crate::setup::SetupMarker;
+use crate::toolchain::Tools;
+use crate::trust::TrustStore;
+use crate::utils::{
+    create_private_file, execute_cmd_live, execute_cmd_with_input, expand_path, find_program,
+    get_files, get_local_repo_path, is_executable, is_project_hooks_dir, matches,
+    system_config_dir, user_config_dir, PROJECT_HOOKS_DIR,
+};
+
+mod builtins;
+mod checksums;
+pub mod config_format;
+mod container;
+pub mod context;
+pub mod edit;
+pub mod error;
+pub mod exec;
+mod explain;
+pub mod export;
+pub mod git;
+pub mod import;
+pub mod include;
+pub mod install;
+pub mod language;
+pub mod lock;
+pub mod migrate;
+mod monorepo;
+mod nix;
+pub mod outdated;
+mod policy;
+pub mod preview;
+mod protect;
+pub mod registry;
+#[cfg(feature = "self-update")]
+pub mod release;
+pub mod repo;
+pub mod report;
+pub mod requires;
+mod sandbox;
+mod select;
+pub mod server;
+mod setup;
+pub mod status;
+mod strict;
+pub mod toolchain;
+mod trust;
+pub mod utils;
+pub mod vendor;
+mod wasm;
+
+#[cfg(test)]
+mod tests {
+    use crate::context::RunContext;
+    use crate::error::exit_code;
+    use crate::include::Source;
+    use crate::utils::set_mode;
+    use crate::{
+        content_hashes, git, hook_path, is_about, project_script, remove_diff_file, temp_path,
+        write_diff_file, Action, ExternalHookRepo, GitHooksError, Hook, HookConfig, HookEvent,
+        LoadOptions,
+    };
+    use std::env::{current_dir, set_current_dir, split_paths};
+    use std::path::{Path, PathBuf};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_merge() {
+        let mut conf = HookConfig {
+            hooks: vec![Hook {
+                name: "test1".to_string(),
+                action: Some(Action::Single("exe2".to_string())),
+                ..Default::default()
+            }],
+            repos: vec![ExternalHookRepo {
+                url: "dummy".to_string(),
+                hooks: vec![Hook {
+                    name: "test1".to_string(),
+                    on_event: Some(vec![HookEvent::PreCommit]),
+                    on_file_regex: Some(vec![".*".to_string()]),
+                    action: Some(Action::Single("exe1".to_string())),
+                    setup_script: Some("hello.sh".to_string()),
+                    ..Default::default()
+                }],
+                version: None,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_ne!(conf.hooks[0].action, conf.repos[0].hooks[0].action);
+        conf.update_repos_config();
+        assert_eq!(conf.hooks[0].action, conf.repos[0].hooks[0].action);
+    }
+
+    #[test]
+    fn test_alias() {
+        let mut conf: HookConfig = serde_yaml::from_str(
+            r#"
+hooks:
+  - name: fmt
+    on_file_regex: ['^src/']
+  - name: fmt-examples
+    alias_of: fmt
+    on_file_regex: ['^examples/']
+    action: fmt --relaxed {changed_files}
+  - name: missing
+    alias_of: unknown
+"#,
+        )
+        .unwrap();
+        conf.repos.push(ExternalHookRepo {
+            url: "dummy".to_string(),
+            hooks: vec![Hook {
+                name: "fmt".to_string(),
+                on_event: Some(vec![HookEvent::PrePush]),
+                action: Some(Action::Single("fmt {changed_files}".to_string())),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        conf.update_repos_config();
+        // running it again, once the repos are set up, adds no other copy
+        conf.update_repos_config();
+        let hooks = &conf.repos[0].hooks;
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(hooks[0].on_file_regex, Some(vec!["^src/".to_string()]));
+        assert_eq!(
+            hooks[0].action,
+            Some(Action::Single("fmt {changed_files}".to_string()))
+        );
+        assert_eq!(hooks[1].name, "fmt-examples");
+        assert_eq!(hooks[1].on_event, Some(vec![HookEvent::PrePush]));
+        assert_eq!(hooks[1].on_file_regex, Some(vec!["^examples/".to_string()]));
+        assert_eq!(
+            hooks[1].action,
+            Some(Action::Single("fmt --relaxed {changed_files}".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        std::fs::create_dir(dir.path().join("ci")).expect("could not create dir");
+        std::fs::write(
+            dir.path().join("ci").join("base.toml"),
+            r#"
+[[repos]]
+url = "https://github.com/paulollivier/rust-hooks"
+version = "v1"
+
+[[hooks]]
+name = "cargofmt"
+on_event = ["pre-push"]
+on_file_regex = [".*\\.rs"]
+
+[[hooks]]
+name = "cargocheck"
+"#,
+        )
+        .expect("could not write fragment");
+        let main = dir.path().join(".hooks.yml");
+        let content = r#"
+include:
+  - ci/base.toml
+repos:
+  - url: https://github.com/paulollivier/rust-hooks
+    version: v2
+hooks:
+  - name: cargofmt
+    on_event: [pre-commit]
+"#;
+        let conf = HookConfig::parse(&Source::File(main.clone()), content, &mut vec![], false)
+            .expect("could not parse config");
+        assert_eq!(conf.repos.len(), 1);
+        assert_eq!(conf.repos[0].version, Some("v2".to_string()));
+        assert_eq!(conf.hooks.len(), 2);
+        assert_eq!(conf.hooks[0].on_event, Some(vec![HookEvent::PreCommit]));
+        assert_eq!(
+            conf.hooks[0].on_file_regex,
+            Some(vec![".*\\.rs".to_string()])
+        );
+        assert_eq!(conf.hooks[1].name, "cargocheck");
+
+        let r = HookConfig::parse(
+            &Source::File(main.clone()),
+            "include: [.hooks.yml]",
+            &mut vec![],
+            false,
+        );
+        assert!(r.is_err());
+        let r = HookConfig::parse(
+            &Source::File(main.clone()),
+            "hooks: [{name: a, on_file_regex: ['(']}]",
+            &mut vec![],
+            false,
+        );
+        assert_eq!(exit_code(&r.unwrap_err()), 2);
+        // the included fragments are parsed as strictly as the including configuration
+        let typo = "hooks:\n  - name: a\n    on_events: [pre-push]\n";
+        std::fs::write(dir.path().join("typo.yml"), typo).unwrap();
+        let including = "include: [typo.yml]";
+        let source = Source::File(main);
+        assert!(HookConfig::parse(&source, including, &mut vec![], false).is_ok());
+        let r = HookConfig::parse(&source, including, &mut vec![], true);
+        assert!(r.unwrap_err().to_string().ends_with(
+            "typo.yml:\n  line 3: unknown key on_events in hooks[0], did you mean on_event?"
+        ));
+    }
+
+    #[test]
+    fn test_project_script() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let root = dir.path().display().to_string();
+        std::fs::create_dir(dir.path().join("scripts")).unwrap();
+        std::fs::write(dir.path().join("scripts/check.sh"), "#!/bin/sh\n").unwrap();
+        let hook = Hook {
+            name: "check".to_string(),
+            ..Default::default()
+        };
+        let e = project_script(&hook, &root, "./scripts/check.sh").unwrap_err();
+        assert_eq!(exit_code(&e), 2);
+        assert!(e.to_string().starts_with(
+            "check: ./scripts/check.sh is not executable: run `chmod +x ./scripts/check.sh`"
+        ));
+        set_mode(&dir.path().join("scripts/check.sh"), 0o755).unwrap();
+        assert_eq!(
+            project_script(&hook, &root, "./scripts/check.sh").unwrap(),
+            dir.path().join("scripts/check.sh").display().to_string()
+        );
+        let e = project_script(&hook, &root, "scripts/missing.sh").unwrap_err();
+        assert!(e
+            .to_string()
+            .starts_with("check: cannot run scripts/missing.sh, relative to the root"));
+    }
+
+    #[test]
+    fn test_hook_path() {
+        let repo: ExternalHookRepo = serde_yaml::from_str(
+            r#"
+url: https://example.com/b
+hooks:
+  - name: black
+    language: python
+  - name: prettier
+    language: node
+  - name: isort
+    language: python
+"#,
+        )
+        .unwrap();
+        let shared = repo.bin_dirs("/hooks/b");
+        assert_eq!(
+            shared,
+            [
+                Path::new("/hooks/b/.git/git-hooks-envs/python/bin"),
+                Path::new("/hooks/b/node_modules/.bin"),
+                Path::new("/hooks/b"),
+            ]
+        );
+        let mut shared = shared;
+        shared.insert(0, PathBuf::from("/hooks/a"));
+        let hook = Hook {
+            name: "lint".to_string(),
+            ..Default::default()
+        };
+        let path: Vec<PathBuf> =
+            split_paths(&hook_path(&hook, "/hooks/a", &shared).unwrap()).collect();
+        // the hook's own repo is not repeated, and comes before the others
+        assert_eq!(&path[path.len() - 4..], &shared[..]);
+    }
+
+    #[test]
+    fn test_default_on_event() {
+        let mut conf: HookConfig = serde_yaml::from_str(
+            r#"
+default_on_event: [pre-push]
+repos:
+  - url: https://example.com/a
+  - url: https://example.com/b
+    default_on_event: [commit-msg]
+hooks:
+  - name: a
+  - name: b
+  - name: lint
+    script: ./lint.sh
+"#,
+        )
+        .unwrap();
+        let hook = |name: &str, on_event: Option<Vec<HookEvent>>| Hook {
+            name: name.to_string(),
+            on_event,
+            ..Default::default()
+        };
+        conf.repos[0].hooks = vec![
+            hook("a", None),
+            hook("c", Some(vec![HookEvent::PostCommit])),
+        ];
+        conf.repos[1].hooks = vec![hook("b", None)];
+        conf.update_repos_config();
+        assert_eq!(
+            conf.repos[0].hooks[0].on_event,
+            Some(vec![HookEvent::PrePush])
+        );
+        assert_eq!(
+            conf.repos[0].hooks[1].on_event,
+            Some(vec![HookEvent::PostCommit])
+        );
+        assert_eq!(
+            conf.repos[1].hooks[0].on_event,
+            Some(vec![HookEvent::CommitMsg])
+        );
+        assert_eq!(conf.events(), [HookEvent::PrePush, HookEvent::CommitMsg]);
+    }
+
+    #[test]
+    fn test_unmatched_hooks() {
+        let mut conf: HookConfig = serde_yaml::from_str(
+            r#"
+hooks:
+  - name: cargofmt
+  - name: cargo-fmt
+  - name: fmt-examples
+    alias_of: cargofnt
+  - name: builtin:check-yaml
+  - name: lint
+    script: ./lint.sh
+  - name: unrelated
+"#,
+        )
+        .unwrap();
+        conf.repos.push(ExternalHookRepo {
+            url: "dummy".to_string(),
+            hooks: vec![Hook {
+                name: "cargofmt".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        });
+        conf.update_repos_config();
+        assert_eq!(
+            conf.unmatched_hooks(),
+            [
+                "cargo-fmt is enabled, but none of the hook repos defines it: it never runs, did you mean cargofmt?",
+                "fmt-examples is an alias of cargofnt, which none of the hook repos defines, did you mean cargofmt?",
+                "unrelated is enabled, but none of the hook repos defines it: it never runs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_repo_defining() {
+        let mut conf: HookConfig = serde_yaml::from_str(
+            "repos:\n  - url: /nonexistent/lints\nhooks:\n  - name: lint\n    action: ./lint.sh\n",
+        )
+        .unwrap();
+        // never set up, eg. untrusted: its hooks may be any
+        assert_eq!(
+            conf.unknown_repo_defining("lint"),
+            Some("/nonexistent/lints")
+        );
+        conf.repos[0].hooks.push(Hook {
+            name: "shellcheck".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(conf.unknown_repo_defining("lint"), None);
+    }
+
+    #[test]
+    fn test_disable_hooks() {
+        let mut conf: HookConfig =
+            serde_yaml::from_str("hooks: [{name: a}, {name: b}, {name: c}]\ndisable: [a, c]")
+                .unwrap();
+        conf.disable_hooks(&["c".to_string()]);
+        let names: Vec<&str> = conf.hooks.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_mirrors() {
+        let mut conf: HookConfig = serde_yaml::from_str(
+            r#"
+mirrors:
+  https://github.com/: https://mirror.example.com/github/
+  https://github.com/paulollivier/: https://git.example.com/paulollivier/
+repos:
+  - url: https://github.com/paulollivier/rust-hooks
+  - url: https://github.com/someone/hooks
+  - url: https://gitlab.com/someone/hooks
+"#,
+        )
+        .unwrap();
+        conf.apply_mirrors();
+        let urls: Vec<&str> = conf.repos.iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://git.example.com/paulollivier/rust-hooks",
+                "https://mirror.example.com/github/someone/hooks",
+                "https://gitlab.com/someone/hooks"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_external_repo_with_version() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let old_dir = current_dir().expect("could not get current dir");
+        set_current_dir(dir.path()).expect("could not cd to temp dir");
+        git::init(None).expect("could not init repo");
+        let mut er = ExternalHookRepo {
+            url: "https://github.com/paulollivier/rust-hooks".to_string(),
+            version: Some("0e74c2b9c6b1cf4ff36d7eedbee8e8093acacaac".to_string()),
+            hooks: vec![],
+            ..Default::default()
+        };
+        let r = er.init(&LoadOptions::default());
+        assert!(r.is_ok());
+        let cloned_dir = dir
+            .path()
+            .join(".git")
+            .join("hook-repos")
+            .join("rust-hooks");
+        assert!(cloned_dir.join("hooks.yml").exists());
+        set_current_dir(cloned_dir).expect("could not cd to cloned dir");
+        let r = git::get_hash("HEAD");
+        assert!(r.is_ok());
+        assert_eq!(
+            "0e74c2b9c6b1cf4ff36d7eedbee8e8093acacaac".to_string(),
+            r.unwrap()
+        );
+        set_current_dir(old_dir).expect("could not revert current dir");
+    }
+
+    #[test]
+    fn test_run_from_subdirectory() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        git::init(Some(dir.path().to_str().unwrap())).expect("could not init repo");
+        std::fs::write(dir.path().join(".git/COMMIT_EDITMSG"), "feat: x\n").unwrap();
+        std::fs::write(
+            dir.path().join(".hooks.yml"),
+            "hooks:\n  - name: lint\n    script: true\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        set_current_dir(dir.path().join("src")).expect("could not cd to subdirectory");
+        let conf = HookConfig::from_file(None, &LoadOptions::default());
+        let ctx = RunContext::new(
+            HookEvent::CommitMsg,
+            &["../.git/COMMIT_EDITMSG".to_string()],
+        );
+        set_current_dir(env!("CARGO_MANIFEST_DIR")).expect("could not revert current dir");
+        assert_eq!(conf.expect("could not read config").hooks.len(), 1);
+        let message_file = &ctx.expect("could not build context").args[0];
+        assert!(Path::new(message_file).is_absolute());
+        assert_eq!(std::fs::read_to_string(message_file).unwrap(), "feat: x\n");
+    }
+
+    #[test]
+    fn test_action_sequence() {
+        let hook: Hook = serde_yaml::from_str("name: a\naction: cargo fmt").unwrap();
+        assert_eq!(hook.action.unwrap().commands(), vec!["cargo fmt"]);
+        let hook: Hook =
+            serde_yaml::from_str("name: a\naction:\n  - cargo fmt\n  - cargo check").unwrap();
+        assert_eq!(
+            hook.action.unwrap().commands(),
+            vec!["cargo fmt", "cargo check"]
+        );
+    }
+
+    #[test]
+    fn test_content_hashes() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let root = dir.path().display().to_string();
+        std::fs::write(dir.path().join("a.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "fn main() {}\n").unwrap();
+        let files = vec!["a.rs".to_string(), "b.rs".to_string(), "c.rs".to_string()];
+        let before = content_hashes(&root, &files);
+        assert_eq!(before[0], before[1]);
+        assert_eq!(before[2], None);
+        std::fs::write(dir.path().join("b.rs"), "fn main() {\n}\n").unwrap();
+        let after = content_hashes(&root, &files);
+        assert_eq!(before[0], after[0]);
+        assert_ne!(before[1], after[1]);
+        assert_eq!(after[2], None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_diff_file() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let hook = Hook {
+            name: "test-write-diff-file".to_string(),
+            ..Default::default()
+        };
+        // planted by someone else, eg. for the diff to overwrite their target
+        let target = dir.path().join("target");
+        std::fs::write(&target, "kept").unwrap();
+        symlink(&target, temp_path(&hook, ".diff")).unwrap();
+        let path = write_diff_file(&hook, "+fn main() {}\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "kept");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "+fn main() {}\n");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        remove_diff_file(&hook);
+        assert!(!Path::new(&path).exists());
+    }
+
+    #[test]
+    fn test_is_about() {
+        let hook: Hook =
+            serde_yaml::from_str("name: a\non_file_regex: ['.*\\.rs']\nroot: api").unwrap();
+        let files = |files: &[&str]| files.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        assert!(is_about(&hook, &files(&["README.md", "api/src/main.rs"])));
+        assert!(!is_about(
+            &hook,
+            &files(&["api/README.md", "web/src/main.rs"])
+        ));
+        let hook: Hook = serde_yaml::from_str("name: a").unwrap();
+        assert!(is_about(&hook, &files(&["README.md"])));
+        assert!(!is_about(&hook, &[]));
+    }
+
+    #[test]
+    fn test_event_from_str() {
+        let event: HookEvent = "pre-push".parse().unwrap();
+        assert_eq!(event, HookEvent::PrePush);
+        assert_eq!(event.to_string(), "pre-push");
+        assert_eq!(
+            "pre-pushed".parse::<HookEvent>(),
+            Err(GitHooksError::Config(
+                "unknown event pre-pushed".to_string()
+            ))
+        );
+    }
+}
+
+/// Represents the possible placeholders to be substituted to actual file values.
+/// The singular variants mean that the action is to be executed for each file found.
+enum ActionFileToken {
+    Files,
+    File,
+    ChangedFiles,
+    ChangedFile,
+    UntrackedFiles,
+    ModifiedFiles,
+    Root,
+    Diff,
+    Event,
+}
+
+impl ActionFileToken {
+    /// Returns the variant from a textual representation
+    /// ```ignore
+    /// assert_eq!(ActionFileToken::File, ActionFileToken::from_str("{file}"));
+    /// assert_eq!(ActionFileToken::ChangedFiles, ActionFileToken::from_str("{changed_files}"));
+    /// ```
+    fn from_str(token: &str) -> Option<ActionFileToken> {
+        match token {
+            "{file}" => Some(ActionFileToken::File),
+            "{files}" => Some(ActionFileToken::Files),
+            "{changed_files}" => Some(ActionFileToken::ChangedFiles),
+            "{changed_file}" => Some(ActionFileToken::ChangedFile),
+            "{untracked_files}" => Some(ActionFileToken::UntrackedFiles),
+            "{modified_files}" => Some(ActionFileToken::ModifiedFiles),
+            "{root}" => Some(ActionFileToken::Root),
+            "{diff}" => Some(ActionFileToken::Diff),
+            "{event}" => Some(ActionFileToken::Event),
+            _ => None,
+        }
+    }
+}
+
+/// The git events a hook can react to
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Copy, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    ApplyPatchMsg,
+    CommitMsg,
+    PostCommit,
+    PostUpdate,
+    PreApplyPatch,
+    PreCommit,
+    PreMergeCommit,
+    PrePush,
+    PreRebase,
+    PreReceive,
+    PostReceive,
+    PrepareCommitMsg,
+    Update,
+    /// Not a git event: the hooks only run through `git-hooks run manual`, eg. the slow ones
+    Manual,
+}
+
+/// Every event `git-hooks` can install itself for
+pub static ALL_HOOK_EVENTS: &[HookEvent] = &[
+    HookEvent::ApplyPatchMsg,
+    HookEvent::CommitMsg,
+    HookEvent::PostCommit,
+    HookEvent::PostUpdate,
+    HookEvent::PreApplyPatch,
+    HookEvent::PreCommit,
+    HookEvent::PreMergeCommit,
+    HookEvent::PrePush,
+    HookEvent::PreRebase,
+    HookEvent::PreReceive,
+    HookEvent::PostReceive,
+    HookEvent::PrepareCommitMsg,
+    HookEvent::Update,
+];
+
+impl HookEvent {
+    /// Returns the name git gives to the event, which is also the name of its hook script
+    pub fn to_kebab_case(self) -> &'static str {
+        match self {
+            HookEvent::ApplyPatchMsg => "apply-patch-msg",
+            HookEvent::CommitMsg => "commit-msg",
+            HookEvent::PostCommit => "post-commit",
+            HookEvent::PostUpdate => "post-update",
+            HookEvent::PreApplyPatch => "pre-apply-patch",
+            HookEvent::PreCommit => "pre-commit",
+            HookEvent::PreMergeCommit => "pre-merge-commit",
+            HookEvent::PrePush => "pre-push",
+            HookEvent::PreRebase => "pre-rebase",
+            HookEvent::PreReceive => "pre-receive",
+            HookEvent::PostReceive => "post-receive",
+            HookEvent::PrepareCommitMsg => "prepare-commit-msg",
+            HookEvent::Update => "update",
+            HookEvent::Manual => "manual",
+        }
+    }
+
+    /// Returns the event named `s`, as in `.git/hooks`
+    pub fn from_kebab_case(s: &str) -> Option<Self> {
+        match s {
+            "apply-patch-msg" => Some(HookEvent::ApplyPatchMsg),
+            "commit-msg" => Some(HookEvent::CommitMsg),
+            "post-commit" => Some(HookEvent::PostCommit),
+            "post-update" => Some(HookEvent::PostUpdate),
+            "pre-apply-patch" => Some(HookEvent::PreApplyPatch),
+            "pre-commit" => Some(HookEvent::PreCommit),
+            "pre-merge-commit" => Some(HookEvent::PreMergeCommit),
+            "pre-push" => Some(HookEvent::PrePush),
+            "pre-rebase" => Some(HookEvent::PreRebase),
+            "pre-receive" => Some(HookEvent::PreReceive),
+            "post-receive" => Some(HookEvent::PostReceive),
+            "prepare-commit-msg" => Some(HookEvent::PrepareCommitMsg),
+            "update" => Some(HookEvent::Update),
+            "manual" => Some(HookEvent::Manual),
+            _ => None,
+        }
+    }
+
+    /// Tells whether the first argument git gives the hook is the file holding the message of the
+    /// commit or patch
+    pub fn takes_message_file(self) -> bool {
+        matches!(
+            self,
+            HookEvent::ApplyPatchMsg | HookEvent::CommitMsg | HookEvent::PrepareCommitMsg
+        )
+    }
+
+    /// Tells whether git gives data to the hook on its stdin
+    pub fn reads_stdin(self) -> bool {
+        matches!(
+            self,
+            HookEvent::PrePush | HookEvent::PreReceive | HookEvent::PostReceive
+        )
+    }
+
+    /// Tells whether the event happens on the repository receiving a push
+    pub fn is_server_side(self) -> bool {
+        matches!(
+            self,
+            HookEvent::PreReceive | HookEvent::Update | HookEvent::PostReceive
+        )
+    }
+
+    /// Returns the first version of git running the event's hooks, for the events newer than
+    /// the versions `git-hooks` supports anyway
+    pub fn min_git_version(self) -> Option<git::GitVersion> {
+        match self {
+            HookEvent::PreMergeCommit => Some(git::GitVersion::new(2, 24, 0)),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for HookEvent {
+    type Err = GitHooksError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HookEvent::from_kebab_case(s)
+            .ok_or_else(|| GitHooksError::Config(format!("unknown event {}", s)))
+    }
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_kebab_case())
+    }
+}
+
+/// The command line(s) a hook runs: either a single one, or a list of them, run in sequence until
+/// one fails.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Action {
+    Single(String),
+    Sequence(Vec<String>),
+}
+
+impl Action {
+    /// Returns the command lines to run, in order
+    pub fn commands(&self) -> Vec<&str> {
+        match self {
+            Action::Single(command) => vec![command.as_str()],
+            Action::Sequence(commands) => commands.iter().map(|c| c.as_str()).collect(),
+        }
+    }
+}
+
+/// The files the `{files}` token of a hook stands for
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Copy, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSource {
+    /// The files the event is about: the staged ones, or those of the range of commits of the run
+    Staged,
+    /// The files changed since `HEAD`, staged or not, and the untracked ones
+    Worktree,
+    /// The files changed by the commits being pushed, on `pre-push`. Elsewhere, the staged ones.
+    PushRange,
+    /// All the files of the repository
+    #[default]
+    All,
+}
+
+/// A hook, as defined in a hook repo's `hooks.yml`, or enabled in the repository's `.hooks.yml`
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Hook {
+    pub name: String,
+    /// What the hook checks, and how to fix what it reports, shown by `explain` & on failures
+    pub description: Option<String>,
+    /// Name of the hook of a repo this one is an instance of, with its own overrides, so that a
+    /// hook may run several times with different settings
+    pub alias_of: Option<String>,
+    /// Events the hook runs on. Defaults to `pre-commit`.
+    pub on_event: Option<Vec<HookEvent>>,
+    /// Regexps of the files the hook is about. Defaults to all of them.
+    pub on_file_regex: Option<Vec<String>>,
+    pub action: Option<Action>,
+    /// Script of the hook repo to run once to prepare the hook
+    pub setup_script: Option<String>,
+    /// Docker/OCI image to run the action in
+    pub image: Option<String>,
+    /// Toolchain the hook needs an environment for. Defaults to `system`.
+    pub language: Option<Language>,
+    /// Packages to install in the language environment, on top of the ones the repo declares
+    pub dependencies: Option<Vec<String>>,
+    /// Nix flake reference or `.nix` file to run the action in
+    pub nix: Option<String>,
+    /// Tool versions to run the action with, through mise or asdf
+    pub tools: Option<Tools>,
+    /// Settings specific to builtin hooks
+    pub settings: Option<serde_yaml::Value>,
+    /// Inline shell script to run instead of `action`
+    pub script: Option<String>,
+    /// WASI module of the hook repo to run the action with, in a sandbox
+    pub wasm: Option<String>,
+    /// Subdirectory the hook is about, relative to the repository root. The hook only runs when
+    /// files under it are staged, from within it.
+    pub root: Option<String>,
+    /// Run the action without network access, on Linux
+    pub no_network: Option<bool>,
+    /// What `{files}` stands for. Defaults to all the files of the repository.
+    pub file_source: Option<FileSource>,
+    /// Programs the hook needs, optionally at a minimum version, eg. `node>=18`
+    pub requires: Option<Vec<String>>,
+    /// Let the action print as it runs, rather than capturing what it prints
+    pub verbose: Option<bool>,
+    /// Name of a lock the hook holds while running: the hooks sharing it, eg. a cache, never run
+    /// at once, even from concurrent git commands
+    pub lock: Option<String>,
+}
+
+impl Hook {
+    /// Tells whether the hook runs without a hook repo, when none defines it: it is an inline
+    /// script, or has an action of its own and is no alias. An action only runs so when none of
+    /// the repos which could not be set up may define the hook, see `unknown_repo_defining`.
+    pub fn is_local(&self) -> bool {
+        self.script.is_some() || (self.action.is_some() && self.alias_of.is_none())
+    }
+
+    /// Overrides the parts of the definition `other` sets
+    pub fn override_with(&mut self, other: &Hook) {
+        if let Some(description) = &other.description {
+            self.description = Some(description.clone());
+        }
+        if let Some(alias_of) = &other.alias_of {
+            self.alias_of = Some(alias_of.clone());
+        }
+        if let Some(on_event) = &other.on_event {
+            self.on_event = Some(on_event.clone());
+        }
+        if let Some(on_file_regex) = &other.on_file_regex {
+            self.on_file_regex = Some(on_file_regex.clone());
+        }
+        if let Some(action) = &other.action {
+            self.action = Some(action.clone());
+        }
+        if let Some(setup_script) = &other.setup_script {
+            self.setup_script = Some(setup_script.clone());
+        }
+        if let Some(image) = &other.image {
+            self.image = Some(image.clone());
+        }
+        if let Some(language) = other.language {
+            self.language = Some(language);
+        }
+        if let Some(dependencies) = &other.dependencies {
+            self.dependencies = Some(dependencies.clone());
+        }
+        if let Some(nix) = &other.nix {
+            self.nix = Some(nix.clone());
+        }
+        if let Some(tools) = &other.tools {
+            self.tools = Some(tools.clone());
+        }
+        if let Some(settings) = &other.settings {
+            self.settings = Some(settings.clone());
+        }
+        if let Some(script) = &other.script {
+            self.script = Some(script.clone());
+        }
+        if let Some(wasm) = &other.wasm {
+            self.wasm = Some(wasm.clone());
+        }
+        if let Some(root) = &other.root {
+            self.root = Some(root.clone());
+        }
+        if let Some(no_network) = other.no_network {
+            self.no_network = Some(no_network);
+        }
+        if let Some(file_source) = other.file_source {
+            self.file_source = Some(file_source);
+        }
+        if let Some(lock) = &other.lock {
+            self.lock = Some(lock.clone());
+        }
+        if let Some(requires) = &other.requires {
+            self.requires = Some(requires.clone());
+        }
+        if let Some(verbose) = other.verbose {
+            self.verbose = Some(verbose);
+        }
+    }
+}
+
+/// Installs `git-hooks` in the submodules having their own configuration, recursively, so that
+/// commits made inside them run their hooks. Returns the paths of those submodules.
+pub fn init_submodules(events: &[HookEvent], mode: InstallMode) -> anyhow::Result<Vec<String>> {
+    let root = git::root()?;
+    let mut initialized = Vec::new();
+    for submodule in git::submodules(&root)? {
+        let path = Path::new(&root).join(&submodule).display().to_string();
+        if default_config_file_in(Path::new(&path)).is_none() {
+            debug!("{} has no configuration, skipping it", submodule);
+            continue;
+        }
+        install(&path, events, mode)?;
+        initialized.push(submodule);
+    }
+    Ok(initialized)
+}
+
+/// Returns the `PATH` of `hook`, defined by the hook repo cloned in `hook_repo_path`: the
+/// directories its language installs programs in, then $PATH, its repo, and the `shared`
+/// directories of the other repos
+fn hook_path(hook: &Hook, hook_repo_path: &str, shared: &[PathBuf]) -> anyhow::Result<String> {
+    // the hook's own repo comes first, for its programs to win over the other repos'
+    let mut last = vec![PathBuf::from(hook_repo_path)];
+    last.extend(
+        shared
+            .iter()
+            .filter(|dir| !dir.starts_with(hook_repo_path))
+            .cloned(),
+    );
+    expand_path(
+        &hook.language.unwrap_or_default().bin_dirs(hook_repo_path),
+        &last,
+    )
+}
+
+/// Runs `hook`, defined by the hook repo cloned in `hook_repo_path`, then re-adds to the index the
+/// staged files it modified, or fails in strict mode. Returns what the hook printed.
+pub fn run_hook(hook: &Hook, hook_repo_path: &str, ctx: &RunContext) -> anyhow::Result<String> {
+    let root = hook_root(hook)?;
+    let bin_path = hook_path(hook, hook_repo_path, &ctx.shared_path)?;
+    if let Some(requires) = &hook.requires {
+        // shown like the output of the failing commands, which the missing program would be
+        if let Err(e) = requires::check(&hook.name, requires, OsStr::new(&bin_path)) {
+            error!("{}", e);
+            return Err(e);
+        }
+    }
+    let mut env = HashMap::new();
+    env.insert("PATH".to_string(), bin_path);
+    if let Some(event) = ctx.event {
+        env.insert(
+            "GIT_HOOKS_EVENT".to_string(),
+            event.to_kebab_case().to_string(),
+        );
+    }
+    // the content of the files the hook may modify, to re-add only the ones it does modify, not
+    // those edited meanwhile. Nothing is re-added without a worktree.
+    let snapshot = if ctx.bare {
+        None
+    } else {
+        // in strict mode, the files are not necessarily staged, eg. in CI
+        let files = if ctx.strict {
+            ctx.changed_files.clone()
+        } else {
+            git::changed_files(true)?
+        };
+        let repo_root = git::root()?;
+        let hashes = content_hashes(&repo_root, &files);
+        Some((repo_root, files, hashes))
+    };
+    let _lock = match &hook.lock {
+        Some(name) => Some(lock::acquire(
+            &lock::hook_lock_path(&git::root()?, name)?,
+            &format!("to release the {} lock", name),
+        )?),
+        None => None,
+    };
+    let mut output = String::new();
+    if let Some(script) = &hook.script {
+        output.push_str(&run_script(hook, script, &root, env, ctx)?);
+    } else {
+        let action = hook.action.as_ref().ok_or_else(|| {
+            GitHooksError::Config(format!("{} has neither an action nor a script", hook.name))
+        })?;
+        for command in action.commands() {
+            output.push_str(&run_command(
+                hook,
+                hook_repo_path,
+                command,
+                &root,
+                env.clone(),
+                ctx,
+            )?);
+        }
+    }
+    let (repo_root, files, hashes) = match snapshot {
+        Some(snapshot) => snapshot,
+        None => return Ok(output),
+    };
+    let files_to_re_add: Vec<&String> = files
+        .iter()
+        .zip(hashes.iter().zip(content_hashes(&repo_root, &files)))
+        .filter(|(_, (before, after))| **before != *after)
+        .map(|(file, _)| file)
+        .collect();
+    if !files_to_re_add.is_empty() {
+        show_changes(hook, &files_to_re_add)?;
+        if ctx.strict {
+            return Err(anyhow::Error::msg(format!(
+                "modified {}: run {}, and stage the files again",
+                files_to_re_add
+                    .iter()
+                    .map(|f| f.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                hook.name
+            )));
+        }
+        debug!("we must re-add those files: {:#?}", files_to_re_add);
+        git::add(&files_to_re_add)?;
+    }
+    Ok(output)
+}
+
+/// Returns the hash of the content of each of `files`, relative to `root`, if it exists
+fn content_hashes(root: &str, files: &[String]) -> Vec<Option<Vec<u8>>> {
+    files
+        .iter()
+        .map(|f| {
+            std::fs::read(Path::new(root).join(f))
+                .ok()
+                .map(|content| Sha256::digest(&content).to_vec())
+        })
+        .collect()
+}
+
+/// Prints on stderr what the hook changed in `files`, colored on a terminal unless `$NO_COLOR` is
+/// set
+fn show_changes<T: AsRef<str>>(hook: &Hook, files: &[T]) -> anyhow::Result<()> {
+    let color = stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    let diff = git::worktree_diff(files, color)?;
+    if !diff.is_empty() {
+        eprintln!("{} modified:", hook.name);
+        eprint!("{}", diff);
+    }
+    Ok(())
+}
+
+/// Returns the files of the hook's `file_source` matching its `on_file_regex`: the `{files}` token.
+/// Bare repositories have no files but the pushed ones.
+fn hook_files(hook: &Hook, root: &str, ctx: &RunContext) -> anyhow::Result<Vec<String>> {
+    if ctx.bare {
+        return hook_changed_files(hook, ctx);
+    }
+    match hook.file_source.unwrap_or_default() {
+        FileSource::Staged => hook_changed_files(hook, ctx),
+        FileSource::Worktree => {
+            let mut files = git::changed_files(true)?;
+            files.extend(git::modified_files()?);
+            files.extend(git::changed_files(false)?);
+            files.sort();
+            files.dedup();
+            Ok(hook_files_among(hook, &files))
+        }
+        FileSource::PushRange => Ok(hook_files_among(hook, &push_range_files(ctx)?)),
+        FileSource::All => get_files(
+            root,
+            hook.on_file_regex
+                .as_ref()
+                .unwrap_or(&vec![".*".to_string()]),
+        ),
+    }
+}
+
+/// Returns the files changed by the commits being pushed, relative to the repository root: those
+/// of the refs `pre-push` reads on stdin, missing from the remotes. The files of the other events
+/// are the ones they are about.
+fn push_range_files(ctx: &RunContext) -> anyhow::Result<Vec<String>> {
+    if ctx.event != Some(HookEvent::PrePush) || ctx.range.is_some() {
+        return Ok(ctx.changed_files.clone());
+    }
+    let mut files = Vec::new();
+    for update in &ctx.updates {
+        if update.is_deletion() {
+            continue;
+        }
+        if update.is_creation() {
+            files.extend(git::unpushed_files(&update.new)?);
+        } else {
+            files.extend(git::diff_files(&update.old, &update.new)?);
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Tells whether any of `files`, relative to the repository root, matches the hook's `root` &
+/// `on_file_regex`
+fn is_about(hook: &Hook, files: &[String]) -> bool {
+    !hook_files_among(hook, files).is_empty()
+}
+
+/// Returns the directory the hook runs in: the repository root, or its `root` subdirectory
+fn hook_root(hook: &Hook) -> anyhow::Result<String> {
+    let root = git::root()?;
+    Ok(match &hook.root {
+        Some(dir) => Path::new(&root).join(dir).display().to_string(),
+        None => root,
+    })
+}
+
+/// Returns the files the event is about, staged or pushed, matching the hook's `on_file_regex`:
+/// the `{changed_files}` token. They are relative to the hook's `root`.
+fn hook_changed_files(hook: &Hook, ctx: &RunContext) -> anyhow::Result<Vec<String>> {
+    Ok(hook_files_among(hook, &ctx.changed_files))
+}
+
+/// Returns the untracked files, not ignored, matching the hook's `on_file_regex`: the
+/// `{untracked_files}` token. They are relative to the hook's `root`.
+fn hook_untracked_files(hook: &Hook, ctx: &RunContext) -> anyhow::Result<Vec<String>> {
+    if ctx.bare {
+        return Ok(vec![]);
+    }
+    Ok(hook_files_among(hook, &git::changed_files(false)?))
+}
+
+/// Returns the files modified since they were staged, or without being staged, matching the
+/// hook's `on_file_regex`: the `{modified_files}` token. They are relative to the hook's `root`.
+fn hook_modified_files(hook: &Hook, ctx: &RunContext) -> anyhow::Result<Vec<String>> {
+    if ctx.bare {
+        return Ok(vec![]);
+    }
+    Ok(hook_files_among(hook, &git::modified_files()?))
+}
+
+/// Returns the `files`, relative to the repository root, under the hook's `root` & matching its
+/// `on_file_regex`, relative to its `root`
+fn hook_files_among(hook: &Hook, files: &[String]) -> Vec<String> {
+    let files = match &hook.root {
+        Some(dir) => monorepo::files_under(dir, files),
+        None => files.to_vec(),
+    };
+    files
+        .iter()
+        .map(Path::new)
+        .filter(|p| {
+            matches(
+                p,
+                hook.on_file_regex
+                    .as_ref()
+                    .unwrap_or(&vec![".*".to_string()]),
+            )
+        })
+        .map(|p| p.display().to_string())
+        .collect()
+}
+
+/// Runs an inline script. It is written to a temporary file, and the values of the tokens are
+/// given through the environment: `GIT_HOOKS_ROOT`, the newline-separated `GIT_HOOKS_FILES`,
+/// `GIT_HOOKS_CHANGED_FILES`, `GIT_HOOKS_UNTRACKED_FILES` & `GIT_HOOKS_MODIFIED_FILES`, and
+/// `GIT_HOOKS_DIFF`. The arguments git gave to the hook are given to the script, eg. the commit
+/// message file as `$1`.
+/// Scripts without a shebang are run with `sh -e`.
+fn run_script(
+    hook: &Hook,
+    script: &str,
+    root: &str,
+    mut env: HashMap<String, String>,
+    ctx: &RunContext,
+) -> anyhow::Result<String> {
+    env.insert("GIT_HOOKS_ROOT".to_string(), root.to_string());
+    env.insert(
+        "GIT_HOOKS_FILES".to_string(),
+        hook_files(hook, root, ctx)?.join("\n"),
+    );
+    env.insert(
+        "GIT_HOOKS_CHANGED_FILES".to_string(),
+        hook_changed_files(hook, ctx)?.join("\n"),
+    );
+    env.insert(
+        "GIT_HOOKS_UNTRACKED_FILES".to_string(),
+        hook_untracked_files(hook, ctx)?.join("\n"),
+    );
+    env.insert(
+        "GIT_HOOKS_MODIFIED_FILES".to_string(),
+        hook_modified_files(hook, ctx)?.join("\n"),
+    );
+    env.insert(
+        "GIT_HOOKS_DIFF".to_string(),
+        write_diff_file(hook, &hook_diff(hook, ctx)?)?,
+    );
+    let script_path = temp_path(hook, "");
+    create_private_file(&script_path, script.as_bytes(), 0o700)?;
+    let script_path = script_path.display().to_string();
+    let script_args = ctx.args.iter().cloned();
+    let (cmd, args): (String, Vec<String>) = if script.starts_with("#!") && cfg!(windows) {
+        // Windows knows nothing of shebangs, but the sh of git for Windows does
+        let args = ["-c", "exec \"$0\" \"$@\"", &script_path]
+            .iter()
+            .map(|a| a.to_string())
+            .chain(script_args)
+            .collect();
+        ("sh".to_string(), args)
+    } else if script.starts_with("#!") {
+        (script_path.clone(), script_args.collect())
+    } else {
+        let args = ["-e", &script_path]
+            .iter()
+            .map(|a| a.to_string())
+            .chain(script_args)
+            .collect();
+        ("sh".to_string(), args)
+    };
+    let (cmd, args) = if hook.no_network.unwrap_or_default() {
+        sandbox::wrap_command(&hook.name, cmd, args)
+    } else {
+        (cmd, args)
+    };
+    let r = execute_hook_cmd(hook, &cmd, &args, root, &env, ctx);
+    if let Err(e) = std::fs::remove_file(&script_path) {
+        warn!("could not remove temporary script {}: {}", script_path, e);
+    }
+    remove_diff_file(hook);
+    let (_, output) = r?;
+    Ok(output)
+}
+
+/// Returns the path of a temporary file of the hook, ending with `extension`. Being predictable,
+/// it is only to be created with `create_private_file`.
+fn temp_path(hook: &Hook, extension: &str) -> PathBuf {
+    let name: String = hook
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    env::temp_dir().join(format!(
+        "git-hooks-{}-{}{}",
+        std::process::id(),
+        name,
+        extension
+    ))
+}
+
+/// Returns the diff of the files the event is about matching the hook's `on_file_regex`: the
+/// staged changes, or those of the range of commits of the run. Bare repositories have none.
+fn hook_diff(hook: &Hook, ctx: &RunContext) -> anyhow::Result<String> {
+    let files: Vec<&String> = ctx
+        .changed_files
+        .iter()
+        .filter(|f| is_about(hook, std::slice::from_ref(f)))
+        .collect();
+    if ctx.bare || files.is_empty() {
+        return Ok(String::new());
+    }
+    let range = ctx.range.as_ref().map(|r| (r.from.as_str(), r.to.as_str()));
+    git::diff(range, &files)
+}
+
+/// Writes `diff` in the temporary file of the `{diff}` token, and returns its path
+fn write_diff_file(hook: &Hook, diff: &str) -> anyhow::Result<String> {
+    let path = temp_path(hook, ".diff");
+    create_private_file(&path, diff.as_bytes(), 0o600)?;
+    Ok(path.display().to_string())
+}
+
+fn remove_diff_file(hook: &Hook) {
+    let path = temp_path(hook, ".diff");
+    if path.exists() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!("could not remove temporary diff {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Runs one of the command lines of a hook's action, after substituting its tokens.
+/// Returns what it printed.
+fn run_command(
+    hook: &Hook,
+    hook_repo_path: &str,
+    command: &str,
+    root: &str,
+    env: HashMap<String, String>,
+    ctx: &RunContext,
+) -> anyhow::Result<String> {
+    let (cmd, final_args) = expand_command(hook, command, root, ctx)?;
+    let r = execute_command(hook, hook_repo_path, root, cmd, final_args, env, ctx);
+    remove_diff_file(hook);
+    r
+}
+
+/// Returns the path of the script `cmd` of the repository at `repo_root`, eg.
+/// `./scripts/check.sh`, that the action of `hook` runs, checking that it can be run
+fn project_script(hook: &Hook, repo_root: &str, cmd: &str) -> anyhow::Result<String> {
+    let path = Path::new(repo_root).join(cmd.trim_start_matches("./"));
+    match is_executable(&path) {
+        Ok(true) => Ok(path.display().to_string()),
+        Ok(false) => Err(GitHooksError::Config(format!(
+            "{}: {} is not executable: run `chmod +x {}`, and `git update-index --chmod=+x {}` for the others to get it so",
+            hook.name, cmd, cmd, cmd
+        ))
+        .into()),
+        Err(e) => Err(GitHooksError::Config(format!(
+            "{}: cannot run {}, relative to the root of the repository: {}",
+            hook.name, cmd, e
+        ))
+        .into()),
+    }
+}
+
+/// Checks the checksum of `program`, if it is one of the hook repo checked out in
+/// `hook_repo_path`, eg. one of its scripts or a program its setup installed
+fn verify_program(
+    hook: &Hook,
+    hook_repo_path: &str,
+    program: &Path,
+    ctx: &RunContext,
+) -> anyhow::Result<()> {
+    // the repository's own hook repo changes with it
+    if is_project_hooks_dir(hook_repo_path) {
+        return Ok(());
+    }
+    // the links, eg. to the python of a virtualenv, lead out of the repo
+    let (program, repo_dir) = match (
+        program.canonicalize(),
+        Path::new(hook_repo_path).canonicalize(),
+    ) {
+        (Ok(program), Ok(repo_dir)) if program.starts_with(&repo_dir) => (program, repo_dir),
+        _ => return Ok(()),
+    };
+    // shown like the output of the failing commands
+    checksums::verify(&hook.name, &repo_dir, &program, ctx.strict).inspect_err(|e| error!("{}", e))
+}
+
+/// Runs the program `cmd` of a hook's action, with its tokens substituted
+fn execute_command(
+    hook: &Hook,
+    hook_repo_path: &str,
+    root: &str,
+    cmd: String,
+    final_args: Vec<String>,
+    mut env: HashMap<String, String>,
+    ctx: &RunContext,
+) -> anyhow::Result<String> {
+    let repository = git::root()?;
+    if let Some(module) = &hook.wasm {
+        let module = Path::new(hook_repo_path).join(module);
+        if Path::new(hook_repo_path) != Path::new(&repository) {
+            verify_program(hook, hook_repo_path, &module, ctx)?;
+        }
+        let mut argv = vec![cmd];
+        argv.extend(final_args);
+        let code = wasm::run(&module, root, &argv)?;
+        debug!(
+            "finished executing {} with exit status {}",
+            module.display(),
+            code
+        );
+        return if code == 0 {
+            Ok(String::new())
+        } else {
+            Err(anyhow::Error::msg(format!(
+                "{} reported execution failure: {}",
+                hook.name, code
+            )))
+        };
+    }
+    // the local hooks run from the repository: the paths of their scripts are relative to its
+    // root, whichever directory they run in. Containers mount it their own way.
+    let cmd = if cmd.contains('/')
+        && Path::new(&cmd).is_relative()
+        && hook.image.is_none()
+        && Path::new(hook_repo_path) == Path::new(&repository)
+    {
+        // shown like the output of the failing commands
+        project_script(hook, hook_repo_path, &cmd).inspect_err(|e| error!("{}", e))?
+    } else {
+        cmd
+    };
+    // the programs of the hook repos, not those of the images
+    if Path::new(hook_repo_path) != Path::new(&repository) && hook.image.is_none() {
+        let program = if cmd.contains('/') {
+            Some(Path::new(root).join(&cmd))
+        } else {
+            env.get("PATH")
+                .and_then(|path| find_program(&cmd, OsStr::new(path)))
+        };
+        if let Some(program) = program {
+            verify_program(hook, hook_repo_path, &program, ctx)?;
+        }
+    }
+    let (cmd, final_args) = wrap_command(hook, hook_repo_path, root, cmd, final_args, &mut env)?;
+    let (s, output) = execute_hook_cmd(hook, &cmd, &final_args, root, &env, ctx)?;
+    debug!("finished executing {} with exit status {:?}", cmd, s.code());
+    if !s.success() {
+        Err(anyhow::Error::msg(format!(
+            "{:?} reported execution failure: {:?}",
+            hook,
+            s.code()
+        )))
+    } else {
+        Ok(output)
+    }
+}
+
+/// Runs `cmd` for `hook`, streaming what it prints when the run or the hook is verbose, else
+/// capturing it. Returns its exit status and what it printed, if captured.
+fn execute_hook_cmd(
+    hook: &Hook,
+    cmd: &str,
+    args: &[String],
+    root: &str,
+    env: &HashMap<String, String>,
+    ctx: &RunContext,
+) -> anyhow::Result<(ExitStatus, String)> {
+    if ctx.verbose || hook.verbose.unwrap_or_default() {
+        eprintln!("{}:", hook.name);
+        let status = execute_cmd_live(cmd, args, Some(root), Some(env), ctx.input.as_deref())?;
+        return Ok((status, String::new()));
+    }
+    let (status, stdout, stderr) =
+        execute_cmd_with_input(cmd, args, Some(root), Some(env), ctx.input.as_deref())?;
+    Ok((status, stdout + &stderr))
+}
+
+/// Splits one of the command lines of a hook's action into the program & its arguments, with the
+/// tokens substituted
+fn expand_command(
+    hook: &Hook,
+    command: &str,
+    root: &str,
+    ctx: &RunContext,
+) -> anyhow::Result<(String, Vec<String>)> {
+    let mut should_run = true;
+    // parse the action cli
+    let mut action = Shlex::new(command);
+    let cmd = action
+        .next()
+        .ok_or_else(|| GitHooksError::Config(format!("{}: empty action", hook.name)))?;
+    let args: Vec<String> = action.collect();
+    let mut final_args: Vec<String> = Vec::new();
+    for arg in &args {
+        if let Some(token) = ActionFileToken::from_str(arg) {
+            match token {
+                ActionFileToken::Files => {
+                    let mut files = hook_files(hook, root, ctx)?;
+                    should_run = !files.is_empty();
+                    final_args.append(&mut files);
+                }
+                // running the action once per file changes the whole execution logic
+                ActionFileToken::File | ActionFileToken::ChangedFile => {
+                    return Err(GitHooksError::Config(format!(
+                        "{}: {} is not supported yet",
+                        hook.name, arg
+                    ))
+                    .into());
+                }
+                ActionFileToken::ChangedFiles => {
+                    let mut changed_files = hook_changed_files(hook, ctx)?;
+                    should_run = !changed_files.is_empty();
+                    final_args.append(&mut changed_files);
+                }
+                ActionFileToken::UntrackedFiles => {
+                    let mut files = hook_untracked_files(hook, ctx)?;
+                    should_run = !files.is_empty();
+                    final_args.append(&mut files);
+                }
+                ActionFileToken::ModifiedFiles => {
+                    let mut files = hook_modified_files(hook, ctx)?;
+                    should_run = !files.is_empty();
+                    final_args.append(&mut files);
+                }
+                ActionFileToken::Root => {
+                    final_args.push(root.to_string());
+                }
+                ActionFileToken::Event => {
+                    if let Some(event) = ctx.event {
+                        final_args.push(event.to_kebab_case().to_string());
+                    }
+                }
+                ActionFileToken::Diff => {
+                    let diff = hook_diff(hook, ctx)?;
+                    should_run = !diff.is_empty();
+                    final_args.push(write_diff_file(hook, &diff)?);
+                }
+            }
+        } else if should_run {
+            final_args.push(arg.to_string());
+        } else {
+            info!("Could find any files to run hook on");
+        }
+    }
+    Ok((cmd, final_args))
+}
+
+/// Wraps the program `cmd` so that it runs in the environment the hook asks for: a container, a
+/// nix shell, or with pinned tools, and without network access if it says so
+fn wrap_command(
+    hook: &Hook,
+    hook_repo_path: &str,
+    root: &str,
+    cmd: String,
+    final_args: Vec<String>,
+    env: &mut HashMap<String, String>,
+) -> anyhow::Result<(String, Vec<String>)> {
+    let no_network = hook.no_network.unwrap_or_default();
+    let (cmd, final_args) = match (&hook.image, &hook.nix) {
+        (Some(_), Some(_)) => {
+            return Err(GitHooksError::Config(format!(
+                "{}: image and nix cannot be used together",
+                hook.name
+            ))
+            .into())
+        }
+        // containers have their own network settings
+        (Some(image), None) => {
+            return container::wrap_command(
+                image,
+                root,
+                hook_repo_path,
+                &cmd,
+                &final_args,
+                no_network,
+            )
+        }
+        (None, Some(nix)) => nix::wrap_command(nix, hook_repo_path, &cmd, &final_args),
+        (None, None) => match &hook.tools {
+            Some(tools) => toolchain::wrap_command(tools, cmd, final_args, env),
+            None => (cmd, final_args),
+        },
+    };
+    Ok(if no_network {
+        sandbox::wrap_command(&hook.name, cmd, final_args)
+    } else {
+        (cmd, final_args)
+    })
+}
+
+/// A hook repo, as listed in the repository's `.hooks.yml`
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct ExternalHookRepo {
+    /// The hooks the repo defines, read from its `hooks.yml`
+    pub hooks: Vec<Hook>,
+    pub url: String,
+    /// Revision of the repo to check out. Defaults to the latest one.
+    pub version: Option<String>,
+    /// Events the hooks of the repo without `on_event` run on, instead of the configuration's
+    /// `default_on_event`
+    pub default_on_event: Option<Vec<HookEvent>>,
+    /// Repo-wide setup script, run before the hooks' own `setup_script`s.
+    /// Only read from the repo's `hooks.yml`.
+    setup: Option<String>,
+    /// Tool versions used by all the repo's hooks, unless a hook sets its own.
+    /// Only read from the repo's `hooks.yml`.
+    tools: Option<Tools>,
+}
+
+impl ExternalHookRepo {
+    /// Checks the repo out, from its copy vendored in the repository if any, else from its clone,
+    /// reads its hooks, and sets them up. The repository's own hook repo is used as it is.
+    pub fn init(&mut self, opts: &LoadOptions) -> anyhow::Result<()> {
+        let root = PathBuf::from(git::root()?);
+        if self.is_project() {
+            let dir = self.local_path()?;
+            self.read_manifest(&dir)?;
+            return self.setup_in(&dir, opts.force_setup);
+        }
+        let _lock = self.lock()?;
+        protect::discard_unrecorded(&self.local_path()?)?;
+        // its hooks do not run until it is looked into, which must not go unnoticed
+        protect::check(&self.url, &self.local_path()?).inspect_err(|e| error!("{}", e))?;
+        let (dir, files) = match vendor::find(&root, &self.url, self.version.as_deref())? {
+            Some(vendored) => {
+                let dir = vendor::run_dir(&self.url)?;
+                vendor::checkout(&root, &vendored, &dir)?;
+                let files = vendor::files(&root, &vendored);
+                protect::protect(&dir, &files)?;
+                (dir, files)
+            }
+            None => {
+                let dir = if opts.offline {
+                    self.checkout_cached().or_else(|e| {
+                        debug!("{} is not cached: {}", self.url, e);
+                        self.fetch()
+                    })?
+                } else {
+                    self.fetch()?
+                };
+                let files = git::ls_files(&dir)?;
+                (dir, files)
+            }
+        };
+        self.read_manifest(&dir)?;
+        let setup = self.setup_in(&dir, opts.force_setup);
+        // what the setup added, eg. the dependencies it installed, is recorded as well, even
+        // should it fail halfway
+        protect::protect(&dir, &files)?;
+        setup
+    }
+
+    /// Locks the clone of the repo, and its vendored working copy, for this process to update &
+    /// set them up
+    pub fn lock(&self) -> anyhow::Result<lock::Lock> {
+        lock::acquire(
+            Path::new(&format!("{}.lock", get_local_repo_path(&self.url)?)),
+            &format!("to set {} up", self.url),
+        )
+    }
+
+    /// Clones or updates the repo, then checks its `version` out, read-only. Returns where it is
+    /// cloned.
+    pub fn fetch(&self) -> anyhow::Result<String> {
+        let clone_dir = get_local_repo_path(&self.url)?;
+        protect::discard_unrecorded(&clone_dir)?;
+        // a clone the hooks tampered with is left as it is, to be looked into
+        protect::check(&self.url, &clone_dir)?;
+        debug!("cloning {} to {}", &self.url, &clone_dir);
+        let updated = git::pull(&self.url, &clone_dir)
+            .map_err(|e| {
+                GitHooksError::Network(format!("could not fetch {}: {}", self.url, e)).into()
+            })
+            .and_then(|_| match &self.version {
+                Some(v) => git::checkout(v, &clone_dir),
+                None => Ok(()),
+            });
+        // only git ran since the check: what it left is recorded, whether it failed or not
+        if Path::new(&clone_dir).is_dir() {
+            protect::protect(&clone_dir, &git::ls_files(&clone_dir)?)?;
+        }
+        updated?;
+        Ok(clone_dir)
+    }
+
+    /// Checks the `version` of the repo out of its clone, without updating it. Returns where it is
+    /// cloned.
+    fn checkout_cached(&self) -> anyhow::Result<String> {
+        let clone_dir = get_local_repo_path(&self.url)?;
+        if !Path::new(&clone_dir).is_dir() {
+            return Err(GitHooksError::Config(format!("{} does not exist", clone_dir)).into());
+        }
+        protect::check(&self.url, &clone_dir)?;
+        let checked_out = match &self.version {
+            Some(v) => git::checkout(v, &clone_dir),
+            None => Ok(()),
+        };
+        protect::protect(&clone_dir, &git::ls_files(&clone_dir)?)?;
+        checked_out?;
+        Ok(clone_dir)
+    }
+
+    /// Returns where the repo is checked out: the working copy of its vendored files if the
+    /// repository vendors it at this version, else its clone. The repository's own hook repo is
+    /// in its worktree.
+    pub fn local_path(&self) -> anyhow::Result<String> {
+        let root = PathBuf::from(git::root()?);
+        if self.is_project() {
+            return Ok(root.join(PROJECT_HOOKS_DIR).display().to_string());
+        }
+        match vendor::find(&root, &self.url, self.version.as_deref())? {
+            Some(_) => vendor::run_dir(&self.url),
+            None => get_local_repo_path(&self.url),
+        }
+    }
+
+    /// Tells whether the repo is the repository's own, its `PROJECT_HOOKS_DIR`
+    pub fn is_project(&self) -> bool {
+        self.url == PROJECT_HOOKS_DIR
+    }
+
+    /// Returns the directories of the repo checked out in `clone_dir` holding programs: the ones
+    /// the languages of its hooks install them in, then the repo itself
+    pub fn bin_dirs(&self, clone_dir: &str) -> Vec<PathBuf> {
+        let mut languages: Vec<Language> = Vec::new();
+        for hook in &self.hooks {
+            let language = hook.language.unwrap_or_default();
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        let mut dirs: Vec<PathBuf> = languages
+            .into_iter()
+            .flat_map(|language| language.bin_dirs(clone_dir))
+            .collect();
+        dirs.push(PathBuf::from(clone_dir));
+        dirs
+    }
+
+    /// Reads the hooks, & the settings they share, from the `hooks.yml` of the repo checked out in
+    /// `dir`
+    pub fn read_manifest(&mut self, dir: &str) -> anyhow::Result<()> {
+        let mut repo_config = String::new();
+        File::open(format!("{}/{}", dir, "hooks.yml"))?.read_to_string(&mut repo_config)?;
+        debug!("Got hooks.yml");
+        let manifest = format!("{}/hooks.yml", self.url);
+        let hook_repo: ExternalHookRepo = serde_yaml::from_str(&repo_config)
+            .map_err(|e| GitHooksError::Config(format!("invalid {}: {}", manifest, e)))?;
+        check_regexes(&hook_repo.hooks, &manifest)?;
+        debug!("{:?}", hook_repo);
+        self.hooks = hook_repo.hooks;
+        if let Some(repo_tools) = &hook_repo.tools {
+            for hook in self.hooks.iter_mut() {
+                let mut tools = repo_tools.clone();
+                tools.extend(hook.tools.take().unwrap_or_default());
+                hook.tools = Some(tools);
+            }
+        }
+        self.setup = hook_repo.setup;
+        Ok(())
+    }
+
+    /// prepares the language environments the hooks need, then runs the optional repo-level
+    /// then per-hook setup scripts of the repo checked out in `clone_dir`, unless they already ran
+    /// for this version of the repo. `force` reruns them anyway.
+    pub fn setup_in(&self, clone_dir: &str, force: bool) -> anyhow::Result<()> {
+        let mut marker = SetupMarker::load(clone_dir)?;
+        let mut languages: Vec<Language> = Vec::new();
+        for hook in &self.hooks {
+            let language = hook.language.unwrap_or_default();
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        let mut bin_dirs = Vec::new();
+        for language in languages {
+            let mut dependencies: Vec<String> = self
+                .hooks
+                .iter()
+                .filter(|h| h.language.unwrap_or_default() == language)
+                .filter_map(|h| h.dependencies.as_ref())
+                .flatten()
+                .cloned()
+                .collect();
+            dependencies.sort();
+            dependencies.dedup();
+            let key = marker.key(
+                clone_dir,
+                &format!(
+                    "language:{} {}",
+                    language.to_kebab_case(),
+                    dependencies.join(" ")
+                ),
+            )?;
+            if force || !marker.is_done(&key) {
+                language.install(clone_dir, &dependencies)?;
+                checksums::record_setup(clone_dir, &key)?;
+                marker.mark_done(key);
+                marker.save()?;
+            }
+            bin_dirs.extend(language.bin_dirs(clone_dir));
+        }
+        bin_dirs.push(PathBuf::from(&clone_dir));
+        let path = expand_path(&bin_dirs, &[])?;
+        let mut env = HashMap::new();
+        env.insert("PATH".to_string(), path);
+        let setup_scripts = self
+            .setup
+            .iter()
+            .chain(self.hooks.iter().filter_map(|h| h.setup_script.as_ref()));
+        for setup_script in setup_scripts {
+            let key = marker.key(clone_dir, setup_script)?;
+            if !force && marker.is_done(&key) {
+                debug!("{} already ran for this version, skipping", setup_script);
+                continue;
+            }
+            utils::execute_cmd(setup_script, &[] as &[&str], Some(clone_dir), Some(&env))?;
+            checksums::record_setup(clone_dir, &key)?;
+            marker.mark_done(key);
+            marker.save()?;
+        }
+        Ok(())
+    }
+}
+
+/// Options driving how the configuration is loaded & the external repos are initialized
+#[derive(Debug, Default, Clone)]
+pub struct LoadOptions {
+    /// Do not ask for trust before cloning new repos
+    pub trust_all: bool,
+    /// Rerun the setup scripts even if they already ran for the current repo versions
+    pub force_setup: bool,
+    /// Read the configuration from this revision instead of the worktree. Bare repositories
+    /// default to `HEAD`.
+    pub config_ref: Option<String>,
+    /// Use the clones of the repos as they are, without pulling them, unless they are missing or
+    /// lack the pinned version
+    pub offline: bool,
+    /// Reject the configuration files having keys no setting stands for, eg. misspelled ones
+    pub strict: bool,
+}
+
+/// The configuration of a repository, read from its `.hooks.yml`
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct HookConfig {
+    /// Version of the schema the configuration is written for, see `migrate::CONFIG_VERSION`
+    pub version: Option<u32>,
+    /// Configuration fragments to merge beneath this one
+    pub include: Vec<Include>,
+    /// Replacements of the repos URLs prefixes, eg. to use an internal mirror
+    pub mirrors: BTreeMap<String, String>,
+    /// Names of hooks enabled by a lower configuration, to turn off
+    pub disable: Vec<String>,
+    /// Rules the pushes must follow, when `git-hooks` runs on a server
+    pub push_policy: Option<PushPolicy>,
+    /// The hook repos to fetch hooks from
+    pub repos: Vec<ExternalHookRepo>,
+    /// The hooks to enable, possibly overriding parts of their definition
+    pub hooks: Vec<Hook>,
+    /// How many times to run the hooks at most, for the formatters to agree: as long as hooks
+    /// modify staged files, the ones about those files run again
+    pub autofix: Option<usize>,
+    /// Events the hooks without `on_event` run on. Defaults to `pre-commit`.
+    pub default_on_event: Option<Vec<HookEvent>>,
+    /// Whether the `PATH` of the hooks holds the directories of all the hook repos, after their
+    /// own repo's, for them to call what the setup of another repo installed
+    pub shared_path: Option<bool>,
+}
+
+/// Checks the `on_file_regex` of the hooks defined in `source`, so that the invalid ones fail
+/// when the configuration is loaded rather than when matching the files
+fn check_regexes(hooks: &[Hook], source: &str) -> anyhow::Result<()> {
+    for hook in hooks {
+        for regex in hook.on_file_regex.iter().flatten() {
+            if let Err(e) = Regex::new(regex) {
+                return Err(GitHooksError::Config(format!(
+                    "{}: invalid on_file_regex of {}: {}",
+                    source, hook.name, e
+                ))
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl HookConfig {
+    /// Reads the configuration, `.hooks.yml`, `.hooks.toml` or `.hooks.json` unless `filename` is
+    /// given, between the local overrides, `.hooks.local.yml`, and the user's then the system's
+    /// configuration. The configurations of the subprojects holding staged files are merged over
+    /// it, eg. `services/api/.hooks.yml`. Then checks the external repos against the user & system
+    /// policies, and clones & sets up the ones the user trusts.
+    pub fn from_file(filename: Option<&str>, opts: &LoadOptions) -> anyhow::Result<HookConfig> {
+        let mut conf_content = String::new();
+        let bare = git::is_bare()?;
+        // the default configuration is at the root, wherever git-hooks is run from
+        let path = match filename {
+            Some(f) => PathBuf::from(f),
+            None if bare => PathBuf::from(DEFAULT_CONFIG_FILES[0]),
+            None => {
+                let root = PathBuf::from(git::root()?);
+                default_config_file_in(&root).unwrap_or_else(|| root.join(DEFAULT_CONFIG_FILES[0]))
+            }
+        };
+        let p = &path.display().to_string();
+        // within a revision, paths are relative to the root
+        let revision_path = match filename {
+            Some(f) => f.to_string(),
+            None => path
+                .file_name()
+                .map(|f| f.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        // bare repositories have no worktree to read the configuration from
+        let config_ref = match &opts.config_ref {
+            Some(revision) => Some(revision.as_str()),
+            None if bare && filename.is_none() => Some("HEAD"),
+            None => None,
+        };
+        let mut unconfigured = false;
+        match config_ref {
+            Some(revision) => conf_content = git::show(revision, &revision_path)?,
+            None => match File::open(p) {
+                Ok(mut f) => {
+                    f.read_to_string(&mut conf_content)?;
+                }
+                // hooks installed globally also run in the repositories not using git-hooks
+                Err(e) if filename.is_none() && e.kind() == std::io::ErrorKind::NotFound => {
+                    debug!("no config file {}, only using the user & system ones", p);
+                    unconfigured = true;
+                }
+                Err(e) => {
+                    return Err(GitHooksError::Config(format!(
+                        "could not read config file {}: {}",
+                        p, e
+                    ))
+                    .into());
+                }
+            },
+        }
+        let repo_conf = if unconfigured {
+            HookConfig::default()
+        } else {
+            HookConfig::parse(
+                &Source::File(Path::new(p).to_path_buf()),
+                &conf_content,
+                &mut vec![],
+                opts.strict,
+            )?
+        };
+        let mut conf = HookConfig::read_optional(&system_config_dir(), "config", opts.strict)?
+            .unwrap_or_default();
+        // the organization's hooks cannot be disabled
+        let mandatory: Vec<String> = conf.hooks.iter().map(|h| h.name.clone()).collect();
+        if let Some(user_conf) =
+            HookConfig::read_optional(&user_config_dir()?, "config", opts.strict)?
+        {
+            conf.merge(user_conf);
+        }
+        conf.merge(repo_conf);
+        if filename.is_none() && !bare {
+            for path in
+                monorepo::nested_config_files(Path::new(&git::root()?), &git::changed_files(true)?)
+            {
+                debug!("merging nested config {}", path.display());
+                let content = std::fs::read_to_string(&path)?;
+                conf.merge(HookConfig::parse(
+                    &Source::File(path),
+                    &content,
+                    &mut vec![],
+                    opts.strict,
+                )?);
+            }
+        }
+        let conf_dir = Path::new(p).parent().unwrap_or_else(|| Path::new(""));
+        if let Some(local_conf) = HookConfig::read_optional(conf_dir, ".hooks.local", opts.strict)?
+        {
+            conf.merge(local_conf);
+        }
+        conf.disable_hooks(&mandatory);
+        conf.apply_mirrors();
+        if !bare {
+            conf.add_project_hooks()?;
+        }
+        conf.update_repos_config();
+        debug!("{:?}", conf);
+        let policies = Policies::load()?;
+        for repo in conf.repos.iter().filter(|r| !r.is_project()) {
+            policies.check(&repo.url)?;
+        }
+        let mut trust_store = TrustStore::load()?;
+        conf.repos
+            .iter_mut()
+            .map(|repo| {
+                // versioned with the repository, like its inline scripts
+                if !repo.is_project() {
+                    match trust_store.ensure_trusted(
+                        &repo.url,
+                        repo.version.as_deref(),
+                        opts.trust_all,
+                    ) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            error!(
+                                "{} is not trusted, its hooks will not run: rerun with --trust-all to run them anyway",
+                                repo.url
+                            );
+                            return;
+                        }
+                        Err(e) => {
+                            error!("the hooks of {} will not run: {}", repo.url, e);
+                            return;
+                        }
+                    }
+                }
+                debug!("init {:?}", repo.url);
+                let r = repo.init(opts);
+                if let Err(e) = r {
+                    warn!(
+                        "Got an error while attempting to initialize repo {}: {}",
+                        repo.url, e
+                    );
+                }
+            })
+            .for_each(drop); // consume the iterator
+
+        // the repos' hooks are only known once they are initialized
+        conf.update_repos_config();
+        Ok(conf)
+    }
+
+    /// Returns what is likely wrong with the configuration loaded, for the user to be warned
+    /// about: the enabled hooks matching nothing, unless some repos could not be set up, their
+    /// hooks being unknown
+    pub fn warnings(&self) -> Vec<String> {
+        if self.repos.iter().any(|r| r.hooks.is_empty()) {
+            return vec![];
+        }
+        self.unmatched_hooks()
+    }
+
+    /// Reads the hooks of the repos from their clones or vendored copies as they are checked out,
+    /// without fetching nor setting anything up. Returns the URLs of the repos not checked out
+    /// yet, whose hooks are unknown.
+    pub fn read_local_repos(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut missing = Vec::new();
+        for repo in &mut self.repos {
+            let dir = repo.local_path()?;
+            if Path::new(&dir).join("hooks.yml").is_file() {
+                repo.read_manifest(&dir)?;
+            } else {
+                missing.push(repo.url.clone());
+            }
+        }
+        self.update_repos_config();
+        Ok(missing)
+    }
+
+    /// Tells which of the enabled hooks match nothing, and would never run: neither builtin nor
+    /// local hooks, they are defined by none of the repos, eg. misspelled
+    pub fn unmatched_hooks(&self) -> Vec<String> {
+        let defined: Vec<&str> = self
+            .repos
+            .iter()
+            .flat_map(|r| &r.hooks)
+            .filter(|h| h.alias_of.is_none())
+            .map(|h| h.name.as_str())
+            .collect();
+        self.hooks
+            .iter()
+            .filter(|h| !h.name.starts_with(BUILTIN_PREFIX) && !h.is_local())
+            .filter(|h| !self.is_defined_by_repo(&h.name))
+            .map(|h| {
+                let name = h.alias_of.as_ref().unwrap_or(&h.name);
+                let mut problem = match &h.alias_of {
+                    Some(aliased) => format!(
+                        "{} is an alias of {}, which none of the hook repos defines",
+                        h.name, aliased
+                    ),
+                    None => format!(
+                        "{} is enabled, but none of the hook repos defines it: it never runs",
+                        h.name
+                    ),
+                };
+                if let Some(closest) = defined
+                    .iter()
+                    .map(|d| (strict::distance(d, name), *d))
+                    .filter(|(distance, _)| *distance <= 2)
+                    .min()
+                {
+                    problem.push_str(&format!(", did you mean {}?", closest.1));
+                }
+                problem
+            })
+            .collect()
+    }
+
+    /// Reads the configuration file `path`, its includes resolved, without setting the repos up.
+    /// In `strict` mode, the keys no setting stands for are errors.
+    pub fn read(path: &Path, strict: bool) -> anyhow::Result<HookConfig> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            GitHooksError::Config(format!(
+                "could not read config file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let mut conf = HookConfig::parse(
+            &Source::File(path.to_path_buf()),
+            &content,
+            &mut vec![],
+            strict,
+        )?;
+        conf.apply_mirrors();
+        if !git::is_bare()? {
+            conf.add_project_hooks()?;
+        }
+        Ok(conf)
+    }
+
+    /// Adds the repository's own hook repo, its `PROJECT_HOOKS_DIR`, to the repos, if it has one
+    /// the configuration does not list already
+    fn add_project_hooks(&mut self) -> anyhow::Result<()> {
+        let dir = Path::new(&git::root()?).join(PROJECT_HOOKS_DIR);
+        if dir.join("hooks.yml").is_file() && !self.repos.iter().any(|r| r.is_project()) {
+            debug!("using the hook repo {}", dir.display());
+            self.repos.push(ExternalHookRepo {
+                url: PROJECT_HOOKS_DIR.to_string(),
+                ..Default::default()
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads the `name` configuration file of `dir`, such as the user's
+    /// `~/.config/git-hooks/config.yml`, if there is one
+    fn read_optional(dir: &Path, name: &str, strict: bool) -> anyhow::Result<Option<HookConfig>> {
+        match find_config_file(dir, name) {
+            Some(path) => {
+                debug!("reading {}", path.display());
+                let content = std::fs::read_to_string(&path)?;
+                Ok(Some(HookConfig::parse(
+                    &Source::File(path),
+                    &content,
+                    &mut vec![],
+                    strict,
+                )?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the configuration read from `source`, and merges the fragments it includes beneath
+    /// it. `including` holds the configurations including this one, to detect cycles. In `strict`
+    /// mode, the keys no setting stands for are errors, rather than ignored.
+    fn parse(
+        source: &Source,
+        content: &str,
+        including: &mut Vec<Source>,
+        strict: bool,
+    ) -> anyhow::Result<HookConfig> {
+        let format = ConfigFormat::detect(source.name(), content);
+        let mut conf: HookConfig = format.parse(content).map_err(|e| {
+            GitHooksError::Config(format!("invalid config {}: {}", source.name(), e))
+        })?;
+        if strict {
+            let unknown: Vec<String> = strict::unknown_keys(content, format)?
+                .iter()
+                .map(|k| k.to_string())
+                .collect();
+            if !unknown.is_empty() {
+                return Err(GitHooksError::Config(format!(
+                    "invalid config {}:\n  {}",
+                    source.name(),
+                    unknown.join("\n  ")
+                ))
+                .into());
+            }
+        }
+        migrate::check_version(conf.version, &source.name())?;
+        check_regexes(&conf.hooks, &source.name())?;
+        including.push(source.clone());
+        let mut base = HookConfig::default();
+        for include in std::mem::take(&mut conf.include) {
+            let (included, content) = include::fetch(source, &include)?;
+            if including.contains(&included) {
+                return Err(GitHooksError::Config(format!(
+                    "{} includes itself through {}",
+                    included.name(),
+                    source.name()
+                ))
+                .into());
+            }
+            base.merge(HookConfig::parse(&included, &content, including, strict)?);
+        }
+        including.pop();
+        base.merge(conf);
+        Ok(base)
+    }
+
+    /// Merges `other` over this configuration: `other`'s repos are added, replacing the ones with
+    /// the same URL, and its hooks override the parts of the definitions they set.
+    fn merge(&mut self, other: HookConfig) {
+        self.mirrors.extend(other.mirrors);
+        self.disable.extend(other.disable);
+        if other.push_policy.is_some() {
+            self.push_policy = other.push_policy;
+        }
+        if other.autofix.is_some() {
+            self.autofix = other.autofix;
+        }
+        if other.default_on_event.is_some() {
+            self.default_on_event = other.default_on_event;
+        }
+        if other.shared_path.is_some() {
+            self.shared_path = other.shared_path;
+        }
+        for repo in other.repos {
+            match self.repos.iter_mut().find(|r| r.url == repo.url) {
+                Some(r) => *r = repo,
+                None => self.repos.push(repo),
+            }
+        }
+        for hook in other.hooks {
+            match self.hooks.iter_mut().find(|h| h.name == hook.name) {
+                Some(h) => h.override_with(&hook),
+                None => self.hooks.push(hook),
+            }
+        }
+    }
+
+    /// Removes the hooks listed in `disable`, unless they are `mandatory`
+    fn disable_hooks(&mut self, mandatory: &[String]) {
+        for name in &self.disable {
+            if mandatory.contains(name) {
+                warn!(
+                    "{} is mandatory on this machine, it cannot be disabled",
+                    name
+                );
+            }
+        }
+        let disable = &self.disable;
+        self.hooks
+            .retain(|h| mandatory.contains(&h.name) || !disable.contains(&h.name));
+    }
+
+    /// Rewrites the repos URLs starting with one of the mirrored prefixes
+    fn apply_mirrors(&mut self) {
+        for repo in self.repos.iter_mut() {
+            // the longest prefix wins
+            if let Some((from, to)) = self
+                .mirrors
+                .iter()
+                .filter(|(from, _)| repo.url.starts_with(from.as_str()))
+                .max_by_key(|(from, _)| from.len())
+            {
+                let url = format!("{}{}", to, &repo.url[from.len()..]);
+                debug!("using mirror {} for {}", url, repo.url);
+                repo.url = url;
+            }
+        }
+    }
+
+    /// Installs itself as a hook
+    pub fn init(self, events: &[HookEvent], mode: InstallMode) -> anyhow::Result<()> {
+        install(&git::root()?, events, mode)
+    }
+
+    /// Runs the enabled hooks reacting to `event`. `args` are the arguments git gave to the hook.
+    /// Hooks failing do not prevent the others from running; the returned report tells how each
+    /// of them went.
+    pub fn run(&self, event: HookEvent, args: &[String]) -> anyhow::Result<RunReport> {
+        self.run_in(&RunContext::new(event, args)?)
+    }
+
+    /// Runs the enabled hooks reacting to the event of `ctx`, on the files it is about, eg. the
+    /// ones changed in a range of commits. With `autofix`, the hooks about the staged files
+    /// modified by a run are run again, until they no longer modify any.
+    pub fn run_in(&self, ctx: &RunContext) -> anyhow::Result<RunReport> {
+        let event = ctx
+            .event
+            .ok_or_else(|| anyhow::Error::msg("no event to run the hooks of"))?;
+        let mut report = RunReport::new(event);
+        if let (Some(policy), HookEvent::PreReceive | HookEvent::Update) =
+            (&self.push_policy, event)
+        {
+            let start = Instant::now();
+            // the pushes are audited once, rather than once per ref
+            let audit = match event {
+                HookEvent::PreReceive => policy.audit(&ctx.updates),
+                _ => Ok(()),
+            };
+            let outcome = audit
+                .and_then(|_| policy.check(&ctx.updates))
+                .map(|_| String::new());
+            report.record("push-policy", start.elapsed(), outcome);
+        }
+        // only the staged files can be fixed
+        let max_runs = match self.autofix {
+            Some(max_runs) if !ctx.bare && ctx.range.is_none() => max_runs,
+            _ => 0,
+        };
+        if max_runs == 0 {
+            self.run_hooks(ctx, None, &mut report)?;
+            return Ok(report);
+        }
+        let mut tree = git::write_tree()?;
+        let mut modified = None;
+        for run in 1..=max_runs {
+            self.run_hooks(ctx, modified.as_deref(), &mut report)?;
+            let new_tree = git::write_tree()?;
+            if new_tree == tree {
+                return Ok(report);
+            }
+            let files = git::diff_files(&tree, &new_tree)?;
+            debug!("run {} of the hooks modified {:?}", run, files);
+            modified = Some(files);
+            tree = new_tree;
+        }
+        report.record(
+            "autofix",
+            Duration::default(),
+            Err(anyhow::Error::msg(format!(
+                "the hooks still modified {} after {} runs",
+                modified.unwrap_or_default().join(", "),
+                max_runs
+            ))),
+        );
+        Ok(report)
+    }
+
+    /// Runs the enabled hooks reacting to the event of `ctx`, and records how they went in
+    /// `report`. Given `modified` files, only the hooks about them run.
+    fn run_hooks(
+        &self,
+        ctx: &RunContext,
+        modified: Option<&[String]>,
+        report: &mut RunReport,
+    ) -> anyhow::Result<()> {
+        let event = ctx
+            .event
+            .ok_or_else(|| anyhow::Error::msg("no event to run the hooks of"))?;
+        // hooks scoped to a subdirectory without changed files are skipped
+        let out_of_scope = |hook: &Hook| match &hook.root {
+            Some(dir) if monorepo::files_under(dir, &ctx.changed_files).is_empty() => {
+                Some(format!("no changed file under {}", dir))
+            }
+            _ => None,
+        };
+        let affected = |hook: &Hook| match modified {
+            Some(files) => is_about(hook, files),
+            None => true,
+        };
+        let shared;
+        let ctx = if self.shared_path.unwrap_or(false) {
+            shared = RunContext {
+                shared_path: self.repo_dirs()?,
+                ..ctx.clone()
+            };
+            &shared
+        } else {
+            ctx
+        };
+        for repo in &self.repos {
+            let repo_path = repo.local_path()?;
+            repo.hooks
+                .iter()
+                // filter hooks with the right event
+                .filter(|&hook| {
+                    hook.on_event
+                        .as_ref()
+                        .unwrap_or(&self.default_events())
+                        .contains(&event)
+                })
+                // filter hooks with their IDs present.
+                .filter(|&hook| self.hooks.iter().any(|h| h.name == hook.name))
+                .filter(|&hook| affected(hook))
+                .for_each(|hook| {
+                    if let Some(reason) = out_of_scope(hook) {
+                        report.skip(&hook.name, &reason);
+                        return;
+                    }
+                    debug!("would run hook {:?}", hook);
+                    let start = Instant::now();
+                    // the hooks which ran before may have rewritten this one
+                    let outcome = protect::check(&repo.url, &repo_path)
+                        .inspect_err(|e| error!("{}", e))
+                        .and_then(|_| run_hook(hook, &repo_path, ctx));
+                    report.record_hook(hook, start.elapsed(), outcome);
+                });
+        }
+        for hook in self
+            .hooks
+            .iter()
+            .filter(|hook| hook.name.starts_with(BUILTIN_PREFIX))
+            .filter(|&hook| affected(hook))
+        {
+            match Builtin::from_name(&hook.name) {
+                Some(builtin) => {
+                    let on_event = hook
+                        .on_event
+                        .clone()
+                        .unwrap_or_else(|| builtin.default_events());
+                    if !on_event.contains(&event) {
+                        continue;
+                    }
+                    if let Some(reason) = out_of_scope(hook) {
+                        report.skip(&hook.name, &reason);
+                        continue;
+                    }
+                    debug!("would run builtin hook {:?}", hook);
+                    let start = Instant::now();
+                    let outcome = builtin.run(hook, ctx).map(|_| String::new());
+                    report.record_hook(hook, start.elapsed(), outcome);
+                }
+                None => report.record(
+                    &hook.name,
+                    Duration::default(),
+                    Err(anyhow::Error::msg("unknown builtin hook")),
+                ),
+            }
+        }
+        // inline scripts & actions not defined by any repo are project-local hooks
+        let root = git::root()?;
+        for hook in self.hooks.iter().filter(|hook| {
+            hook.on_event
+                .as_ref()
+                .unwrap_or(&self.default_events())
+                .contains(&event)
+        }) {
+            if hook.name.starts_with(BUILTIN_PREFIX)
+                || self.is_defined_by_repo(&hook.name)
+                || !affected(hook)
+            {
+                continue;
+            }
+            if !hook.is_local() {
+                report.skip(&hook.name, "not defined by any trusted hook repo");
+                continue;
+            }
+            // an action may override the hook of a repo which could not be set up, and must not
+            // run in its place, past its trust & policy checks
+            if hook.script.is_none() {
+                if let Some(url) = self.unknown_repo_defining(&hook.name) {
+                    let reason = format!("{} may define it, but could not be set up", url);
+                    warn!("{} does not run: {}", hook.name, reason);
+                    report.skip(&hook.name, &reason);
+                    continue;
+                }
+            }
+            if let Some(reason) = out_of_scope(hook) {
+                report.skip(&hook.name, &reason);
+                continue;
+            }
+            debug!("would run local hook {:?}", hook);
+            let start = Instant::now();
+            let outcome = run_hook(hook, &root, ctx);
+            report.record_hook(hook, start.elapsed(), outcome);
+        }
+        Ok(())
+    }
+
+    /// Returns the directories holding the programs of the checked out hook repos, in the order of
+    /// the configuration
+    fn repo_dirs(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut dirs = Vec::new();
+        for repo in &self.repos {
+            let repo_path = repo.local_path()?;
+            if !Path::new(&repo_path).is_dir() {
+                continue;
+            }
+            dirs.extend(repo.bin_dirs(&repo_path));
+        }
+        Ok(dirs)
+    }
+
+    /// Returns the events the enabled hooks react to, which must have their shim installed
+    pub fn events(&self) -> Vec<HookEvent> {
+        let mut events = Vec::new();
+        if self.push_policy.is_some() {
+            events.extend([HookEvent::PreReceive, HookEvent::Update]);
+        }
+        for hook in &self.hooks {
+            for event in self.hook_events(hook) {
+                // git never runs the manual hooks
+                if event != HookEvent::Manual && !events.contains(&event) {
+                    events.push(event);
+                }
+            }
+        }
+        events
+    }
+
+    /// Returns the events the enabled `hook` reacts to
+    fn hook_events(&self, hook: &Hook) -> Vec<HookEvent> {
+        match Builtin::from_name(&hook.name) {
+            Some(builtin) => hook
+                .on_event
+                .clone()
+                .unwrap_or_else(|| builtin.default_events()),
+            // the repos definitions hold the overrides already
+            None => self
+                .repos
+                .iter()
+                .flat_map(|r| &r.hooks)
+                .find(|h| h.name == hook.name)
+                .unwrap_or(hook)
+                .on_event
+                .clone()
+                .unwrap_or_else(|| self.default_events()),
+        }
+    }
+
+    /// Returns the events the hooks without `on_event` run on
+    fn default_events(&self) -> Vec<HookEvent> {
+        self.default_on_event
+            .clone()
+            .unwrap_or_else(|| vec![HookEvent::PreCommit])
+    }
+
+    /// Tells whether one of the repos defines a hook named `name`
+    fn is_defined_by_repo(&self, name: &str) -> bool {
+        self.repos
+            .iter()
+            .any(|r| r.hooks.iter().any(|h| h.name == name))
+    }
+
+    /// Returns the URL of the repo which may define the hook `name`, though its hooks are unknown
+    /// as it could not be set up: its checkout declares it, or there is none to tell
+    pub(crate) fn unknown_repo_defining(&self, name: &str) -> Option<&str> {
+        self.repos
+            .iter()
+            .filter(|r| r.hooks.is_empty())
+            .find(|r| {
+                let mut manifest = ExternalHookRepo {
+                    url: r.url.clone(),
+                    ..Default::default()
+                };
+                r.local_path()
+                    .and_then(|dir| manifest.read_manifest(&dir))
+                    .map_or(true, |_| manifest.hooks.iter().any(|h| h.name == name))
+            })
+            .map(|r| r.url.as_str())
+    }
+
+    /// finds defined values in the hook definitions, and overrides the definitions in repos, which
+    /// then hold the events they run on.
+    /// The aliases are added to the repos defining the hooks they are an alias of, as copies of
+    /// those named after them.
+    fn update_repos_config(&mut self) {
+        // TODO error[E0500]: closure requires unique access to `self` but it is already borrowed
+        let hooks = &self.hooks;
+        let default_events = self.default_events();
+        for repo in &mut self.repos {
+            for alias in hooks.iter().filter(|h| h.alias_of.is_some()) {
+                if repo.hooks.iter().any(|h| h.name == alias.name) {
+                    continue;
+                }
+                if let Some(aliased) = repo
+                    .hooks
+                    .iter()
+                    .find(|h| h.alias_of.is_none() && Some(&h.name) == alias.alias_of.as_ref())
+                {
+                    let definition = Hook {
+                        name: alias.name.clone(),
+                        alias_of: alias.alias_of.clone(),
+                        ..aliased.clone()
+                    };
+                    repo.hooks.push(definition);
+                }
+            }
+        }
+        self.repos
+            .iter_mut()
+            .map(|repo| {
+                repo.hooks
+                    .iter_mut()
+                    .map(|h| {
+                        let hooks: Vec<&Hook> =
+                            hooks.iter().filter(|hook| hook.name == h.name).collect();
+                        if !hooks.is_empty() {
+                            let hook = hooks[0];
+                            h.override_with(hook);
+                        }
+                    })
+                    .for_each(drop);
+            })
+            .for_each(drop);
+        // the hooks without events of their own run on the default ones of their repo, or else
+        // of the configuration
+        for repo in &mut self.repos {
+            let events = repo
+                .default_on_event
+                .clone()
+                .unwrap_or_else(|| default_events.clone());
+            for hook in repo.hooks.iter_mut().filter(|h| h.on_event.is_none()) {
+                hook.on_event = Some(events.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Advisory file locks, keeping the `git-hooks` processes git fires at once, eg. on an IDE's
+//! auto-fetch during a commit, from cloning, setting up or running the same things together.
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::git;
+
+/// Where the locks of the hooks' `lock` are, relative to the git directory
+const HOOK_LOCKS_LOCATION: &str = "git-hooks/locks";
+
+/// A lock held until dropped
+#[derive(Debug)]
+pub struct Lock {
+    // closing the file releases the lock
+    _file: File,
+}
+
+/// Locks `path`, creating it if needed, waiting for the other processes holding it to release
+/// it. `what` tells them what is waited for.
+pub fn acquire(path: &Path, what: &str) -> anyhow::Result<Lock> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            eprintln!("waiting for another git-hooks {}...", what);
+            file.lock()?;
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(anyhow::Error::msg(format!(
+                "could not lock {}: {}",
+                path.display(),
+                e
+            )))
+        }
+    }
+    debug!("locked {}", path.display());
+    Ok(Lock { _file: file })
+}
+
+/// Returns the file locking the hooks sharing the `lock` `name` in the repository at `root`
+pub fn hook_lock_path(root: &str, name: &str) -> anyhow::Result<PathBuf> {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    Ok(Path::new(&git::common_dir(root)?)
+        .join(HOOK_LOCKS_LOCATION)
+        .join(format!("{}.lock", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    use tempdir::TempDir;
+
+    use crate::lock::acquire;
+
+    #[test]
+    fn test_acquire() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let path = dir.path().join("hook-repos/hooks.lock");
+        let lock = acquire(&path, "test").unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let waiting = {
+            let path = path.clone();
+            thread::spawn(move || {
+                let _lock = acquire(&path, "test").unwrap();
+                sender.send(()).unwrap();
+            })
+        };
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        drop(lock);
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+        waiting.join().unwrap();
+    }
+}
@@ -1,488 +1,504 @@
-use std::collections::HashMap;
 use std::env;
-use std::fs::{File, Permissions};
-use std::io::{stdin, stdout, Read, Write};
-use std::os::unix::fs::PermissionsExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process;
 
-use clap::{App, Arg, SubCommand};
-use log::{debug, error, info, warn};
-use serde::{Deserialize, Serialize};
-use shlex::Shlex;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use log::{debug, error};
 
-use crate::utils::{execute_cmd, get_files, get_local_repo_path, matches, prefix_path};
+use git_hooks_manager::config_format::{
+    default_config_file_in, to_config_yaml, to_yaml, ConfigFormat, DEFAULT_CONFIG_FILES,
+};
+use git_hooks_manager::context::{RefRange, RunContext};
+use git_hooks_manager::edit::{
+    add_hook, add_repo, parse_events, parse_regexes, prompt_hook, write_new_yaml,
+    write_sample_config,
+};
+use git_hooks_manager::error::exit_code;
+use git_hooks_manager::import::{self, HUSKY_DIR, LEFTHOOK_CONFIGS, PRE_COMMIT_CONFIG};
+use git_hooks_manager::install::{
+    check, install_bootstrap, install_global, needs_bootstrap, SHIM_SHELL_OPTION,
+};
+use git_hooks_manager::preview::{self, Preview};
+use git_hooks_manager::registry::{self, index_location, Index};
+use git_hooks_manager::report::CiReport;
+use git_hooks_manager::status::status;
+use git_hooks_manager::utils::ask_for_user_confirmation;
+use git_hooks_manager::{exec, export, git, migrate, outdated, repo, vendor};
+use git_hooks_manager::{
+    init_submodules, run_hook, GitHooksError, Hook, HookConfig, HookEvent, InstallMode,
+    LoadOptions, ALL_HOOK_EVENTS,
+};
 
-mod git;
-mod utils;
-
-#[cfg(test)]
-mod tests {
-    use crate::{git, ExternalHookRepo, Hook, HookConfig, HookEvent};
-    use std::env::{current_dir, set_current_dir};
-    use tempdir::TempDir;
-
-    #[test]
-    fn test_merge() {
-        let mut conf = HookConfig {
-            hooks: vec![Hook {
-                name: "test1".to_string(),
-                on_event: None,
-                on_file_regex: None,
-                action: Some("exe2".to_string()),
-                setup_script: None,
-            }],
-            repos: vec![ExternalHookRepo {
-                url: "dummy".to_string(),
-                hooks: vec![Hook {
-                    name: "test1".to_string(),
-                    on_event: Some(vec![HookEvent::PreCommit]),
-                    on_file_regex: Some(vec![".*".to_string()]),
-                    action: Some("exe1".to_string()),
-                    setup_script: Some("hello.sh".to_string()),
-                }],
-                version: None,
-            }],
-        };
-        assert_ne!(conf.hooks[0].action, conf.repos[0].hooks[0].action);
-        conf.update_repos_config();
-        assert_eq!(conf.hooks[0].action, conf.repos[0].hooks[0].action);
+fn load_options(args: Option<&ArgMatches>) -> LoadOptions {
+    LoadOptions {
+        trust_all: args.is_some_and(|a| a.is_present("trust-all")),
+        force_setup: args.is_some_and(|a| a.is_present("force-setup")),
+        config_ref: args.and_then(|a| a.value_of("config-ref").map(|r| r.to_string())),
+        offline: false,
+        strict: args.is_some_and(|a| a.is_present("strict-config"))
+            || env::var_os("GIT_HOOKS_STRICT_CONFIG").is_some(),
     }
-
-    #[test]
-    fn test_external_repo_with_version() {
-        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
-        let old_dir = current_dir().expect("could not get current dir");
-        set_current_dir(dir.path()).expect("could not cd to temp dir");
-        git::init(None).expect("could not init repo");
-        let mut er = ExternalHookRepo {
-            url: "https://github.com/paulollivier/rust-hooks".to_string(),
-            version: Some("0e74c2b9c6b1cf4ff36d7eedbee8e8093acacaac".to_string()),
-            hooks: vec![],
-        };
-        let r = er.init();
-        assert!(r.is_ok());
-        let cloned_dir = dir
-            .path()
-            .join(".git")
-            .join("hook-repos")
-            .join("rust-hooks");
-        assert!(cloned_dir.join("hooks.yml").exists());
-        set_current_dir(cloned_dir).expect("could not cd to cloned dir");
-        let r = git::get_hash("HEAD");
-        assert!(r.is_ok());
-        assert_eq!(
-            "0e74c2b9c6b1cf4ff36d7eedbee8e8093acacaac".to_string(),
-            r.unwrap()
-        );
-        set_current_dir(old_dir).expect("could not revert current dir");
-    }
-}
-
-/// Represents the possible placeholders to be substituted to actual file values.
-/// The singular variants mean that the action is to be executed for each file found.
-enum ActionFileToken {
-    Files,
-    File,
-    ChangedFiles,
-    ChangedFile,
-    Root,
-}
-
-impl ActionFileToken {
-    /// Returns the variant from a textual representation
-    /// ```rust
-    /// assert_eq!(ActionFileToken::File, ActionFileToken::from_str("{file}"));
-    /// assert_eq!(ActionFileToken::ChangedFiles, ActionFileToken::from_str("{changed_files}"));
-    /// ```
-    fn from_str(token: &str) -> Option<ActionFileToken> {
-        match token {
-            "{file}" => Some(ActionFileToken::File),
-            "{files}" => Some(ActionFileToken::Files),
-            "{changed_files}" => Some(ActionFileToken::ChangedFiles),
-            "{changed_file}" => Some(ActionFileToken::ChangedFile),
-            "{root}" => Some(ActionFileToken::Root),
-            _ => None,
-        }
-    }
-}
-
-#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Copy, Clone)]
-#[serde(rename_all = "kebab-case")]
-enum HookEvent {
-    ApplyPatchMsg,
-    CommitMsg,
-    PostCommit,
-    PostUpdate,
-    PreApplyPatch,
-    PreCommit,
-    PreMergeCommit,
-    PrePush,
-    PreRebase,
-    PreReceive,
-    PrepareCommitMsg,
-    Update,
 }
 
-static ALL_HOOK_EVENTS: &[HookEvent] = &[
-    HookEvent::ApplyPatchMsg,
-    HookEvent::CommitMsg,
-    HookEvent::PostCommit,
-    HookEvent::PostUpdate,
-    HookEvent::PreApplyPatch,
-    HookEvent::PreCommit,
-    HookEvent::PreMergeCommit,
-    HookEvent::PrePush,
-    HookEvent::PreRebase,
-    HookEvent::PreReceive,
-    HookEvent::PrepareCommitMsg,
-    HookEvent::Update,
-];
-
-impl HookEvent {
-    fn to_kebab_case(&self) -> &'static str {
-        match self {
-            HookEvent::ApplyPatchMsg => "apply-patch-msg",
-            HookEvent::CommitMsg => "commit-msg",
-            HookEvent::PostCommit => "post-commit",
-            HookEvent::PostUpdate => "post-update",
-            HookEvent::PreApplyPatch => "pre-apply-patch",
-            HookEvent::PreCommit => "pre-commit",
-            HookEvent::PreMergeCommit => "pre-merge-commit",
-            HookEvent::PrePush => "pre-push",
-            HookEvent::PreRebase => "pre-rebase",
-            HookEvent::PreReceive => "pre-receive",
-            HookEvent::PrepareCommitMsg => "prepare-commit-msg",
-            HookEvent::Update => "update",
-        }
-    }
-    fn from_kebab_case(s: &str) -> Option<Self> {
-        match s {
-            "apply-patch-msg" => Some(HookEvent::ApplyPatchMsg),
-            "commit-msg" => Some(HookEvent::CommitMsg),
-            "post-commit" => Some(HookEvent::PostCommit),
-            "post-update" => Some(HookEvent::PostUpdate),
-            "pre-apply-patch" => Some(HookEvent::PreApplyPatch),
-            "pre-commit" => Some(HookEvent::PreCommit),
-            "pre-merge-commit" => Some(HookEvent::PreMergeCommit),
-            "pre-push" => Some(HookEvent::PrePush),
-            "pre-rebase" => Some(HookEvent::PreRebase),
-            "pre-receive" => Some(HookEvent::PreReceive),
-            "prepare-commit-msg" => Some(HookEvent::PrepareCommitMsg),
-            "update" => Some(HookEvent::Update),
-            _ => None,
-        }
+/// Loads the configuration, see `HookConfig::from_file`, warning about what is likely wrong with it
+fn load_config(filename: Option<&str>, opts: &LoadOptions) -> anyhow::Result<HookConfig> {
+    let conf = HookConfig::from_file(filename, opts)?;
+    for warning in conf.warnings() {
+        eprintln!("warning: {}", warning);
     }
+    Ok(conf)
 }
 
-#[derive(Deserialize, Serialize, Debug, Default)]
-#[serde(default)]
-struct Hook {
-    name: String,
-    on_event: Option<Vec<HookEvent>>,
-    on_file_regex: Option<Vec<String>>,
-    action: Option<String>,
-    setup_script: Option<String>,
-}
-
-impl Clone for Hook {
-    fn clone(&self) -> Self {
-        let mut h = Hook::default();
-        h.name = self.name.clone();
-        if let Some(self_on_event) = &self.on_event {
-            let mut on_event = Vec::new();
-            for e in self_on_event {
-                on_event.push(*e);
-            }
-            h.on_event = Some(on_event);
-        }
-        if let Some(regex) = &self.on_file_regex {
-            let mut on_file_regex = Vec::new();
-            for r in regex {
-                on_file_regex.push(r.clone());
-            }
-            h.on_file_regex = Some(on_file_regex);
-        }
-        if let Some(action) = &self.action {
-            h.action = Some(action.clone());
-        }
-        if let Some(setup_script) = &self.setup_script {
-            h.setup_script = Some(setup_script.clone());
-        }
-        h
-    }
+/// Returns the names of the events the hooks may run on: the git ones, and `manual`
+fn event_names() -> Vec<&'static str> {
+    ALL_HOOK_EVENTS
+        .iter()
+        .chain(std::iter::once(&HookEvent::Manual))
+        .map(|e| e.to_kebab_case())
+        .collect()
 }
 
-fn run_hook(hook: &Hook, hook_repo_path: &str) -> anyhow::Result<()> {
-    let root = git::root().expect("Could not get git root.");
-    let mut should_run = true;
-    // expand PATH
-    let mut bin_path = env::var("PATH").expect("PATH is not set in the env.");
-    bin_path.push_str(&format!(":{}", hook_repo_path));
-    debug!("New $PATH: {}", &bin_path);
-    let mut env = HashMap::new();
-    env.insert("PATH".to_string(), bin_path);
-    // parse the action cli
-    let mut action = Shlex::new(
-        hook.action
-            .as_ref()
-            .expect("None action on hook exec")
-            .as_str(),
-    );
-    let cmd = action.next().unwrap();
-    let args: Vec<String> = action.collect();
-    let mut final_args: Vec<String> = Vec::new();
-    for arg in &args {
-        if let Some(token) = ActionFileToken::from_str(&arg) {
-            match token {
-                ActionFileToken::Files => {
-                    let mut files = get_files(
-                        &root,
-                        &hook
-                            .on_file_regex
-                            .as_ref()
-                            .unwrap_or(&vec![".*".to_string()]),
-                    )?;
-                    should_run = !files.is_empty();
-                    final_args.append(&mut files);
-                }
-                ActionFileToken::File => {
-                    unimplemented!("we should check for the token before, as it changes the whole execution logic");
-                }
-                ActionFileToken::ChangedFiles => {
-                    let mut changed_files: Vec<String> = git::changed_files(true)?
-                        .iter()
-                        .map(|f| Path::new(f))
-                        .filter(|p| {
-                            matches(
-                                p,
-                                &(*hook
-                                    .on_file_regex
-                                    .as_ref()
-                                    .unwrap_or(&vec![".*".to_string()])),
-                            )
-                        })
-                        .map(|p| p.display().to_string())
-                        .collect();
-                    should_run = !changed_files.is_empty();
-                    final_args.append(&mut changed_files);
-                }
-                ActionFileToken::ChangedFile => {
-                    // TODO: implement me
-                    unimplemented!();
-                }
-                ActionFileToken::Root => {
-                    final_args.push(root.clone());
-                }
+/// Replaces the executable with the binary of the release `version`, a tag, or else with the
+/// latest one of `channel`, `stable` by default, if newer
+#[cfg(feature = "self-update")]
+fn update(channel: Option<&str>, version: Option<&str>) -> anyhow::Result<()> {
+    use git_hooks_manager::release::{self, Asset, Channel, PUBLIC_KEY_OPTION};
+    use self_update::backends::github::{ReleaseList, Update};
+    use self_update::cargo_crate_version;
+    let channel: Channel = channel.map(str::parse).transpose()?.unwrap_or_default();
+    let asset = release::current_asset()?;
+    let latest = match (version, channel) {
+        // GitHub lists the newest releases first, pre-releases included
+        (None, Channel::Prerelease) => ReleaseList::configure()
+            .repo_owner("paulollivier")
+            .repo_name("git-hooks")
+            .build()?
+            .fetch()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::Error::msg("git-hooks has no release yet"))?,
+        _ => {
+            let updater = Update::configure()
+                .repo_owner("paulollivier")
+                .repo_name("git-hooks")
+                .bin_name(&asset)
+                .current_version(cargo_crate_version!())
+                .build()?;
+            match version {
+                Some(tag) => updater.get_release_version(tag)?,
+                None => updater.get_latest_release()?,
             }
-        } else if should_run {
-            final_args.push(arg.to_string());
-        } else {
-            info!("Could find any files to run hook on");
         }
+    };
+    // a pinned version is installed even if older
+    let wanted = match version {
+        Some(_) => latest.version != cargo_crate_version!(),
+        None => self_update::version::bump_is_greater(cargo_crate_version!(), &latest.version)?,
+    };
+    if !wanted {
+        println!("No available update.");
+        return Ok(());
     }
-    let (s, _, _) = execute_cmd(&cmd, &final_args, Some(&root), Some(&env))?;
-    debug!(
-        "finished executing {} with exit status {}",
-        cmd,
-        s.code().unwrap()
-    );
-    if !s.success() {
-        Err(anyhow::Error::msg(format!(
-            "{:?} reported execution failure: {:?}",
-            hook,
-            s.code()
-        )))
-    } else {
-        let index_files = git::changed_files(true)?;
-        let changed_files = git::changed_files(false)?;
-        let files_to_re_add: Vec<&String> = changed_files
-            .iter()
-            .filter(|f| index_files.contains(f))
-            .collect();
-        if !files_to_re_add.is_empty() {
-            debug!("we must re-add those files: {:#?}", files_to_re_add);
-            git::add(&files_to_re_add)?;
-        }
-        Ok(())
-    }
-}
-
-#[derive(Deserialize, Serialize, Debug, Default)]
-#[serde(default)]
-struct ExternalHookRepo {
-    hooks: Vec<Hook>,
-    url: String,
-    version: Option<String>,
-}
-
-impl ExternalHookRepo {
-    pub fn init(&mut self) -> anyhow::Result<()> {
-        let clone_dir = get_local_repo_path(&self.url)?;
-        debug!("cloning {} to {}", &self.url, &clone_dir);
-        git::pull(&self.url, &clone_dir)?;
-        if let Some(v) = &self.version {
-            git::checkout(v, &clone_dir)?;
-        }
-        let mut repo_config = String::new();
-        File::open(format!("{}/{}", clone_dir, "hooks.yml"))?.read_to_string(&mut repo_config)?;
-        debug!("Got hooks.yml");
-        let hook_repo: ExternalHookRepo = serde_yaml::from_str(&repo_config)?;
-        debug!("{:?}", hook_repo);
-        self.hooks = hook_repo.hooks;
-        self.setup()
-    }
-
-    /// runs the optional setup scripts
-    fn setup(&self) -> anyhow::Result<()> {
-        let mut env = HashMap::new();
-        env.insert(
-            "PATH".to_string(),
-            prefix_path(&get_local_repo_path(&self.url)?),
-        );
-        for hook in &self.hooks {
-            if hook.setup_script.is_some() {
-                utils::execute_cmd(
-                    hook.setup_script.as_ref().expect("should not happen"),
-                    &[] as &[&str],
-                    Some(&get_local_repo_path(&self.url)?),
-                    Some(&env),
-                )?;
-            }
-        }
+    let assets: Vec<Asset> = latest
+        .assets
+        .iter()
+        .map(|a| Asset {
+            name: a.name.clone(),
+            url: a.download_url.clone(),
+        })
+        .collect();
+    // downloaded next to the executable, so that it is moved in place rather than copied
+    let exe = env::current_exe()?;
+    let dir = exe
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(format!(".git-hooks-update-{}", process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let public_key = git::get_config(None, PUBLIC_KEY_OPTION)?;
+    let installed = release::download_verified(
+        &latest.version,
+        &assets,
+        &asset,
+        &dir,
+        public_key.as_deref(),
+    )
+    .and_then(|binary| {
+        self_update::Move::from_source(&binary)
+            .replace_using_temp(&dir.join("previous"))
+            .to_dest(&exe)?;
         Ok(())
-    }
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    installed?;
+    println!("Downloaded a new version: `{}`!", latest.version);
+    Ok(())
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-struct HookConfig {
-    repos: Vec<ExternalHookRepo>,
-    hooks: Vec<Hook>,
+#[cfg(not(feature = "self-update"))]
+fn update(_channel: Option<&str>, _version: Option<&str>) -> anyhow::Result<()> {
+    Err(anyhow::Error::msg(
+        "git-hooks was built without the \"self-update\" feature: update it the way it was installed, eg. with your package manager, or with `cargo install git-hooks-manager`",
+    ))
 }
 
-impl HookConfig {
-    fn from_file(filename: Option<&str>) -> anyhow::Result<HookConfig> {
-        let mut conf_content = String::new();
-        let p = filename.unwrap_or(".hooks.yml");
-        match File::open(p) {
-            Ok(mut f) => {
-                f.read_to_string(&mut conf_content)?;
-            }
-            Err(e) => {
-                error!("could not read config file {}: {}", p, e);
-            }
+/// Returns the configuration file to edit or show: `config`, or the repository's default one
+fn config_path(config: Option<&str>) -> anyhow::Result<PathBuf> {
+    Ok(match config {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let root = PathBuf::from(git::root()?);
+            default_config_file_in(&root).unwrap_or_else(|| root.join(DEFAULT_CONFIG_FILES[0]))
         }
-        let mut conf: HookConfig = serde_yaml::from_str(&conf_content)?;
-        conf.update_repos_config();
-        debug!("{:?}", conf);
-        conf.repos
-            .iter_mut()
-            .map(|repo| {
-                debug!("init {:?}", repo.url);
-                let r = repo.init();
-                if let Err(e) = r {
-                    warn!(
-                        "Got an error while attempting to initialize repo {}: {}",
-                        repo.url, e
-                    );
-                }
-            })
-            .for_each(drop); // consume the iterator
-        Ok(conf)
-    }
-
-    /// Installs itself as a hook
-    fn init(self, events: &[HookEvent]) -> anyhow::Result<()> {
-        for event in events {
-            let mut hook_script = File::create(format!(
-                "{}/.git/hooks/{}",
-                git::root()?,
-                event.to_kebab_case()
-            ))?;
-            hook_script.set_permissions(Permissions::from_mode(0o755))?;
-            hook_script.write_all(
-                format!("#!/bin/bash -e\ngit-hooks run {}\n", event.to_kebab_case()).as_bytes(),
-            )?;
-        }
-        //TODO: create .hooks.yml if not existing?
-        Ok(())
-    }
-
-    /// finds defined values in the hook definitions, and overrides the definitions in repos
-    fn update_repos_config(&mut self) {
-        // TODO error[E0500]: closure requires unique access to `self` but it is already borrowed
-        let hooks = &self.hooks;
-        self.repos
-            .iter_mut()
-            .map(|repo| {
-                repo.hooks
-                    .iter_mut()
-                    .map(|h| {
-                        let hooks: Vec<&Hook> =
-                            hooks.iter().filter(|hook| hook.name == h.name).collect();
-                        if !hooks.is_empty() {
-                            let hook = hooks[0];
-                            if h.name == hook.name {
-                                if let Some(on_event) = &hook.on_event {
-                                    h.on_event = Some(on_event.clone());
-                                }
-                                if let Some(on_file_regex) = &hook.on_file_regex {
-                                    h.on_file_regex = Some(on_file_regex.clone());
-                                }
-                                if let Some(action) = &hook.action {
-                                    h.action = Some(action.clone());
-                                }
-                                if let Some(setup_script) = &hook.setup_script {
-                                    h.setup_script = Some(setup_script.clone());
-                                }
-                            }
-                        }
-                    })
-                    .for_each(drop);
-            })
-            .for_each(drop);
-    }
+    })
 }
 
-fn ask_for_user_confirmation(prompt: &str) -> anyhow::Result<bool> {
-    print!("{}: ", prompt);
-    stdout().flush()?;
-    let mut input = String::new();
-    stdin().read_line(&mut input)?;
-    Ok(match input.trim() {
-        "Y" | "y" => true,
-        "N" | "n" => false,
-        _ => {
-            println!("Incorrect input. Try again.");
-            ask_for_user_confirmation(prompt)?
-        }
-    })
+/// Reads `path`, telling which file could not be read
+fn read_file(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| anyhow::Error::msg(format!("could not read {}: {}", path.display(), e)))
 }
 
-fn update() -> anyhow::Result<()> {
-    use self_update::cargo_crate_version;
-    let status = self_update::backends::github::Update::configure()
-        .repo_owner("paulollivier")
-        .repo_name("git-hooks")
-        .bin_name("git-hooks-linux-amd64")
-        .show_download_progress(true)
-        .current_version(cargo_crate_version!())
-        .build()?
-        .update()?;
-    if status.updated() {
-        println!("Downloaded a new version: `{}`!", status.version());
-    } else {
-        println!("No available update.");
+/// Exits with the code telling what kind of failure happened, see `git_hooks_manager::error`
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {:?}", e);
+        process::exit(exit_code(&e));
     }
-    Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
+fn run() -> anyhow::Result<()> {
     pretty_env_logger::try_init()?;
     let app = App::new("git-hooks")
         .author("Paul Ollivier <contact@paulollivier.fr>")
         .about("A git hooks manager\nhttps://github.com/paulollivier/git-hooks")
-        .subcommand(SubCommand::with_name("self-update").about("git-hooks will try to update itself."))
-        .subcommand(SubCommand::with_name("init").about("Install the git hooks in .git/hooks"))
+        .arg(
+            Arg::with_name("trust-all")
+                .long("trust-all")
+                .global(true)
+                .help("Trust all external hook repos without asking. Meant for CI."),
+        )
+        .arg(
+            Arg::with_name("force-setup")
+                .long("force-setup")
+                .global(true)
+                .help("Rerun the hook repos setup scripts, even if they already ran."),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .global(true)
+                .takes_value(true)
+                .help("Read the configuration from this file instead of .hooks.yml."),
+        )
+        .arg(
+            Arg::with_name("strict-config")
+                .long("strict-config")
+                .global(true)
+                .help("Reject the configuration files having unknown keys, eg. misspelled settings, instead of ignoring them. Set GIT_HOOKS_STRICT_CONFIG=1 to do so when git runs the hooks."),
+        )
+        .arg(
+            Arg::with_name("config-ref")
+                .long("config-ref")
+                .global(true)
+                .takes_value(true)
+                .help("Read the configuration from this git revision instead of the worktree. Defaults to HEAD in bare repos."),
+        )
+        .subcommand(SubCommand::with_name("self-update").about("git-hooks will try to update itself.")
+            .arg(Arg::with_name("channel")
+                .long("channel")
+                .takes_value(true)
+                .possible_values(&["stable", "prerelease"])
+                .help("Update to the latest release, or to the latest one pre-releases included. Defaults to stable")
+            )
+            .arg(Arg::with_name("version")
+                .long("version")
+                .takes_value(true)
+                .value_name("TAG")
+                .conflicts_with("channel")
+                .help("Install the release of this tag, even if older, eg. to pin the version a CI image uses")
+            ),
+        )
+        .subcommand(SubCommand::with_name("init").about("Install the git hooks in .git/hooks")
+            .arg(Arg::with_name("recurse-submodules")
+                .long("recurse-submodules")
+                .help("Also install the hooks in the submodules having their own .hooks.yml")
+            )
+            .arg(Arg::with_name("global")
+                .long("global")
+                .help("Install the hooks for all the repositories of the user: through a git template directory for the new ones, or the global core.hooksPath in hookspath mode")
+            )
+            .arg(Arg::with_name("check")
+                .long("check")
+                .help("Install nothing, but fail if the hooks the configuration needs are missing or outdated")
+            )
+            .arg(Arg::with_name("bootstrap")
+                .long("bootstrap")
+                .requires("global")
+                .help("Only install, for the repositories cloned from then on, a post-checkout hook offering to run init in the ones having a .hooks.yml")
+            )
+            .arg(Arg::with_name("sample-config")
+                .long("sample-config")
+                .help("Write a commented starter .hooks.yml first. Offered anyway when there is no configuration.")
+            )
+            .arg(Arg::with_name("mode")
+                .long("mode")
+                .takes_value(true)
+                .possible_values(&["hooks", "hookspath"])
+                .default_value("hooks")
+                .help("Write the hooks in .git/hooks, or in a directory of their own set as core.hooksPath, leaving .git/hooks untouched")
+            )
+            .arg(Arg::with_name("shell")
+                .long("shell")
+                .takes_value(true)
+                .possible_values(&["bash", "env-bash", "sh", "exec"])
+                .help("Write the hooks for /bin/bash, the bash of the PATH, /bin/sh, or /bin/sh executing git-hooks right away. Remembered as the git-hooks.shimShell git option")
+            ),
+        )
+        .subcommand(SubCommand::with_name("bootstrap")
+            .setting(AppSettings::Hidden)
+            .about("Offers to run init if the repository has a .hooks.yml but not the hooks installed")
+        )
+        .subcommand(
+            SubCommand::with_name("add")
+                .about("Edits .hooks.yml")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("repo")
+                    .about("Adds a hook repo, then lists the hooks it provides")
+                    .arg(Arg::with_name("url")
+                        .index(1)
+                        .required(true)
+                        .help("The git URL of the hook repo, or its name in the index of `git-hooks search`")
+                    )
+                    .arg(Arg::with_name("rev")
+                        .long("rev")
+                        .takes_value(true)
+                        .help("The revision of the hook repo to use, eg. a tag. Defaults to the latest tag of indexed repos")
+                    )
+                    .arg(Arg::with_name("index")
+                        .long("index")
+                        .takes_value(true)
+                        .help("The index to look the repo name up in, instead of $GIT_HOOKS_INDEX or the community one")
+                    ),
+                )
+                .subcommand(SubCommand::with_name("hook")
+                    .about("Enables a hook, asking on which events and files it runs, and what it runs if no hook repo defines it")
+                    .arg(Arg::with_name("name")
+                        .index(1)
+                        .required(true)
+                        .help("The name of the hook")
+                    )
+                    .arg(Arg::with_name("event")
+                        .long("event")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&event_names())
+                        .help("An event to run the hook on, instead of asking")
+                    )
+                    .arg(Arg::with_name("regex")
+                        .long("regex")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("A regex of the files the hook is about, instead of asking")
+                    )
+                    .arg(Arg::with_name("script")
+                        .long("script")
+                        .takes_value(true)
+                        .help("The command the hook runs, instead of asking")
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Inspects the configuration")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("show")
+                    .about("Prints the repository's configuration file")
+                    .arg(Arg::with_name("resolved")
+                        .long("resolved")
+                        .help("Prints the configuration the hooks run with instead, as YAML: the system's, the user's, the repository's and the local ones merged, the includes resolved, and the hooks' definitions overridden")
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Checks the repository's configuration file and the ones it includes, without setting the hook repos up: unknown keys, regexes, versions, and hooks no repo defines")
+                .arg(Arg::with_name("no-strict")
+                    .long("no-strict")
+                    .help("Ignore the unknown keys, as running the hooks does by default")
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Upgrades the repository's configuration file to the latest version of its schema, printing the changes")
+                .arg(Arg::with_name("dry-run")
+                    .long("dry-run")
+                    .help("Only prints the changes, without writing them")
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Converts the configuration of another hooks manager into a .hooks.yml")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("pre-commit")
+                    .about("Converts a pre-commit configuration: its repos, revs, hooks, file regexes & stages")
+                    .arg(Arg::with_name("file")
+                        .index(1)
+                        .help("The pre-commit configuration to convert. Defaults to the repository's .pre-commit-config.yaml")
+                    ),
+                )
+                .subcommand(SubCommand::with_name("husky")
+                    .about("Converts the husky hooks, of .husky and package.json, into inline scripts")
+                    .arg(Arg::with_name("dir")
+                        .index(1)
+                        .help("The directory holding .husky & package.json. Defaults to the root of the repository")
+                    ),
+                )
+                .subcommand(SubCommand::with_name("lefthook")
+                    .about("Converts a lefthook configuration: its commands & scripts, their globs & roots, telling what has no equivalent, eg. parallel")
+                    .arg(Arg::with_name("file")
+                        .index(1)
+                        .help("The lefthook configuration to convert. Defaults to the repository's lefthook.yml")
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Writes an approximation of the configuration for another hooks manager")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("pre-commit")
+                    .about("Writes a pre-commit configuration: the hook repos, the builtin hooks pre-commit has too, and the inline scripts as local hooks")
+                    .arg(Arg::with_name("file")
+                        .index(1)
+                        .help("The pre-commit configuration to write. Defaults to the repository's .pre-commit-config.yaml")
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("vendor")
+                .about("Copies the hook repos, at their pinned version, in .git-hooks/vendor, to commit: they are used instead of cloning them"),
+        )
+        .subcommand(
+            SubCommand::with_name("repo")
+                .about("Helps writing hook repos")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("new")
+                    .about("Creates a hook repo: a hooks.yml defining an example hook, the script it runs, a setup script stub and a smoke test")
+                    .arg(Arg::with_name("dir")
+                        .index(1)
+                        .required(true)
+                        .help("The directory to create the hook repo in. It must be empty if it exists")
+                    ),
+                )
+                .subcommand(SubCommand::with_name("lint")
+                    .about("Checks the hooks.yml of a hook repo, and that the scripts its hooks run exist and are executable. Meant for the hook repo's CI.")
+                    .arg(Arg::with_name("dir")
+                        .index(1)
+                        .help("The hook repo to check. Defaults to the current directory")
+                    ),
+                )
+                .subcommand(SubCommand::with_name("test")
+                    .about("Runs the test cases of tests/cases.yml: each stages fixture files in a new repository, runs a hook there, and checks it passes or fails as expected")
+                    .arg(Arg::with_name("dir")
+                        .index(1)
+                        .help("The hook repo to test. Defaults to the current directory")
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("Looks for hook repos in an index of the known ones")
+                .arg(Arg::with_name("term")
+                    .index(1)
+                    .required(true)
+                    .help("Text to look for in the names, URLs & descriptions of the repos and their hooks")
+                )
+                .arg(Arg::with_name("index")
+                    .long("index")
+                    .takes_value(true)
+                    .help("The URL or path of the index, instead of $GIT_HOOKS_INDEX or the community one")
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Tells whether git-hooks is healthy in this repository: its configuration, shims, hook repos & last runs"),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Tells what the configured hooks would do for a given event, and why some would not run, without running them")
+                .arg(Arg::with_name("event")
+                    .index(1)
+                    .help("The event to explain the hooks of, eg. \"pre-commit\"")
+                    .required(true)
+                    .possible_values(&event_names())
+                )
+                .arg(Arg::with_name("args")
+                    .index(2)
+                    .multiple(true)
+                    .help("The arguments git would give to the hook")
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("exec")
+                .about("Runs a single hook of a hook repo, whether the configuration enables it or not, eg. to try it out or debug it")
+                .arg(Arg::with_name("target")
+                    .index(1)
+                    .required(true)
+                    .value_name("REPO:HOOK")
+                    .help("The hook to run, and its repo: the name or URL of a repo of the configuration, or the URL of another one, eg. lints:shellcheck")
+                )
+                .arg(Arg::with_name("files")
+                    .index(2)
+                    .multiple(true)
+                    .help("The files to run the hook on, as if they were the staged ones. Defaults to the staged files")
+                )
+                .arg(Arg::with_name("event")
+                    .long("event")
+                    .takes_value(true)
+                    .possible_values(&event_names())
+                    .help("The event to run the hook as if on. Defaults to the first one it runs on")
+                )
+                .arg(Arg::with_name("arg")
+                    .long("arg")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("An argument git would give to the hook, eg. the commit message file for commit-msg")
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("try")
+                .about("Clones a hook repo in a temporary directory and describes its hooks, to look into it before adding it to the configuration")
+                .arg(Arg::with_name("url")
+                    .index(1)
+                    .required(true)
+                    .help("The URL of the hook repo")
+                )
+                .arg(Arg::with_name("rev")
+                    .long("rev")
+                    .takes_value(true)
+                    .help("The revision to look into. Defaults to its newest version tag")
+                )
+                .arg(Arg::with_name("hook")
+                    .long("hook")
+                    .takes_value(true)
+                    .help("Set the repo up, and run this hook on copies of the files given, in a scratch repository, leaving this one as it is")
+                )
+                .arg(Arg::with_name("files")
+                    .index(2)
+                    .multiple(true)
+                    .requires("hook")
+                    .help("The files, relative to the current directory, to run the hook on")
+                )
+                .arg(Arg::with_name("event")
+                    .long("event")
+                    .takes_value(true)
+                    .requires("hook")
+                    .possible_values(&event_names())
+                    .help("The event to run the hook as if on. Defaults to the first one it runs on")
+                ),
+        )
         .subcommand(
             SubCommand::with_name("run")
                 .about("Runs the configured hooks for a given event")
@@ -490,78 +506,668 @@ fn main() -> anyhow::Result<()> {
                     .index(1)
                     .help("Runs the hook for the given event, eg. \"pre-commit\", \"post-commit\"…")
                     .required(true)
-                    .possible_values(&ALL_HOOK_EVENTS.iter().map(|e| e.to_kebab_case()).collect::<Vec<&'static str>>())
+                    .possible_values(&event_names())
+                )
+                .arg(Arg::with_name("args")
+                    .index(2)
+                    .multiple(true)
+                    .help("The arguments git gives to the hook, eg. the commit message file for \"commit-msg\"")
+                )
+                .arg(Arg::with_name("hook")
+                    .long("hook")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Only run this hook, eg. a manual one: `git-hooks run manual --hook tests`")
+                )
+                .arg(Arg::with_name("select")
+                    .long("select")
+                    .help("Pick the hooks to run this time on the terminal. Set GIT_HOOKS_SELECT=1 to do so when git runs the hooks, eg. `GIT_HOOKS_SELECT=1 git commit`")
+                )
+                .arg(Arg::with_name("strict")
+                    .long("strict")
+                    .help("Fail when hooks modify files, instead of staging them again")
+                )
+                .arg(Arg::with_name("verbose")
+                    .long("verbose")
+                    .short("v")
+                    .help("Let the hooks print as they run, instead of capturing what they print. Set GIT_HOOKS_VERBOSE=1 to do so when git runs the hooks")
+                )
+                .arg(Arg::with_name("autofix")
+                    .long("autofix")
+                    .takes_value(true)
+                    .value_name("RUNS")
+                    .help("Run the hooks again, at most this many times in all, as long as they modify staged files, for the formatters to agree. Overrides `autofix` in the configuration")
+                )
+                .arg(Arg::with_name("from-ref")
+                    .long("from-ref")
+                    .takes_value(true)
+                    .help("Run on the files changed since this revision instead of the staged ones, eg. origin/main to check everything a branch touched")
+                )
+                .arg(Arg::with_name("to-ref")
+                    .long("to-ref")
+                    .takes_value(true)
+                    .requires("from-ref")
+                    .help("The end of the range of --from-ref. Defaults to HEAD")
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ci")
+                .about("Runs the hooks of events on all the files, or a range of commits, without prompting nor updating the cached hook repos, and reports the results as JSON")
+                .arg(Arg::with_name("events")
+                    .index(1)
+                    .multiple(true)
+                    .possible_values(&event_names())
+                    .help("The events to run the hooks of. Defaults to pre-commit")
+                )
+                .arg(Arg::with_name("from-ref")
+                    .long("from-ref")
+                    .takes_value(true)
+                    .help("Run on the files changed since this revision instead of all the files, eg. origin/main")
+                )
+                .arg(Arg::with_name("to-ref")
+                    .long("to-ref")
+                    .takes_value(true)
+                    .requires("from-ref")
+                    .help("The end of the range of --from-ref. Defaults to HEAD")
+                )
+                .arg(Arg::with_name("no-strict")
+                    .long("no-strict")
+                    .help("Let hooks modify files, instead of failing")
+                )
+                .arg(Arg::with_name("report")
+                    .long("report")
+                    .takes_value(true)
+                    .help("Write the JSON report in this file instead of the standard output")
                 ),
         );
     let matches = app.get_matches();
     debug!("{:?}", matches);
     match matches.subcommand() {
-        ("self-update", _) => {
-            update()?;
+        ("self-update", args) => {
+            update(
+                args.and_then(|a| a.value_of("channel")),
+                args.and_then(|a| a.value_of("version")),
+            )?;
         }
-        ("init", _) => {
+        ("init", args) => {
+            let mode: InstallMode = args
+                .and_then(|a| a.value_of("mode"))
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or_default();
+            if let Some(shell) = args.and_then(|a| a.value_of("shell")) {
+                if args.is_some_and(|a| a.is_present("global")) {
+                    git::set_global_config(SHIM_SHELL_OPTION, shell)?;
+                } else {
+                    git::set_config(&git::root()?, SHIM_SHELL_OPTION, shell)?;
+                }
+            }
+            if args.is_some_and(|a| a.is_present("bootstrap")) {
+                let hook = install_bootstrap()?;
+                println!(
+                    "Installed {}: the repositories you clone will offer to init themselves 🚀",
+                    hook
+                );
+                return Ok(());
+            }
+            if args.is_some_and(|a| a.is_present("global")) {
+                let dir = install_global(ALL_HOOK_EVENTS, mode)?;
+                println!(
+                    "Installed the hooks for all your repositories in {} 🚀",
+                    dir
+                );
+                return Ok(());
+            }
+            let config = args.and_then(|a| a.value_of("config"));
+            let check_only = args.is_some_and(|a| a.is_present("check"));
+            let path = config_path(config)?;
+            if args.is_some_and(|a| a.is_present("sample-config"))
+                || (!check_only
+                    && !path.exists()
+                    && ask_for_user_confirmation(&format!(
+                        "There is no {}. Write a commented starter one? [Y/N]",
+                        path.display()
+                    ))
+                    // not asking is not an answer
+                    .unwrap_or(false))
+            {
+                write_sample_config(&path)?;
+                println!("Wrote {}, have a look at it!", path.display());
+            }
             debug!("reading conf");
-            let conf = HookConfig::from_file(None)?;
+            let conf = load_config(config, &load_options(args))?;
             debug!("merged conf: {:#?}", conf);
-            if ask_for_user_confirmation(
-                "This will overwrite all the hooks in .git/hooks. Are you sure? [Y/N]",
-            )? {
-                conf.init(ALL_HOOK_EVENTS)?;
+            if check_only {
+                let problems = check(&git::root()?, &conf.events())?;
+                for problem in &problems {
+                    error!("{}", problem);
+                }
+                if !problems.is_empty() {
+                    return Err(anyhow::Error::msg(
+                        "the hooks are not installed properly, run `git-hooks init`",
+                    ));
+                }
+                println!("The hooks are installed properly.");
+                return Ok(());
+            }
+            if mode == InstallMode::HooksPath
+                || ask_for_user_confirmation(
+                    "This will overwrite all the hooks in .git/hooks. Are you sure? [Y/N]",
+                )?
+            {
+                conf.init(ALL_HOOK_EVENTS, mode)?;
+                if args.is_some_and(|a| a.is_present("recurse-submodules")) {
+                    for submodule in init_submodules(ALL_HOOK_EVENTS, mode)? {
+                        println!("Installed in submodule {}", submodule);
+                    }
+                }
                 println!("I have init'd myself successfully! 🚀");
             } else {
                 println!("Operation cancelled by user.");
             }
         }
+        ("bootstrap", args) => {
+            if !needs_bootstrap(&git::root()?)? {
+                return Ok(());
+            }
+            // the hooks may be run without a terminal, eg. by an IDE
+            if ask_for_user_confirmation(
+                "This repository uses git-hooks, but its hooks are not installed. Install them? [Y/N]",
+            )
+            .unwrap_or(false)
+            {
+                let conf = load_config(None, &load_options(args))?;
+                conf.init(ALL_HOOK_EVENTS, InstallMode::Hooks)?;
+                println!("I have init'd myself successfully! 🚀");
+            } else {
+                println!("Run `git-hooks init` to install them.");
+            }
+        }
+        ("add", Some(args)) => {
+            let path = config_path(args.value_of("config"))?;
+            if let ("repo", Some(repo_args)) = args.subcommand() {
+                let mut url = repo_args.value_of("url").unwrap_or_default().to_string();
+                let mut rev = repo_args.value_of("rev").map(|r| r.to_string());
+                if registry::is_repo_name(&url) {
+                    let index = Index::fetch(&index_location(repo_args.value_of("index")))?;
+                    let indexed = index.find(&url).ok_or_else(|| {
+                        anyhow::Error::msg(format!("no hook repo named {} in the index", url))
+                    })?;
+                    url = indexed.url.clone();
+                    rev = rev.or_else(|| indexed.latest.clone());
+                }
+                add_repo(&path, &url, rev.as_deref())?;
+                println!("Added {} to {}", url, path.display());
+                let conf = load_config(
+                    Some(&path.display().to_string()),
+                    &load_options(Some(repo_args)),
+                )?;
+                if let Some(repo) = conf.repos.iter().find(|r| r.url == url) {
+                    println!("It provides these hooks, to enable under `hooks`:");
+                    for hook in &repo.hooks {
+                        let enabled = conf.hooks.iter().any(|h| h.name == hook.name);
+                        let events: Vec<&str> = hook
+                            .on_event
+                            .iter()
+                            .flatten()
+                            .map(|e| e.to_kebab_case())
+                            .collect();
+                        println!(
+                            "  {}, on {}{}",
+                            hook.name,
+                            events.join(", "),
+                            if enabled { " (enabled)" } else { "" }
+                        );
+                        if let Some(description) = &hook.description {
+                            println!("    {}", description.trim_end());
+                        }
+                    }
+                }
+            } else if let ("hook", Some(hook_args)) = args.subcommand() {
+                let name = hook_args.value_of("name").unwrap_or_default();
+                let defined = path.exists()
+                    && load_config(
+                        Some(&path.display().to_string()),
+                        &load_options(Some(hook_args)),
+                    )?
+                    .defines(name);
+                let given = ["event", "regex", "script"]
+                    .iter()
+                    .any(|flag| hook_args.is_present(flag));
+                let hook = if given {
+                    let mut hook = Hook {
+                        name: name.to_string(),
+                        script: hook_args.value_of("script").map(|s| s.to_string()),
+                        ..Default::default()
+                    };
+                    if let Some(events) = hook_args.values_of("event") {
+                        hook.on_event = Some(parse_events(&events.collect::<Vec<_>>().join(" "))?);
+                    }
+                    if let Some(regexes) = hook_args.values_of("regex") {
+                        hook.on_file_regex =
+                            Some(parse_regexes(&regexes.collect::<Vec<_>>().join(" "))?);
+                    }
+                    hook
+                } else {
+                    prompt_hook(name, defined)?
+                };
+                if hook.script.is_none() && !defined && !HookConfig::default().defines(name) {
+                    return Err(anyhow::Error::msg(format!(
+                        "no hook repo defines {}: give it a --script to run",
+                        name
+                    )));
+                }
+                add_hook(&path, &hook)?;
+                println!("Added {} to {}", name, path.display());
+            }
+        }
+        ("config", Some(args)) => {
+            if let ("show", Some(show_args)) = args.subcommand() {
+                if show_args.is_present("resolved") {
+                    let conf =
+                        load_config(args.value_of("config"), &load_options(Some(show_args)))?;
+                    print!("{}", to_yaml(&conf)?);
+                } else {
+                    print!("{}", read_file(&config_path(args.value_of("config"))?)?);
+                }
+            }
+        }
+        ("validate", Some(args)) => {
+            let path = config_path(args.value_of("config"))?;
+            let mut conf = HookConfig::read(&path, !args.is_present("no-strict"))?;
+            let missing = conf.read_local_repos()?;
+            if missing.is_empty() {
+                let unmatched = conf.unmatched_hooks();
+                if !unmatched.is_empty() {
+                    return Err(GitHooksError::Config(format!(
+                        "invalid config {}:\n  {}",
+                        path.display(),
+                        unmatched.join("\n  ")
+                    ))
+                    .into());
+                }
+            } else {
+                eprintln!(
+                    "warning: the names of the hooks cannot be checked, as these hook repos are not cloned yet: {}",
+                    missing.join(", ")
+                );
+            }
+            println!("{} is valid", path.display());
+        }
+        ("migrate", Some(args)) => {
+            let path = config_path(args.value_of("config"))?;
+            let content = read_file(&path)?;
+            let (migrated, changes) =
+                migrate::migrate(&content, ConfigFormat::detect(&path, &content))?;
+            if migrated == content {
+                println!(
+                    "{} is up to date: configuration version {}",
+                    path.display(),
+                    migrate::CONFIG_VERSION
+                );
+                return Ok(());
+            }
+            println!("--- {0}\n+++ {0}", path.display());
+            print!("{}", migrate::diff(&content, &migrated, 3));
+            for change in changes {
+                println!("{}", change);
+            }
+            if !args.is_present("dry-run") {
+                std::fs::write(&path, migrated)?;
+                println!(
+                    "Migrated {} to configuration version {}",
+                    path.display(),
+                    migrate::CONFIG_VERSION
+                );
+            }
+        }
+        ("import", Some(args)) => {
+            let path = config_path(args.value_of("config"))?;
+            let root = PathBuf::from(git::root()?);
+            let (source, import) = match args.subcommand() {
+                ("pre-commit", Some(import_args)) => {
+                    let source = import_args
+                        .value_of("file")
+                        .map(PathBuf::from)
+                        .unwrap_or_else(|| root.join(PRE_COMMIT_CONFIG));
+                    let import = import::pre_commit(&read_file(&source)?)?;
+                    (source, import)
+                }
+                ("husky", Some(import_args)) => {
+                    let dir = import_args
+                        .value_of("dir")
+                        .map(PathBuf::from)
+                        .unwrap_or(root);
+                    let import = import::husky(&dir)?;
+                    (dir.join(HUSKY_DIR), import)
+                }
+                ("lefthook", Some(import_args)) => {
+                    let source = match import_args.value_of("file") {
+                        Some(file) => PathBuf::from(file),
+                        None => LEFTHOOK_CONFIGS
+                            .iter()
+                            .map(|f| root.join(f))
+                            .find(|p| p.is_file())
+                            .unwrap_or_else(|| root.join(LEFTHOOK_CONFIGS[0])),
+                    };
+                    let import = import::lefthook(&read_file(&source)?)?;
+                    (source, import)
+                }
+                _ => unreachable!("clap requires a known subcommand"),
+            };
+            write_new_yaml(&path, &to_config_yaml(&import.config)?)?;
+            println!("Converted {} to {}", source.display(), path.display());
+            if !import.notes.is_empty() {
+                println!("Some of it could not be converted as is:");
+                for note in &import.notes {
+                    println!("  {}", note);
+                }
+            }
+        }
+        ("export", Some(args)) => {
+            if let ("pre-commit", Some(export_args)) = args.subcommand() {
+                let conf = load_config(args.value_of("config"), &load_options(Some(export_args)))?;
+                let path = match export_args.value_of("file") {
+                    Some(file) => PathBuf::from(file),
+                    None => PathBuf::from(git::root()?).join(PRE_COMMIT_CONFIG),
+                };
+                let export = export::pre_commit(&conf)?;
+                write_new_yaml(&path, &export.content)?;
+                println!("Wrote {}", path.display());
+                if !export.notes.is_empty() {
+                    println!("Some of it could not be written as is:");
+                    for note in &export.notes {
+                        println!("  {}", note);
+                    }
+                }
+            }
+        }
+        ("vendor", Some(args)) => {
+            let conf = load_config(args.value_of("config"), &load_options(Some(args)))?;
+            // only the trusted repos are initialized, and have hooks. The repository's own hook
+            // repo is already in it.
+            let (trusted, untrusted): (Vec<_>, Vec<_>) = conf
+                .repos
+                .into_iter()
+                .filter(|r| !r.is_project())
+                .partition(|r| !r.hooks.is_empty());
+            for repo in &untrusted {
+                error!("{} was not vendored: it is not trusted", repo.url);
+            }
+            let root = PathBuf::from(git::root()?);
+            for vendored in vendor::vendor(&root, &trusted)? {
+                println!(
+                    "Vendored {} at {} in {}",
+                    vendored.url,
+                    vendored.commit,
+                    root.join(vendor::VENDOR_DIR).join(&vendored.dir).display()
+                );
+            }
+            println!("Commit {} to use them from now on.", vendor::VENDOR_DIR);
+        }
+        ("repo", Some(args)) => {
+            if let ("lint", Some(lint_args)) = args.subcommand() {
+                let dir = PathBuf::from(lint_args.value_of("dir").unwrap_or("."));
+                let problems = repo::lint(&dir)?;
+                for problem in &problems {
+                    error!("{}", problem);
+                }
+                if !problems.is_empty() {
+                    return Err(anyhow::Error::msg(format!(
+                        "{} problem(s) found in the hook repo",
+                        problems.len()
+                    )));
+                }
+                println!("The hook repo is valid.");
+            } else if let ("test", Some(test_args)) = args.subcommand() {
+                let dir = PathBuf::from(test_args.value_of("dir").unwrap_or("."));
+                let results = repo::test(&dir, load_options(Some(test_args)).force_setup)?;
+                for result in &results {
+                    if result.passed {
+                        println!("ok      {}", result.name);
+                    } else {
+                        println!("FAILED  {}", result.name);
+                        if let Some(detail) = &result.detail {
+                            println!("{}", detail);
+                        }
+                    }
+                }
+                let failed = results.iter().filter(|r| !r.passed).count();
+                if failed > 0 {
+                    return Err(GitHooksError::HookFailure(format!(
+                        "{} of {} test case(s) failed",
+                        failed,
+                        results.len()
+                    ))
+                    .into());
+                }
+                println!("All {} test case(s) passed.", results.len());
+            } else if let ("new", Some(new_args)) = args.subcommand() {
+                let dir = PathBuf::from(new_args.value_of("dir").unwrap_or_default());
+                for file in repo::new(&dir)? {
+                    println!("Wrote {}", file.display());
+                }
+                println!(
+                    "Run `git-hooks repo test {}` to check the example hook, then make it yours!",
+                    dir.display()
+                );
+            }
+        }
+        ("search", Some(args)) => {
+            let index = Index::fetch(&index_location(args.value_of("index")))?;
+            let found = index.search(args.value_of("term").unwrap_or_default());
+            if found.is_empty() {
+                println!("No hook repo found.");
+            }
+            for repo in found {
+                match &repo.latest {
+                    Some(latest) => println!("{} {} ({})", repo.name, repo.url, latest),
+                    None => println!("{} {}", repo.name, repo.url),
+                }
+                if let Some(description) = &repo.description {
+                    println!("    {}", description);
+                }
+                for hook in &repo.hooks {
+                    match &hook.description {
+                        Some(description) => println!("  {}: {}", hook.name, description),
+                        None => println!("  {}", hook.name),
+                    }
+                }
+            }
+        }
+        ("status", args) => {
+            let path = config_path(args.and_then(|a| a.value_of("config")))?;
+            print!("{}", status(&git::root()?, &path)?);
+        }
+        ("explain", Some(args)) => {
+            let conf = load_config(args.value_of("config"), &load_options(Some(args)))?;
+            let event: HookEvent = args.value_of("event").unwrap_or_default().parse()?;
+            let hook_args: Vec<String> = args
+                .values_of("args")
+                .map(|v| v.map(|a| a.to_string()).collect())
+                .unwrap_or_default();
+            print!("{}", conf.explain(&RunContext::new(event, &hook_args)?)?);
+        }
+        ("exec", Some(args)) => {
+            let path = config_path(args.value_of("config"))?;
+            let opts = load_options(Some(args));
+            // only the repo of the hook is set up
+            let conf = if path.exists() {
+                HookConfig::read(&path, opts.strict)?
+            } else {
+                HookConfig::default()
+            };
+            let (repo, name) = exec::parse_target(args.value_of("target").unwrap_or_default())?;
+            let files = match args.values_of("files") {
+                Some(files) => Some(exec::repo_files(
+                    Path::new(&git::root()?),
+                    &env::current_dir()?,
+                    &files.map(|f| f.to_string()).collect::<Vec<_>>(),
+                )?),
+                None => None,
+            };
+            let (repo, hook) = conf.exec_target(repo, name, &opts)?;
+            let event = match args.value_of("event") {
+                Some(event) => event.parse()?,
+                None => exec::default_event(&hook),
+            };
+            let hook_args: Vec<String> = args
+                .values_of("arg")
+                .map(|v| v.map(|a| a.to_string()).collect())
+                .unwrap_or_default();
+            let mut ctx = RunContext::new(event, &hook_args)?;
+            if let Some(files) = files {
+                ctx.changed_files = files;
+            }
+            ctx.verbose = true;
+            println!("Running {} of {} on {}", hook.name, repo.url, event);
+            if let Err(e) = run_hook(&hook, &repo.local_path()?, &ctx) {
+                return Err(
+                    GitHooksError::HookFailure(format!("{} failed: {}", hook.name, e)).into(),
+                );
+            }
+            println!("{} passed", hook.name);
+        }
+        ("try", Some(args)) => {
+            let preview = Preview::fetch(
+                args.value_of("url").unwrap_or_default(),
+                args.value_of("rev"),
+            )?;
+            print!("{}", preview::describe(&preview.repo)?);
+            if let Some(name) = args.value_of("hook") {
+                let event = args.value_of("event").map(str::parse).transpose()?;
+                let files: Vec<String> = args
+                    .values_of("files")
+                    .map(|v| v.map(|f| f.to_string()).collect())
+                    .unwrap_or_default();
+                println!("\nRunning {}, as if on {} file(s)", name, files.len());
+                let (outcome, modified) =
+                    preview.dry_run(name, event, &files, load_options(Some(args)).force_setup)?;
+                if !modified.is_empty() {
+                    println!("{} modified {}", name, modified.join(", "));
+                }
+                if let Err(e) = outcome {
+                    return Err(
+                        GitHooksError::HookFailure(format!("{} failed: {}", name, e)).into(),
+                    );
+                }
+                println!("{} passed", name);
+            }
+            match &preview.repo.version {
+                Some(version) => println!(
+                    "\nRun `git-hooks add repo {} --rev {}` to use it.",
+                    preview.repo.url, version
+                ),
+                None => println!("\nRun `git-hooks add repo {}` to use it.", preview.repo.url),
+            }
+        }
         ("run", args) => {
+            // built before the configuration is loaded, which may ask the user whether to trust
+            // a repo: the refs git gives on stdin must be read by then
+            let run = match args {
+                Some(arg_matches) => match arg_matches.value_of("event") {
+                    Some(event) => {
+                        let event: HookEvent = event.parse()?;
+                        let hook_args: Vec<String> = arg_matches
+                            .values_of("args")
+                            .map(|v| v.map(|a| a.to_string()).collect())
+                            .unwrap_or_default();
+                        let mut ctx = RunContext::new(event, &hook_args)?;
+                        if let Some(from) = arg_matches.value_of("from-ref") {
+                            ctx = ctx.with_range(RefRange {
+                                from: from.to_string(),
+                                to: arg_matches.value_of("to-ref").unwrap_or("HEAD").to_string(),
+                            })?;
+                        }
+                        ctx.strict = arg_matches.is_present("strict");
+                        ctx.verbose = arg_matches.is_present("verbose")
+                            || env::var_os("GIT_HOOKS_VERBOSE").is_some();
+                        Some((event, ctx))
+                    }
+                    None => None,
+                },
+                None => None,
+            };
             debug!("reading conf");
-            let conf = HookConfig::from_file(None)?;
-            let active_hooks_names: Vec<String> =
-                conf.hooks.iter().map(|h| h.name.clone()).collect();
+            let mut conf =
+                load_config(args.and_then(|a| a.value_of("config")), &load_options(args))?;
             debug!("merged conf: {:#?}", conf);
             if let Some(arg_matches) = args {
-                if let Some(event) = arg_matches.value_of("event") {
-                    let mut has_executed_hook = false;
-                    let mut had_error = false;
-                    let event = HookEvent::from_kebab_case(event).expect(
-                        "Could not unwrap event, although it should be present, thanks to clap",
-                    );
-                    conf.repos
-                        .iter()
-                        .map(|repo| {
-                            repo.hooks
-                                .iter()
-                                // filter hooks with the right event
-                                .filter(|&hook| {
-                                    (*hook).on_event.as_ref().unwrap_or(&vec![HookEvent::PreCommit]).contains(&event)
-                                })
-                                // filter hooks with their IDs present.
-                                .filter(|&hook| {
-                                    active_hooks_names.contains(&hook.name)
-                                })
-                                .map(|hook| {
-                                    debug!("would run hook {:?}", hook);
-                                    if let Err(e) = run_hook(&hook,
-                                                             &get_local_repo_path(&repo.url)
-                                                                 .expect("could not get local root repo when attempting to run hook")) {
-                                        warn!(
-                                            "An error occurred while executing {}: {}",
-                                            hook.name, e
-                                        );
-                                        had_error = true;
-                                    }
-                                    has_executed_hook = true;
-                                }).for_each(drop);
-                        })
-                        .for_each(drop);
-                    if !has_executed_hook {
-                        info!("Nothing to do.");
+                if let Some((event, ctx)) = run {
+                    if let Some(runs) = arg_matches.value_of("autofix") {
+                        conf.autofix = Some(runs.parse().map_err(|_| {
+                            GitHooksError::Config(format!("invalid number of runs: {}", runs))
+                        })?);
+                    }
+                    if let Some(names) = arg_matches.values_of("hook") {
+                        conf.only(&names.map(|n| n.to_string()).collect::<Vec<_>>())?;
+                    }
+                    if arg_matches.is_present("select") || env::var_os("GIT_HOOKS_SELECT").is_some()
+                    {
+                        conf.select(event)?;
+                    }
+                    let report = conf.run_in(&ctx)?;
+                    report.log();
+                    if !ctx.bare {
+                        let root = git::root()?;
+                        if let Err(e) = report.save(&root) {
+                            debug!("could not keep the report of the run: {}", e);
+                        }
+                        if let Err(e) = outdated::notify(&root, &conf.repos) {
+                            debug!("could not check the pinned versions: {}", e);
+                        }
                     }
-                    if had_error {
-                        return Err(anyhow::Error::msg("a hook reported malfunction"));
+                    if !report.success() {
+                        return Err(GitHooksError::HookFailure(
+                            "a hook reported malfunction".to_string(),
+                        )
+                        .into());
                     }
                 }
             }
         }
+        ("ci", Some(args)) => {
+            let opts = LoadOptions {
+                trust_all: true,
+                offline: true,
+                ..load_options(Some(args))
+            };
+            let conf = load_config(args.value_of("config"), &opts)?;
+            let events: Vec<HookEvent> = match args.values_of("events") {
+                Some(events) => events.filter_map(HookEvent::from_kebab_case).collect(),
+                None => vec![HookEvent::PreCommit],
+            };
+            let mut runs = Vec::new();
+            for event in events {
+                let ctx = RunContext::new(event, &[])?;
+                let ctx = match args.value_of("from-ref") {
+                    Some(from) => ctx.with_range(RefRange {
+                        from: from.to_string(),
+                        to: args.value_of("to-ref").unwrap_or("HEAD").to_string(),
+                    })?,
+                    None => ctx.with_all_files()?,
+                };
+                let ctx = RunContext {
+                    strict: !args.is_present("no-strict"),
+                    ..ctx
+                };
+                let report = conf.run_in(&ctx)?;
+                report.log();
+                runs.push(report);
+            }
+            let report = CiReport::new(runs);
+            let json = serde_json::to_string_pretty(&report)?;
+            match args.value_of("report") {
+                Some(file) => std::fs::write(file, json + "\n")?,
+                None => println!("{}", json),
+            }
+            if !report.success {
+                return Err(
+                    GitHooksError::HookFailure("a hook reported malfunction".to_string()).into(),
+                );
+            }
+        }
         _ => {
             // Should not happen, clap handles this
             error!("A subcommand must be set! see help (-h)");
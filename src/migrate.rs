@@ -0,0 +1,286 @@
+//! `git-hooks migrate`: upgrades the configuration files written for older versions of their
+//! schema, as its fields are renamed or moved, so that breaking changes do not strand them.
+use log::warn;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::config_format::ConfigFormat;
+use crate::error::GitHooksError;
+
+/// The version of the configuration schema this `git-hooks` reads. The configurations without
+/// `version` are of the first one.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// The changes from a version of the schema to the next one
+pub struct Migration {
+    /// The version the configuration is at once migrated
+    pub to: u32,
+    /// What changes, for the users
+    pub what: &'static str,
+    /// Rewrites the configuration, keeping its comments & formatting where it can
+    pub apply: fn(&str, ConfigFormat) -> anyhow::Result<String>,
+}
+
+/// The migrations from each version to the next, oldest first. The first versioned schema has
+/// nothing to migrate from yet.
+pub const MIGRATIONS: &[Migration] = &[];
+
+/// The version of a configuration, whatever else it holds
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Versioned {
+    version: Option<u32>,
+}
+
+/// Checks the schema `version` of the configuration read from `source`: newer ones cannot be
+/// read, older ones are read as they are, with a warning
+pub fn check_version(version: Option<u32>, source: &str) -> anyhow::Result<()> {
+    let version = version.unwrap_or(1);
+    if version > CONFIG_VERSION {
+        return Err(GitHooksError::Config(format!(
+            "{} is written for a newer git-hooks, reading configuration version {}: update git-hooks",
+            source, version
+        ))
+        .into());
+    }
+    if version < CONFIG_VERSION {
+        warn!(
+            "{} is written for configuration version {}: run `git-hooks migrate` to upgrade it to version {}",
+            source, version, CONFIG_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// Upgrades the configuration `content`, written in `format`, to `CONFIG_VERSION`, setting its
+/// `version`. Returns the migrated configuration, and what the migrations changed.
+pub fn migrate(content: &str, format: ConfigFormat) -> anyhow::Result<(String, Vec<&'static str>)> {
+    migrate_with(content, format, MIGRATIONS, CONFIG_VERSION)
+}
+
+fn migrate_with(
+    content: &str,
+    format: ConfigFormat,
+    migrations: &[Migration],
+    latest: u32,
+) -> anyhow::Result<(String, Vec<&'static str>)> {
+    let declared = if content.trim().is_empty() {
+        None
+    } else {
+        format.parse::<Versioned>(content)?.version
+    };
+    let version = declared.unwrap_or(1);
+    if version > latest {
+        return Err(GitHooksError::Config(format!(
+            "the configuration version {} is newer than the ones this git-hooks knows, up to {}",
+            version, latest
+        ))
+        .into());
+    }
+    let mut migrated = content.to_string();
+    let mut changes = Vec::new();
+    for migration in migrations.iter().filter(|m| m.to > version) {
+        migrated = (migration.apply)(&migrated, format)?;
+        changes.push(migration.what);
+    }
+    if declared != Some(latest) {
+        migrated = set_version(&migrated, format, latest)?;
+    }
+    Ok((migrated, changes))
+}
+
+/// Sets the top-level `version` of the configuration `content`
+fn set_version(content: &str, format: ConfigFormat, version: u32) -> anyhow::Result<String> {
+    let (existing, line) = match format {
+        ConfigFormat::Yaml => (r"(?m)^version:.*$", format!("version: {}", version)),
+        ConfigFormat::Toml => (r"(?m)^version\s*=.*$", format!("version = {}", version)),
+        ConfigFormat::Json => {
+            let mut conf: serde_json::Value = if content.trim().is_empty() {
+                serde_json::json!({})
+            } else {
+                serde_json::from_str(content)?
+            };
+            let object = conf
+                .as_object_mut()
+                .ok_or_else(|| anyhow::Error::msg("the configuration is not a JSON object"))?;
+            object.insert("version".to_string(), version.into());
+            return Ok(serde_json::to_string_pretty(&conf)? + "\n");
+        }
+    };
+    let existing = Regex::new(existing)?;
+    if existing.is_match(content) {
+        return Ok(existing.replace(content, line.as_str()).into_owned());
+    }
+    // the top-level keys of TOML come before the tables: the version goes first, after the
+    // leading comments
+    let mut lines: Vec<&str> = content.lines().collect();
+    let first = lines
+        .iter()
+        .position(|l| !l.trim().is_empty() && !l.starts_with('#') && *l != "---")
+        .unwrap_or(lines.len());
+    lines.insert(first, &line);
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Renames the key `old` to `new` wherever it appears in the configuration `content`, for the
+/// migrations of renamed fields
+pub fn rename_key(
+    content: &str,
+    format: ConfigFormat,
+    old: &str,
+    new: &str,
+) -> anyhow::Result<String> {
+    let old = regex::escape(old);
+    let renamed = match format {
+        ConfigFormat::Yaml => Regex::new(&format!(r"(?m)^(\s*(?:-\s+)?){}:", old))?
+            .replace_all(content, format!("${{1}}{}:", new).as_str())
+            .into_owned(),
+        ConfigFormat::Toml => {
+            let keys = Regex::new(&format!(r"(?m)^(\s*){}(\s*=)", old))?
+                .replace_all(content, format!("${{1}}{}${{2}}", new).as_str())
+                .into_owned();
+            Regex::new(&format!(r"(?m)^(\s*\[\[?(?:[\w.]+\.)?){}(\]\]?)", old))?
+                .replace_all(&keys, format!("${{1}}{}${{2}}", new).as_str())
+                .into_owned()
+        }
+        ConfigFormat::Json => Regex::new(&format!(r#""{}"(\s*:)"#, old))?
+            .replace_all(content, format!(r#""{}"${{1}}"#, new).as_str())
+            .into_owned(),
+    };
+    Ok(renamed)
+}
+
+/// Returns the lines removed from `old` & added in `new`, as the hunks of a unified diff with
+/// `context` unchanged lines around them. Identical contents give an empty diff.
+pub fn diff(old: &str, new: &str, context: usize) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // lengths of the longest common subsequences of the ends of the two texts
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    // each line of either text, with its position in both & its prefix
+    let mut lines: Vec<(usize, usize, char, &str)> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((i, j, ' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push((i, j, '-', old[i]));
+            i += 1;
+        } else {
+            lines.push((i, j, '+', new[j]));
+            j += 1;
+        }
+    }
+    let changed: Vec<usize> = (0..lines.len()).filter(|&k| lines[k].2 != ' ').collect();
+    let mut out = String::new();
+    let mut k = 0;
+    while k < changed.len() {
+        let start = changed[k].saturating_sub(context);
+        let mut end = changed[k];
+        // the changes close enough to share their context make a single hunk
+        while k + 1 < changed.len() && changed[k + 1] <= end + 2 * context + 1 {
+            k += 1;
+            end = changed[k];
+        }
+        let end = (end + context).min(lines.len() - 1);
+        let hunk = &lines[start..=end];
+        let count = |prefix: char| hunk.iter().filter(|l| l.2 == ' ' || l.2 == prefix).count();
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk[0].0 + 1,
+            count('-'),
+            hunk[0].1 + 1,
+            count('+')
+        ));
+        for (_, _, prefix, line) in hunk {
+            out.push_str(&format!("{}{}\n", prefix, line));
+        }
+        k += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config_format::ConfigFormat;
+    use crate::migrate::{diff, migrate, migrate_with, rename_key, Migration, CONFIG_VERSION};
+    use crate::HookConfig;
+
+    fn rename_events(content: &str, format: ConfigFormat) -> anyhow::Result<String> {
+        rename_key(content, format, "events", "on_event")
+    }
+
+    #[test]
+    fn test_migrate() {
+        let (migrated, changes) =
+            migrate("# mine\nhooks:\n  - name: fmt\n", ConfigFormat::Yaml).unwrap();
+        assert!(changes.is_empty());
+        assert_eq!(
+            migrated,
+            format!(
+                "# mine\nversion: {}\nhooks:\n  - name: fmt\n",
+                CONFIG_VERSION
+            )
+        );
+        let conf: HookConfig = serde_yaml::from_str(&migrated).unwrap();
+        assert_eq!(conf.version, Some(CONFIG_VERSION));
+        // migrated configurations are left as they are
+        assert_eq!(migrate(&migrated, ConfigFormat::Yaml).unwrap().0, migrated);
+        assert!(migrate("version: 99\n", ConfigFormat::Yaml).is_err());
+
+        let migrations = [Migration {
+            to: 2,
+            what: "`events` is renamed `on_event`",
+            apply: rename_events,
+        }];
+        let yaml = "version: 1\nhooks:\n  - name: fmt\n    events: [pre-push] # slow\n";
+        let (migrated, changes) = migrate_with(yaml, ConfigFormat::Yaml, &migrations, 2).unwrap();
+        assert_eq!(
+            migrated,
+            "version: 2\nhooks:\n  - name: fmt\n    on_event: [pre-push] # slow\n"
+        );
+        assert_eq!(changes, ["`events` is renamed `on_event`"]);
+        let toml = "# mine\n[[hooks]]\nname = \"fmt\"\nevents = [\"pre-push\"]\n";
+        assert_eq!(
+            migrate_with(toml, ConfigFormat::Toml, &migrations, 2)
+                .unwrap()
+                .0,
+            "# mine\nversion = 2\n[[hooks]]\nname = \"fmt\"\non_event = [\"pre-push\"]\n"
+        );
+        let json = r#"{"hooks": [{"name": "fmt", "events": ["pre-push"]}]}"#;
+        let migrated: serde_json::Value = serde_json::from_str(
+            &migrate_with(json, ConfigFormat::Json, &migrations, 2)
+                .unwrap()
+                .0,
+        )
+        .unwrap();
+        assert_eq!(
+            migrated,
+            serde_json::json!({"version": 2, "hooks": [{"name": "fmt", "on_event": ["pre-push"]}]})
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\n", "a\nb\n", 3), "");
+        assert_eq!(
+            diff("a\nb\nc\nd\ne\nf\ng\n", "a\nB\nc\nd\ne\nf\ng\nh\n", 1),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -7,1 +7,2 @@\n g\n+h\n"
+        );
+        assert_eq!(
+            diff("hooks: []\n", "version: 1\nhooks: []\n", 3),
+            "@@ -1,1 +1,2 @@\n+version: 1\n hooks: []\n"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use crate::config_format::default_config_file_in;
+
+/// Returns the configuration files of the subprojects `files` belong to, looking for them from
+/// each file's directory up to `root`, excluded. `files` are relative to `root`.
+/// Shallower configurations come first, so that deeper ones can be merged over them.
+pub fn nested_config_files<S: AsRef<str>>(root: &Path, files: &[S]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for file in files {
+        for dir in Path::new(file.as_ref()).ancestors().skip(1) {
+            if dir.as_os_str().is_empty() {
+                break;
+            }
+            if !dirs.iter().any(|d| d == dir) {
+                dirs.push(dir.to_path_buf());
+            }
+        }
+    }
+    dirs.sort_by_key(|d| (d.components().count(), d.clone()));
+    dirs.iter()
+        .filter_map(|d| default_config_file_in(&root.join(d)))
+        .collect()
+}
+
+/// Returns the `files` under `dir`, relative to it. `files` & `dir` are relative to the same
+/// directory.
+pub fn files_under<S: AsRef<str>>(dir: &str, files: &[S]) -> Vec<String> {
+    files
+        .iter()
+        .filter_map(|f| Path::new(f.as_ref()).strip_prefix(dir).ok())
+        .map(|f| f.display().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monorepo::{files_under, nested_config_files};
+    use std::fs;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_nested_config_files() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let root = dir.path();
+        fs::create_dir_all(root.join("services/api/src")).expect("could not create dirs");
+        fs::create_dir_all(root.join("services/web")).expect("could not create dirs");
+        fs::write(root.join(".hooks.yml"), "").expect("could not write config");
+        fs::write(root.join("services/api/.hooks.yml"), "").expect("could not write config");
+        fs::write(root.join("services/.hooks.toml"), "").expect("could not write config");
+        fs::write(root.join("services/web/.hooks.yml"), "").expect("could not write config");
+        assert_eq!(
+            nested_config_files(root, &["services/api/src/main.rs", "README.md"]),
+            vec![
+                root.join("services/.hooks.toml"),
+                root.join("services/api/.hooks.yml")
+            ]
+        );
+        assert!(nested_config_files(root, &["README.md"]).is_empty());
+    }
+
+    #[test]
+    fn test_files_under() {
+        let files = vec![
+            "services/api/src/main.rs",
+            "services/apiv2/main.rs",
+            "README.md",
+        ];
+        assert_eq!(files_under("services/api", &files), vec!["src/main.rs"]);
+        assert_eq!(files_under("services/api/", &files), vec!["src/main.rs"]);
+        assert!(files_under("docs", &files).is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+/// Wraps `cmd` & `args` in a command running them inside a nix environment.
+///
+/// `nix` is either the path to a `.nix` file in the hook repository, used with `nix-shell`, or a
+/// flake reference, used with `nix develop`. Flake references starting with `.` are relative to
+/// the hook repository.
+pub fn wrap_command(
+    nix: &str,
+    hook_repo_path: &str,
+    cmd: &str,
+    args: &[String],
+) -> (String, Vec<String>) {
+    if nix.ends_with(".nix") {
+        let script = std::iter::once(cmd)
+            .chain(args.iter().map(|a| a.as_str()))
+            .map(|a| shlex::quote(a).to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        (
+            "nix-shell".to_string(),
+            vec![
+                Path::new(hook_repo_path).join(nix).display().to_string(),
+                "--run".to_string(),
+                script,
+            ],
+        )
+    } else {
+        let flake = match nix.strip_prefix('.') {
+            Some(rest) => format!("{}{}", hook_repo_path, rest),
+            None => nix.to_string(),
+        };
+        let mut nix_args = vec!["develop".to_string(), flake, "--command".to_string()];
+        nix_args.push(cmd.to_string());
+        nix_args.extend(args.iter().cloned());
+        ("nix".to_string(), nix_args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nix::wrap_command;
+
+    #[test]
+    fn test_wrap_command() {
+        let (cmd, args) = wrap_command(
+            "shell.nix",
+            "/tmp/hook-repo",
+            "shellcheck",
+            &["my script.sh".to_string()],
+        );
+        assert_eq!(cmd, "nix-shell");
+        assert_eq!(
+            args,
+            vec![
+                "/tmp/hook-repo/shell.nix",
+                "--run",
+                "shellcheck \"my script.sh\""
+            ]
+        );
+
+        let (cmd, args) = wrap_command(
+            ".#lint",
+            "/tmp/hook-repo",
+            "shellcheck",
+            &["a.sh".to_string()],
+        );
+        assert_eq!(cmd, "nix");
+        assert_eq!(
+            args,
+            vec![
+                "develop",
+                "/tmp/hook-repo#lint",
+                "--command",
+                "shellcheck",
+                "a.sh"
+            ]
+        );
+
+        let (_, args) = wrap_command("github:org/tools#lint", "/tmp/hook-repo", "x", &[]);
+        assert_eq!(args[1], "github:org/tools#lint");
+    }
+}
@@ -0,0 +1,107 @@
+//! The notice about the hook repos pinned to older versions than the tags their clones know of,
+//! so that the pins do not rot silently. Nothing is fetched for it.
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use log::debug;
+
+use crate::utils::{is_older, parse_version};
+use crate::{git, ExternalHookRepo};
+
+/// Remembers when the pins were last checked, relative to the git directory
+const CHECK_MARKER: &str = "git-hooks/outdated-check";
+
+/// How often the pins are checked at most
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A hook repo pinned to an older version than the newest tag of its clone
+#[derive(Debug, PartialEq, Eq)]
+pub struct Outdated {
+    pub url: String,
+    pub version: String,
+    pub latest: String,
+}
+
+/// Prints which `repos` of the repository at `root` are pinned to older versions than the ones
+/// their clones know of, unless it was checked less than a day ago
+pub fn notify(root: &str, repos: &[ExternalHookRepo]) -> anyhow::Result<()> {
+    let marker = Path::new(&git::common_dir(root)?).join(CHECK_MARKER);
+    let checked = fs::metadata(&marker)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok());
+    if checked.is_some_and(|elapsed| elapsed < CHECK_INTERVAL) {
+        return Ok(());
+    }
+    if let Some(parent) = marker.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&marker, "")?;
+    let outdated = outdated_repos(repos);
+    if outdated.is_empty() {
+        return Ok(());
+    }
+    eprintln!("Newer versions of hook repos are available:");
+    for repo in &outdated {
+        eprintln!("  {}: {} -> {}", repo.url, repo.version, repo.latest);
+    }
+    eprintln!("Bump their version in the configuration to use them.");
+    Ok(())
+}
+
+/// Returns the `repos` pinned to an older version than the newest tag of their clone. The repos
+/// pinned to something else than a version tag, eg. a branch, are left out.
+pub fn outdated_repos(repos: &[ExternalHookRepo]) -> Vec<Outdated> {
+    repos
+        .iter()
+        .filter_map(|repo| {
+            let version = repo.version.as_ref()?;
+            let pinned = parse_version(version)?;
+            let tags = match repo.local_path().and_then(|dir| git::tags(&dir)) {
+                Ok(tags) => tags,
+                Err(e) => {
+                    debug!("could not list the tags of {}: {}", repo.url, e);
+                    return None;
+                }
+            };
+            let latest = newest_version(&tags)?;
+            if !is_older(&pinned, &parse_version(latest)?) {
+                return None;
+            }
+            Some(Outdated {
+                url: repo.url.clone(),
+                version: version.clone(),
+                latest: latest.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the newest of the version `tags`, pre-releases left out
+pub fn newest_version(tags: &[String]) -> Option<&String> {
+    tags.iter()
+        .filter_map(|tag| parse_version(tag).map(|version| (version, tag)))
+        .max()
+        .map(|(_, tag)| tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::outdated::newest_version;
+    use crate::utils::parse_version;
+
+    #[test]
+    fn test_newest_version() {
+        assert_eq!(parse_version("v1.2.10"), Some(vec![1, 2, 10]));
+        assert_eq!(parse_version("2.0"), Some(vec![2, 0]));
+        assert_eq!(parse_version("v2.0.0-rc.1"), None);
+        assert_eq!(parse_version("main"), None);
+        let tags: Vec<String> = ["v1.2.0", "v1.10.0", "v1.9.3", "v2.0.0-rc.1", "nightly"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(newest_version(&tags).map(|t| t.as_str()), Some("v1.10.0"));
+        assert_eq!(newest_version(&[]), None);
+    }
+}
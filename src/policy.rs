@@ -0,0 +1,259 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::error::GitHooksError;
+use crate::utils::{system_config_dir, user_config_dir};
+
+const POLICY_FILENAME: &str = "policy.yml";
+
+/// Restrictions an administrator (or the user) puts on which external hook repos may be used.
+///
+/// ```yaml
+/// # disable external repos entirely
+/// external_repos: false
+/// # or only allow some hosts
+/// allowed_hosts:
+///   - github.com
+///   - git.corp.example.com
+/// # and forbid some repos, by URL prefix, whichever URL they are given by
+/// forbidden_repos:
+///   - https://github.com/someone/
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(default)]
+pub struct Policy {
+    external_repos: bool,
+    allowed_hosts: Vec<String>,
+    forbidden_repos: Vec<String>,
+    #[serde(skip)]
+    source: PathBuf,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            external_repos: true,
+            allowed_hosts: vec![],
+            forbidden_repos: vec![],
+            source: PathBuf::new(),
+        }
+    }
+}
+
+impl Policy {
+    /// Reads a policy file. Returns `None` if it does not exist.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> anyhow::Result<Option<Policy>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mut policy: Policy = serde_yaml::from_str(&fs::read_to_string(path)?).map_err(|e| {
+            GitHooksError::Config(format!("invalid policy {}: {}", path.display(), e))
+        })?;
+        policy.source = path.to_path_buf();
+        Ok(Some(policy))
+    }
+
+    /// Checks if the given repo URL may be used
+    pub fn check(&self, url: &str) -> anyhow::Result<()> {
+        if !self.external_repos {
+            return Err(GitHooksError::Config(format!(
+                "external hook repos are disabled by {}, but the config uses {}",
+                self.source.display(),
+                url
+            ))
+            .into());
+        }
+        let normalized = normalize_url(url.trim_end_matches('/'));
+        if let Some(prefix) = self
+            .forbidden_repos
+            .iter()
+            .find(|p| normalized.starts_with(&normalize_url(p)))
+        {
+            return Err(GitHooksError::Config(format!(
+                "{} is forbidden by {} ({})",
+                url,
+                self.source.display(),
+                prefix
+            ))
+            .into());
+        }
+        if self.allowed_hosts.is_empty() {
+            return Ok(());
+        }
+        match url_host(url) {
+            Some(host)
+                if self
+                    .allowed_hosts
+                    .iter()
+                    .any(|h| h.eq_ignore_ascii_case(host)) =>
+            {
+                Ok(())
+            }
+            Some(host) => Err(GitHooksError::Config(format!(
+                "{} is hosted on {}, which is not allowed by {} (allowed hosts: {})",
+                url,
+                host,
+                self.source.display(),
+                self.allowed_hosts.join(", ")
+            ))
+            .into()),
+            None => Err(GitHooksError::Config(format!(
+                "{} has no host, and {} only allows repos from {}",
+                url,
+                self.source.display(),
+                self.allowed_hosts.join(", ")
+            ))
+            .into()),
+        }
+    }
+}
+
+/// All the policies applying to this user: the system-wide one, then the user one.
+/// A repo must satisfy every one of them.
+#[derive(Debug, Default)]
+pub struct Policies(Vec<Policy>);
+
+impl Policies {
+    pub fn load() -> anyhow::Result<Policies> {
+        let mut paths = vec![system_config_dir().join(POLICY_FILENAME)];
+        if let Ok(dir) = user_config_dir() {
+            paths.push(dir.join(POLICY_FILENAME));
+        }
+        let mut policies = Vec::new();
+        for p in paths {
+            if let Some(policy) = Policy::from_path(&p)? {
+                debug!("loaded policy {}: {:?}", p.display(), policy);
+                policies.push(policy);
+            }
+        }
+        Ok(Policies(policies))
+    }
+
+    pub fn check(&self, url: &str) -> anyhow::Result<()> {
+        for policy in &self.0 {
+            policy.check(url)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a git URL into its host and its path.
+/// Handles `scheme://[user@]host[:port]/path` and scp-like `[user@]host:path` URLs.
+/// Local paths have no host.
+fn split_url(url: &str) -> (Option<&str>, &str) {
+    if let Some(idx) = url.find("://") {
+        let rest = &url[idx + 3..];
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let host = authority.rsplit('@').next().unwrap_or("");
+        let host = host.split(':').next().unwrap_or("");
+        return if host.is_empty() {
+            (None, path)
+        } else {
+            (Some(host), path)
+        };
+    }
+    // scp-like syntax. A colon after a slash is part of a local path.
+    let colon = match url.find(':') {
+        Some(colon) if !url[..colon].contains('/') => colon,
+        _ => return (None, url),
+    };
+    match url[..colon].rsplit('@').next().unwrap_or("") {
+        "" => (None, url),
+        host => (Some(host), &url[colon + 1..]),
+    }
+}
+
+/// Extracts the host part of a git URL, see `split_url`
+fn url_host(url: &str) -> Option<&str> {
+    split_url(url).0
+}
+
+/// Writes the repo at `url` the same way whichever URL it is given by, for the URLs to be
+/// compared: `host/path` without the scheme, the user, the port nor `.git`, or its path when
+/// local, in lower case
+fn normalize_url(url: &str) -> String {
+    let (host, path) = split_url(url.trim());
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let normalized = match host {
+        Some(host) => format!("{}/{}", host, path.trim_start_matches('/')),
+        None => path.to_string(),
+    };
+    normalized.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::exit_code;
+    use crate::policy::{normalize_url, url_host, Policy};
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(
+            url_host("https://github.com/paulollivier/rust-hooks"),
+            Some("github.com")
+        );
+        assert_eq!(
+            url_host("ssh://git@git.example.com:2222/hooks.git"),
+            Some("git.example.com")
+        );
+        assert_eq!(
+            url_host("git@github.com:paulollivier/rust-hooks.git"),
+            Some("github.com")
+        );
+        assert_eq!(url_host("/srv/git/hooks"), None);
+        assert_eq!(url_host("../hooks"), None);
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        for url in [
+            "https://github.com/someone/hooks",
+            "https://GitHub.com/someone/hooks.git",
+            "ssh://git@github.com:22/someone/hooks",
+            "git@github.com:someone/hooks.git",
+            "git://github.com/someone/hooks",
+        ] {
+            assert_eq!(normalize_url(url), "github.com/someone/hooks");
+        }
+        assert_eq!(normalize_url("/srv/git/hooks.git"), "/srv/git/hooks");
+        assert_eq!(normalize_url("file:///srv/git/hooks"), "/srv/git/hooks");
+    }
+
+    #[test]
+    fn test_check() {
+        let policy: Policy = serde_yaml::from_str("allowed_hosts: [github.com]").unwrap();
+        assert!(policy
+            .check("https://github.com/paulollivier/rust-hooks")
+            .is_ok());
+        let e = policy
+            .check("https://gitlab.com/someone/hooks")
+            .unwrap_err();
+        // like the invalid configurations
+        assert_eq!(exit_code(&e), 2);
+        assert!(policy.check("/srv/git/hooks").is_err());
+
+        let policy: Policy = serde_yaml::from_str("external_repos: false").unwrap();
+        assert!(policy
+            .check("https://github.com/paulollivier/rust-hooks")
+            .is_err());
+
+        let policy: Policy =
+            serde_yaml::from_str("forbidden_repos: [https://github.com/someone/]").unwrap();
+        assert!(policy.check("https://github.com/someone/hooks").is_err());
+        // the same repos by other URLs
+        assert!(policy.check("git@github.com:someone/hooks.git").is_err());
+        assert!(policy.check("ssh://git@GitHub.com/someone/hooks").is_err());
+        assert!(policy
+            .check("https://github.com/someone-else/hooks")
+            .is_ok());
+        assert!(policy
+            .check("https://github.com/paulollivier/rust-hooks")
+            .is_ok());
+
+        assert!(Policy::default().check("/srv/git/hooks").is_ok());
+    }
+}
@@ -0,0 +1,250 @@
+//! `git-hooks try`: a look at a hook repo before adding it to the configuration, from a throwaway
+//! clone.
+use std::env;
+use std::fmt::Write;
+use std::path::{Component, Path, PathBuf};
+
+use crate::context::RunContext;
+use crate::error::GitHooksError;
+use crate::exec::default_event;
+use crate::outdated::newest_version;
+use crate::policy::Policies;
+use crate::{content_hashes, git, run_hook, ExternalHookRepo, Hook, HookEvent};
+
+/// A hook repo cloned in a temporary directory, removed once done with
+pub struct Preview {
+    pub repo: ExternalHookRepo,
+    /// Where it is cloned
+    dir: PathBuf,
+}
+
+impl Preview {
+    /// Clones the repo at `url`, allowed by the policies, and checks `version` out, else its
+    /// newest version tag if any. Nothing of it runs.
+    pub fn fetch(url: &str, version: Option<&str>) -> anyhow::Result<Preview> {
+        Policies::load()?.check(url)?;
+        let dir = env::temp_dir().join(format!("git-hooks-try-{}", std::process::id()));
+        // removed when dropped, should the clone fail
+        let mut preview = Preview {
+            repo: ExternalHookRepo {
+                url: url.to_string(),
+                ..Default::default()
+            },
+            dir,
+        };
+        let clone_dir = preview.clone_dir();
+        git::clone(url, &clone_dir)
+            .map_err(|e| GitHooksError::Network(format!("could not fetch {}: {}", url, e)))?;
+        let tags = git::tags(&clone_dir)?;
+        let version = version
+            .map(String::from)
+            .or_else(|| newest_version(&tags).cloned());
+        if let Some(v) = &version {
+            git::checkout(v, &clone_dir)?;
+        }
+        preview.repo.version = version;
+        preview.repo.read_manifest(&clone_dir)?;
+        Ok(preview)
+    }
+
+    fn clone_dir(&self) -> String {
+        self.dir.join("repo").display().to_string()
+    }
+
+    /// Sets the repo up, then runs its hook `name` as if on `event`, else on its first event, on
+    /// copies of the `files` of the current directory staged in a scratch repository, so that
+    /// the project is left as it is. `force_setup` reruns the setup scripts. Returns the outcome
+    /// of the hook, and the files it modified.
+    pub fn dry_run(
+        &self,
+        name: &str,
+        event: Option<HookEvent>,
+        files: &[String],
+        force_setup: bool,
+    ) -> anyhow::Result<(anyhow::Result<String>, Vec<String>)> {
+        let hook = self.hook(name)?;
+        let clone_dir = self.clone_dir();
+        self.repo.setup_in(&clone_dir, force_setup)?;
+        let work_dir = self.dir.join("work");
+        std::fs::create_dir_all(&work_dir)?;
+        let work_dir = work_dir.display().to_string();
+        git::init(Some(&work_dir))?;
+        let old_dir = env::current_dir()?;
+        for file in files {
+            if !Path::new(file)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(anyhow::Error::msg(format!(
+                    "{} is not in the current directory",
+                    file
+                )));
+            }
+            let target = Path::new(&work_dir).join(file);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(old_dir.join(file), &target)
+                .map_err(|e| anyhow::Error::msg(format!("could not copy {}: {}", file, e)))?;
+        }
+        env::set_current_dir(&work_dir)?;
+        let outcome = run_in(hook, &clone_dir, event, files, &work_dir);
+        env::set_current_dir(&old_dir)?;
+        outcome
+    }
+
+    /// Returns the hook `name` of the repo
+    fn hook(&self, name: &str) -> anyhow::Result<&Hook> {
+        self.repo
+            .hooks
+            .iter()
+            .find(|h| h.name == name)
+            .ok_or_else(|| {
+                GitHooksError::Config(format!("{} defines no hook {}", self.repo.url, name)).into()
+            })
+    }
+}
+
+/// Runs `hook`, of the repo cloned in `clone_dir`, on the `files` staged in the scratch
+/// repository `work_dir`, the current directory. Returns its outcome, and the files it modified.
+fn run_in(
+    hook: &Hook,
+    clone_dir: &str,
+    event: Option<HookEvent>,
+    files: &[String],
+    work_dir: &str,
+) -> anyhow::Result<(anyhow::Result<String>, Vec<String>)> {
+    if !files.is_empty() {
+        git::add(files)?;
+    }
+    let ctx = RunContext {
+        event: Some(event.unwrap_or_else(|| default_event(hook))),
+        changed_files: git::changed_files(true)?,
+        verbose: true,
+        ..Default::default()
+    };
+    let before = content_hashes(work_dir, files);
+    let outcome = run_hook(hook, clone_dir, &ctx);
+    let modified = files
+        .iter()
+        .zip(before.iter().zip(content_hashes(work_dir, files)))
+        .filter(|(_, (before, after))| **before != *after)
+        .map(|(file, _)| file.clone())
+        .collect();
+    Ok((outcome, modified))
+}
+
+impl Drop for Preview {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Describes the manifest of `repo`: what it sets up, and for each of its hooks the events & files
+/// it reacts to, what it runs and how it is prepared
+pub fn describe(repo: &ExternalHookRepo) -> anyhow::Result<String> {
+    let mut out = String::new();
+    match &repo.version {
+        Some(version) => writeln!(out, "{} at {}", repo.url, version)?,
+        None => writeln!(out, "{}, no version tag", repo.url)?,
+    }
+    if let Some(setup) = &repo.setup {
+        writeln!(out, "setup: {}", setup.trim_end())?;
+    }
+    if let Some(tools) = &repo.tools {
+        let tools: Vec<String> = tools.iter().map(|(t, v)| format!("{} {}", t, v)).collect();
+        writeln!(out, "tools: {}", tools.join(", "))?;
+    }
+    if let Some(events) = &repo.default_on_event {
+        let events: Vec<&str> = events.iter().map(|e| e.to_kebab_case()).collect();
+        writeln!(out, "default events: {}", events.join(", "))?;
+    }
+    writeln!(out, "hooks:")?;
+    for hook in &repo.hooks {
+        writeln!(out, "  {}", hook.name)?;
+        if let Some(description) = &hook.description {
+            writeln!(out, "    {}", description.trim_end())?;
+        }
+        let events: Vec<&str> = hook
+            .on_event
+            .iter()
+            .chain(&repo.default_on_event)
+            .next()
+            .map(|events| events.iter().map(|e| e.to_kebab_case()).collect())
+            .unwrap_or_else(|| vec!["the configuration's default ones"]);
+        writeln!(out, "    events: {}", events.join(", "))?;
+        if let Some(regexes) = &hook.on_file_regex {
+            writeln!(out, "    files: {}", regexes.join(", "))?;
+        }
+        if let Some(action) = &hook.action {
+            writeln!(out, "    runs: {}", action.commands().join(" && "))?;
+        }
+        if let Some(script) = &hook.script {
+            writeln!(out, "    script: {}", script.trim_end())?;
+        }
+        if let Some(wasm) = &hook.wasm {
+            writeln!(out, "    wasm module: {}", wasm)?;
+        }
+        if let Some(image) = &hook.image {
+            writeln!(out, "    image: {}", image)?;
+        }
+        if let Some(nix) = &hook.nix {
+            writeln!(out, "    nix: {}", nix)?;
+        }
+        if let Some(language) = hook.language {
+            writeln!(out, "    language: {}", language.to_kebab_case())?;
+        }
+        if let Some(dependencies) = &hook.dependencies {
+            writeln!(out, "    dependencies: {}", dependencies.join(", "))?;
+        }
+        if let Some(setup_script) = &hook.setup_script {
+            writeln!(out, "    setup: {}", setup_script.trim_end())?;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preview::describe;
+    use crate::ExternalHookRepo;
+
+    #[test]
+    fn test_describe() {
+        let mut repo: ExternalHookRepo = serde_yaml::from_str(
+            r#"
+setup: ./install.sh
+hooks:
+  - name: shellcheck
+    description: Lints the shell scripts
+    on_file_regex: ['\.sh$']
+    action: shellcheck {changed_files}
+  - name: commit-lint
+    on_event: [commit-msg]
+    action: ["lint.sh {args}", echo done]
+    language: node
+    setup_script: npm ci
+"#,
+        )
+        .unwrap();
+        repo.url = "https://example.com/lints".to_string();
+        repo.version = Some("v1.2.0".to_string());
+        assert_eq!(
+            describe(&repo).unwrap(),
+            r#"https://example.com/lints at v1.2.0
+setup: ./install.sh
+hooks:
+  shellcheck
+    Lints the shell scripts
+    events: the configuration's default ones
+    files: \.sh$
+    runs: shellcheck {changed_files}
+  commit-lint
+    events: commit-msg
+    runs: lint.sh {args} && echo done
+    language: node
+    setup: npm ci
+"#
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Read-only checkouts of the hook repos: once a repo is set up, its files cannot be written, so
+//! that a buggy or compromised hook cannot quietly rewrite the scripts the next commits run. What
+//! they hold once set up is recorded out of the checkout, for rewrites to be caught anyway.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::error::GitHooksError;
+use crate::utils::{is_project_hooks_dir, is_writable, set_writable};
+
+/// What is recorded of a file of a checkout: its sha256, or for a symlink where it points to, and
+/// its metadata when it was hashed, not to hash it again as long as they match. They include the
+/// time of its last change, which unlike its modification time, a hook cannot set back.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+struct Recorded {
+    hash: String,
+    stat: String,
+}
+
+/// Returns where what the checkout `dir` holds is recorded: next to it, where its hooks do not
+/// write
+fn record_path(dir: &str) -> String {
+    format!("{}.protected.yml", dir.trim_end_matches('/'))
+}
+
+/// Returns what is recorded of the checkout `dir`, if anything
+fn read_record(dir: &str) -> anyhow::Result<Option<BTreeMap<String, Recorded>>> {
+    match fs::read_to_string(record_path(dir)) {
+        Ok(record) => Ok(Some(serde_yaml::from_str(&record)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Returns the metadata of a file telling it changed: as git does for its index, its size, its
+/// inode, and the times of its last modification & change
+#[cfg(unix)]
+fn stat(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+    format!(
+        "{} {} {}.{} {}.{}",
+        metadata.len(),
+        metadata.ino(),
+        metadata.mtime(),
+        metadata.mtime_nsec(),
+        metadata.ctime(),
+        metadata.ctime_nsec()
+    )
+}
+
+#[cfg(not(unix))]
+fn stat(metadata: &fs::Metadata) -> String {
+    format!("{} {:?}", metadata.len(), metadata.modified().ok())
+}
+
+/// Records the files under `dir` but its git directory, relative to it. The ones `known` with the
+/// same metadata are not hashed again, eg. all the dependencies the setup installed.
+fn hash_files(
+    dir: &str,
+    known: &BTreeMap<String, Recorded>,
+) -> anyhow::Result<BTreeMap<String, Recorded>> {
+    let mut hashes = BTreeMap::new();
+    let mut hashed = 0;
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() != 1 || e.file_name() != ".git")
+    {
+        let entry = entry?;
+        if !entry.path_is_symlink() && !entry.file_type().is_file() {
+            continue;
+        }
+        let file = entry.path().strip_prefix(dir)?.display().to_string();
+        let stat = stat(&entry.metadata()?);
+        let hash = match known.get(&file) {
+            Some(recorded) if recorded.stat == stat => recorded.hash.clone(),
+            _ if entry.path_is_symlink() => {
+                format!("-> {}", fs::read_link(entry.path())?.display())
+            }
+            _ => {
+                hashed += 1;
+                format!("{:x}", Sha256::digest(&fs::read(entry.path())?))
+            }
+        };
+        hashes.insert(file, Recorded { hash, stat });
+    }
+    debug!("hashed {} of the {} files of {}", hashed, hashes.len(), dir);
+    Ok(hashes)
+}
+
+/// Makes the `files` of the hook repo checked out in `dir` read-only, the symlinks & the
+/// submodules left out, and records the content of all the files under it, for `check`. Called
+/// once checked out, then once set up, to record what its setup added. git still updates the
+/// read-only files, as it replaces them; the ones the setup adds are left writable, for it to run
+/// again.
+pub fn protect(dir: &str, files: &[String]) -> anyhow::Result<()> {
+    let mut protected = 0;
+    for file in files {
+        let path = Path::new(dir).join(file);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_file() => {}
+            _ => continue,
+        }
+        // made read-only again, it would be changed, and hashed again
+        if is_writable(&path)? {
+            set_writable(&path, false)?;
+            protected += 1;
+        }
+    }
+    let known = read_record(dir).unwrap_or_default().unwrap_or_default();
+    let hashes = hash_files(dir, &known)?;
+    debug!(
+        "made {} files of {} read-only, recorded {}",
+        protected,
+        dir,
+        hashes.len()
+    );
+    fs::write(record_path(dir), serde_yaml::to_string(&hashes)?)?;
+    Ok(())
+}
+
+/// Returns the files of the checkout `dir` which were modified, removed or added since `protect`
+/// recorded them, or `None` when it was never recorded
+pub fn modified_files(dir: &str) -> anyhow::Result<Option<Vec<String>>> {
+    let recorded = match read_record(dir)? {
+        Some(recorded) => recorded,
+        None => return Ok(None),
+    };
+    let current = hash_files(dir, &recorded)?;
+    let mut modified: Vec<String> = recorded
+        .iter()
+        .filter(|(file, r)| current.get(*file).map(|c| &c.hash) != Some(&r.hash))
+        .chain(current.iter().filter(|(f, _)| !recorded.contains_key(*f)))
+        .map(|(file, _)| file.clone())
+        .collect();
+    modified.sort();
+    Ok(Some(modified))
+}
+
+/// Removes the checkout `dir` when nothing records what it holds, as for the ones made before
+/// git-hooks recorded them, for it to be checked out and set up again from scratch, rather than
+/// trusted as it is
+pub fn discard_unrecorded(dir: &str) -> anyhow::Result<()> {
+    if is_project_hooks_dir(dir)
+        || !Path::new(dir).is_dir()
+        || Path::new(&record_path(dir)).exists()
+    {
+        return Ok(());
+    }
+    info!("nothing records what {} holds, checking it out again", dir);
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+/// Fails when the files of the hook repo `url`, checked out in `dir`, were tampered with since it
+/// was set up, or when nothing records them, though git-hooks always does once it is checked
+/// out. The repository's own hook repo is versioned with it instead.
+pub fn check(url: &str, dir: &str) -> anyhow::Result<()> {
+    if is_project_hooks_dir(dir) || !Path::new(dir).exists() {
+        return Ok(());
+    }
+    let what = match modified_files(dir)? {
+        Some(modified) if modified.is_empty() => return Ok(()),
+        Some(modified) => format!(
+            "the files of {} were modified since it was set up, maybe by a hook: {}",
+            url,
+            modified.join(", ")
+        ),
+        None => format!(
+            "nothing records what {} held once set up, maybe removed by a hook",
+            url
+        ),
+    };
+    Err(GitHooksError::Config(format!(
+        "{}. Check {}, then remove it for the repo to be checked out again",
+        what, dir
+    ))
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempdir::TempDir;
+
+    use crate::error::exit_code;
+    use crate::protect::{check, discard_unrecorded, modified_files, protect};
+    use crate::utils::{is_writable, set_mode};
+
+    #[test]
+    fn test_protect() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let checkout = dir.path().join("hooks");
+        let root = checkout.display().to_string();
+        // nothing to check before it is checked out
+        assert!(check("https://example.com/hooks", &root).is_ok());
+        fs::create_dir_all(checkout.join(".git")).unwrap();
+        fs::create_dir_all(checkout.join("scripts")).unwrap();
+        fs::write(checkout.join("hooks.yml"), "hooks: []\n").unwrap();
+        fs::write(checkout.join("scripts/check.sh"), "#!/bin/sh\n").unwrap();
+        set_mode(&checkout.join("scripts/check.sh"), 0o755).unwrap();
+        // a checkout nothing records was tampered with
+        let e = check("https://example.com/hooks", &root).unwrap_err();
+        assert_eq!(exit_code(&e), 2);
+        assert!(e
+            .to_string()
+            .starts_with("nothing records what https://example.com/hooks held once set up"));
+
+        let files = [
+            "hooks.yml".to_string(),
+            "scripts/check.sh".to_string(),
+            "scripts/removed.sh".to_string(),
+        ];
+        protect(&root, &files).unwrap();
+        assert!(!is_writable(&checkout.join("hooks.yml")).unwrap());
+        assert!(dir.path().join("hooks.protected.yml").is_file());
+        // what its setup adds is recorded as well, and left writable
+        fs::write(checkout.join("scripts/built"), "v1").unwrap();
+        protect(&root, &files).unwrap();
+        assert!(is_writable(&checkout.join("scripts/built")).unwrap());
+        assert!(check("https://example.com/hooks", &root).is_ok());
+        // the git directory is git's
+        fs::write(checkout.join(".git/HEAD"), "abc").unwrap();
+        assert!(check("https://example.com/hooks", &root).is_ok());
+
+        // rewritten, whatever its permissions
+        set_mode(&checkout.join("scripts/check.sh"), 0o755).unwrap();
+        fs::write(checkout.join("scripts/check.sh"), "#!/bin/sh\nrm -rf ~\n").unwrap();
+        set_mode(&checkout.join("scripts/check.sh"), 0o555).unwrap();
+        fs::write(checkout.join("scripts/built"), "v2").unwrap();
+        fs::remove_file(checkout.join("hooks.yml")).unwrap();
+        fs::write(checkout.join("scripts/added.sh"), "").unwrap();
+        assert_eq!(
+            modified_files(&root).unwrap().unwrap(),
+            [
+                "hooks.yml",
+                "scripts/added.sh",
+                "scripts/built",
+                "scripts/check.sh"
+            ]
+        );
+        let e = check("https://example.com/hooks", &root).unwrap_err();
+        assert_eq!(exit_code(&e), 2);
+        assert!(e.to_string().starts_with(
+            "the files of https://example.com/hooks were modified since it was set up, maybe by a hook: hooks.yml, scripts/added.sh, scripts/built, scripts/check.sh."
+        ));
+
+        // protecting it again, eg. once updated, records it as it is
+        protect(&root, &files[1..]).unwrap();
+        assert_eq!(modified_files(&root).unwrap(), Some(vec![]));
+        // rewritten with the same size & modification time
+        let script = checkout.join("scripts/check.sh");
+        let modified = fs::metadata(&script).unwrap().modified().unwrap();
+        set_mode(&script, 0o755).unwrap();
+        fs::write(&script, "#!/bin/sh\nrm -rf /\n").unwrap();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&script)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        set_mode(&script, 0o555).unwrap();
+        assert_eq!(
+            modified_files(&root).unwrap().unwrap(),
+            ["scripts/check.sh"]
+        );
+        protect(&root, &files[1..]).unwrap();
+
+        // a record removed is no pass
+        fs::remove_file(dir.path().join("hooks.protected.yml")).unwrap();
+        assert!(check("https://example.com/hooks", &root).is_err());
+        // but the checkout is made again from scratch before it is used
+        discard_unrecorded(&root).unwrap();
+        assert!(!checkout.exists());
+        assert!(check("https://example.com/hooks", &root).is_ok());
+    }
+}
@@ -0,0 +1,157 @@
+//! `git-hooks search`: an index of the known hook repos, their hooks & latest tags.
+use std::env;
+use std::fs;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::error::GitHooksError;
+
+/// The index of the community hook repos
+pub const DEFAULT_INDEX_URL: &str =
+    "https://raw.githubusercontent.com/paulollivier/git-hooks/master/index.json";
+
+/// The list of the known hook repos.
+///
+/// ```json
+/// {
+///   "repos": [
+///     {
+///       "name": "rust-hooks",
+///       "url": "https://github.com/paulollivier/rust-hooks",
+///       "description": "rustfmt, clippy & cargo check",
+///       "latest": "v1.2.0",
+///       "hooks": [{"name": "rustfmt", "description": "Formats the staged rust files"}]
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct Index {
+    pub repos: Vec<IndexedRepo>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct IndexedRepo {
+    /// Short name of the repo, which `git-hooks add repo` accepts instead of its URL
+    pub name: String,
+    pub url: String,
+    pub description: Option<String>,
+    /// The latest tag of the repo, which projects should pin
+    pub latest: Option<String>,
+    pub hooks: Vec<IndexedHook>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct IndexedHook {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Returns where the index is read from: `index` if given, else `$GIT_HOOKS_INDEX`, eg. for an
+/// enterprise one, else the community index
+pub fn index_location(index: Option<&str>) -> String {
+    index
+        .map(|i| i.to_string())
+        .or_else(|| env::var("GIT_HOOKS_INDEX").ok().filter(|i| !i.is_empty()))
+        .unwrap_or_else(|| DEFAULT_INDEX_URL.to_string())
+}
+
+impl Index {
+    /// Reads the index at `location`: an HTTPS URL, or a local file
+    pub fn fetch(location: &str) -> anyhow::Result<Index> {
+        debug!("reading the hook repos index {}", location);
+        let content = if location.starts_with("https://") || location.starts_with("http://") {
+            reqwest::blocking::get(location)
+                .and_then(|r| r.error_for_status()?.text())
+                .map_err(|e| {
+                    GitHooksError::Network(format!("could not read the index {}: {}", location, e))
+                })?
+        } else {
+            fs::read_to_string(location).map_err(|e| {
+                GitHooksError::Config(format!("could not read the index {}: {}", location, e))
+            })?
+        };
+        serde_json::from_str(&content).map_err(|e| {
+            GitHooksError::Config(format!("invalid hook repos index {}: {}", location, e)).into()
+        })
+    }
+
+    /// Returns the repos whose name, URL, description or hooks mention `term`, ignoring case
+    pub fn search(&self, term: &str) -> Vec<&IndexedRepo> {
+        let term = term.to_lowercase();
+        let mentions = |text: &str| text.to_lowercase().contains(&term);
+        self.repos
+            .iter()
+            .filter(|repo| {
+                mentions(&repo.name)
+                    || mentions(&repo.url)
+                    || repo.description.as_deref().is_some_and(mentions)
+                    || repo.hooks.iter().any(|hook| {
+                        mentions(&hook.name) || hook.description.as_deref().is_some_and(mentions)
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns the repo named `name`
+    pub fn find(&self, name: &str) -> Option<&IndexedRepo> {
+        self.repos.iter().find(|repo| repo.name == name)
+    }
+}
+
+/// Tells whether `repo`, given to `git-hooks add repo`, is the name of an indexed repo rather
+/// than a git URL or path
+pub fn is_repo_name(repo: &str) -> bool {
+    !repo.is_empty() && !repo.contains(['/', ':', '\\', '@'])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::registry::{is_repo_name, Index};
+
+    #[test]
+    fn test_search() {
+        let index: Index = serde_json::from_str(
+            r#"{
+  "repos": [
+    {
+      "name": "rust-hooks",
+      "url": "https://github.com/paulollivier/rust-hooks",
+      "latest": "v1.2.0",
+      "hooks": [{"name": "rustfmt", "description": "Formats the staged Rust files"}]
+    },
+    {
+      "name": "py-hooks",
+      "url": "https://example.com/py-hooks",
+      "description": "black & flake8",
+      "hooks": [{"name": "black"}]
+    }
+  ]
+}"#,
+        )
+        .unwrap();
+        let names = |term| {
+            index
+                .search(term)
+                .iter()
+                .map(|r| r.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("rust"), vec!["rust-hooks"]);
+        assert_eq!(names("FLAKE8"), vec!["py-hooks"]);
+        assert_eq!(names("hooks").len(), 2);
+        assert!(names("go").is_empty());
+        assert_eq!(
+            index.find("rust-hooks").unwrap().latest.as_deref(),
+            Some("v1.2.0")
+        );
+        assert!(is_repo_name("rust-hooks"));
+        assert!(!is_repo_name("https://github.com/paulollivier/rust-hooks"));
+        assert!(!is_repo_name("git@github.com:paulollivier/rust-hooks.git"));
+        assert!(!is_repo_name("../rust-hooks"));
+    }
+}
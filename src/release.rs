@@ -0,0 +1,270 @@
+//! The binaries published with the releases of `git-hooks`, that `git-hooks self-update`
+//! downloads, along with their SHA-256 & optionally their minisign signature.
+use std::env::consts::{ARCH, OS};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use log::debug;
+use sha2::{Digest, Sha256};
+
+use crate::utils::{execute_cmd, set_mode};
+
+/// Suffix of the asset holding the SHA-256 of a binary, as written by `sha256sum`
+pub const CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// Suffix of the asset holding the minisign signature of a binary
+pub const SIGNATURE_SUFFIX: &str = ".minisig";
+
+/// The git option holding the minisign public key the downloaded binaries must be signed with
+pub const PUBLIC_KEY_OPTION: &str = "git-hooks.updatePublicKey";
+
+/// The releases `git-hooks self-update` picks from
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Default)]
+pub enum Channel {
+    /// The latest release, not a pre-release
+    #[default]
+    Stable,
+    /// The latest release, pre-releases included
+    Prerelease,
+}
+
+impl FromStr for Channel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(Channel::Stable),
+            "prerelease" => Ok(Channel::Prerelease),
+            _ => Err(anyhow::Error::msg(format!("unknown release channel {}", s))),
+        }
+    }
+}
+
+/// A file published with a release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Asset {
+    pub name: String,
+    pub url: String,
+}
+
+/// Returns the name of the release asset built for `os` & `arch`, named as in
+/// `std::env::consts`: `git-hooks-<os>-<arch>`, with `amd64` & `arm64` for the architectures
+pub fn asset_name(os: &str, arch: &str) -> Option<String> {
+    let os = match os {
+        "linux" | "macos" | "windows" => os,
+        _ => return None,
+    };
+    let arch = match arch {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        _ => return None,
+    };
+    let extension = if os == "windows" { ".exe" } else { "" };
+    Some(format!("git-hooks-{}-{}{}", os, arch, extension))
+}
+
+/// Returns the name of the release asset of the platform `git-hooks` runs on
+pub fn current_asset() -> anyhow::Result<String> {
+    asset_name(OS, ARCH).ok_or_else(|| {
+        anyhow::Error::msg(format!(
+            "no git-hooks binary is released for {} on {}: install it with `cargo install git-hooks-manager`",
+            OS, ARCH
+        ))
+    })
+}
+
+/// Returns the binary `asset` among the `assets` of a release, `version`
+pub fn find_asset<'a>(
+    version: &str,
+    assets: &'a [Asset],
+    asset: &str,
+) -> anyhow::Result<&'a Asset> {
+    if let Some(found) = assets.iter().find(|a| a.name == asset) {
+        return Ok(found);
+    }
+    let names: Vec<&str> = assets.iter().map(|a| a.name.as_str()).collect();
+    Err(anyhow::Error::msg(format!(
+        "the release {} has no {} binary, only {}: install it with `cargo install git-hooks-manager`",
+        version,
+        asset,
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(", ")
+        }
+    )))
+}
+
+/// Downloads the binary `asset` of a release, `version`, in `dir`, and checks it against the
+/// SHA-256 published along, then against its signature if `public_key` is given. Returns the
+/// path of the binary, executable, only once it passed the checks.
+pub fn download_verified(
+    version: &str,
+    assets: &[Asset],
+    asset: &str,
+    dir: &Path,
+    public_key: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    let find = |name: &str| assets.iter().find(|a| a.name == name);
+    let binary = dir.join(asset);
+    download(&find_asset(version, assets, asset)?.url, &binary)?;
+    let checksums_name = format!("{}{}", asset, CHECKSUM_SUFFIX);
+    let checksums = find(&checksums_name).ok_or_else(|| {
+        anyhow::Error::msg(format!(
+            "the release {} publishes no {}: cannot check the downloaded binary",
+            version, checksums_name
+        ))
+    })?;
+    let checksums_path = dir.join(&checksums_name);
+    download(&checksums.url, &checksums_path)?;
+    verify_checksum(&binary, &fs::read_to_string(&checksums_path)?, asset)?;
+    if let Some(public_key) = public_key {
+        let signature_name = format!("{}{}", asset, SIGNATURE_SUFFIX);
+        let signature = find(&signature_name).ok_or_else(|| {
+            anyhow::Error::msg(format!(
+                "the release {} publishes no {}, while {} is set",
+                version, signature_name, PUBLIC_KEY_OPTION
+            ))
+        })?;
+        let signature_path = dir.join(&signature_name);
+        download(&signature.url, &signature_path)?;
+        verify_signature(&binary, &signature_path, public_key)?;
+    }
+    set_mode(&binary, 0o755)?;
+    Ok(binary)
+}
+
+/// Downloads `url` to `to`. The URLs of the GitHub API serve the assets' content when asked for
+/// it.
+fn download(url: &str, to: &Path) -> anyhow::Result<()> {
+    debug!("downloading {} to {}", url, to.display());
+    let content = reqwest::blocking::Client::new()
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/octet-stream")
+        .header(reqwest::header::USER_AGENT, "git-hooks")
+        .send()?
+        .error_for_status()?
+        .bytes()?;
+    fs::write(to, &content)?;
+    Ok(())
+}
+
+/// Checks that the SHA-256 of `binary` is the one of `asset` in `checksums`, as written by
+/// `sha256sum`. A lone checksum, without any file name, is the binary's.
+pub fn verify_checksum(binary: &Path, checksums: &str, asset: &str) -> anyhow::Result<()> {
+    let expected = checksums
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let checksum = words.next()?;
+            match words.next() {
+                // `*` marks the files read in binary mode
+                Some(name) if name.trim_start_matches('*') != asset => None,
+                _ => Some(checksum.to_lowercase()),
+            }
+        })
+        .next()
+        .ok_or_else(|| anyhow::Error::msg(format!("no SHA-256 of {} is published", asset)))?;
+    let actual = format!("{:x}", Sha256::digest(&fs::read(binary)?));
+    if actual != expected {
+        return Err(anyhow::Error::msg(format!(
+            "the SHA-256 of the downloaded {} is {}, not the published {}: not installing it",
+            asset, actual, expected
+        )));
+    }
+    Ok(())
+}
+
+/// Checks the minisign `signature` of `binary` with `public_key`, through the `minisign`
+/// program
+fn verify_signature(binary: &Path, signature: &Path, public_key: &str) -> anyhow::Result<()> {
+    let binary = binary.display().to_string();
+    let signature = signature.display().to_string();
+    execute_cmd(
+        "minisign",
+        &["-V", "-P", public_key, "-m", &binary, "-x", &signature],
+        None,
+        None,
+    )
+    .map_err(|e| {
+        anyhow::Error::msg(format!(
+            "could not check the signature of {} with minisign, which must be installed: {}",
+            binary, e
+        ))
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempdir::TempDir;
+
+    use crate::release::{asset_name, find_asset, verify_checksum, Asset, Channel};
+
+    #[test]
+    fn test_asset_name() {
+        assert_eq!(
+            asset_name("linux", "x86_64").as_deref(),
+            Some("git-hooks-linux-amd64")
+        );
+        assert_eq!(
+            asset_name("macos", "aarch64").as_deref(),
+            Some("git-hooks-macos-arm64")
+        );
+        assert_eq!(
+            asset_name("windows", "x86_64").as_deref(),
+            Some("git-hooks-windows-amd64.exe")
+        );
+        assert_eq!(asset_name("freebsd", "x86_64"), None);
+        assert_eq!(asset_name("linux", "riscv64"), None);
+    }
+
+    #[test]
+    fn test_channel() {
+        assert_eq!("stable".parse::<Channel>().unwrap(), Channel::Stable);
+        assert_eq!(
+            "prerelease".parse::<Channel>().unwrap(),
+            Channel::Prerelease
+        );
+        assert!("nightly".parse::<Channel>().is_err());
+    }
+
+    #[test]
+    fn test_find_asset() {
+        let assets = vec![Asset {
+            name: "git-hooks-linux-amd64".to_string(),
+            url: "https://example.com/git-hooks-linux-amd64".to_string(),
+        }];
+        assert_eq!(
+            find_asset("v0.7.0", &assets, "git-hooks-linux-amd64").unwrap(),
+            &assets[0]
+        );
+        let r = find_asset("v0.7.0", &assets, "git-hooks-macos-arm64");
+        assert_eq!(
+            r.unwrap_err().to_string(),
+            "the release v0.7.0 has no git-hooks-macos-arm64 binary, only git-hooks-linux-amd64: install it with `cargo install git-hooks-manager`"
+        );
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let binary = dir.path().join("git-hooks-linux-amd64");
+        fs::write(&binary, "hello\n").unwrap();
+        let checksum = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+        let published = format!(
+            "{}  git-hooks-macos-arm64\n{} *git-hooks-linux-amd64\n",
+            "0".repeat(64),
+            checksum.to_uppercase()
+        );
+        assert!(verify_checksum(&binary, &published, "git-hooks-linux-amd64").is_ok());
+        assert!(verify_checksum(&binary, checksum, "git-hooks-linux-amd64").is_ok());
+        let r = verify_checksum(&binary, &"0".repeat(64), "git-hooks-linux-amd64");
+        assert!(r.unwrap_err().to_string().contains("not installing it"));
+        let r = verify_checksum(&binary, &published, "git-hooks-windows-amd64.exe");
+        assert!(r.is_err());
+    }
+}
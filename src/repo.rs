@@ -0,0 +1,488 @@
+//! `git-hooks repo`: tools for the authors of hook repos.
+use std::env;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::Deserialize;
+use shlex::Shlex;
+
+use crate::context::RunContext;
+use crate::git;
+use crate::language::Language;
+use crate::requires::Requirement;
+use crate::utils::{is_executable, is_program_in_path, set_mode};
+use crate::{run_hook, ExternalHookRepo, Hook, HookEvent};
+
+/// The manifest of a hook repo, at its root
+pub const MANIFEST: &str = "hooks.yml";
+
+/// The test cases of a hook repo, relative to its root
+pub const TEST_CASES: &str = "tests/cases.yml";
+
+/// Where the files of the test cases are, relative to the root of a hook repo
+const TEST_FIXTURES: &str = "tests/fixtures";
+
+/// The settings a manifest may have
+const MANIFEST_KEYS: &[&str] = &["setup", "tools", "hooks"];
+
+/// The settings a hook of a manifest may have
+const HOOK_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "on_event",
+    "on_file_regex",
+    "action",
+    "setup_script",
+    "image",
+    "language",
+    "dependencies",
+    "nix",
+    "tools",
+    "settings",
+    "script",
+    "wasm",
+    "root",
+    "no_network",
+    "file_source",
+    "requires",
+    "verbose",
+    "lock",
+];
+
+/// The files of a new hook repo: their path, content, and whether they are executable
+const SKELETON: &[(&str, &str, bool)] = &[
+    ("hooks.yml", include_str!("repo_template/hooks.yml"), false),
+    ("setup.sh", include_str!("repo_template/setup.sh"), true),
+    ("check.sh", include_str!("repo_template/check.sh"), true),
+    (
+        "tests/smoke.sh",
+        include_str!("repo_template/smoke_test.sh"),
+        true,
+    ),
+    (
+        "tests/cases.yml",
+        include_str!("repo_template/cases.yml"),
+        false,
+    ),
+    (
+        "tests/fixtures/clean.txt",
+        include_str!("repo_template/fixtures/clean.txt"),
+        false,
+    ),
+    (
+        "tests/fixtures/dirty.txt",
+        include_str!("repo_template/fixtures/dirty.txt"),
+        false,
+    ),
+];
+
+/// Creates the skeleton of a hook repo in `dir`, as a new git repository: a `hooks.yml` defining
+/// an example hook, the script it runs, a setup script stub, a smoke test of the script, and test
+/// cases of the hook.
+/// `dir` may exist, as long as it is empty. Returns the paths of the files written.
+pub fn new(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if dir.exists() && std::fs::read_dir(dir)?.next().is_some() {
+        return Err(anyhow::Error::msg(format!(
+            "{} is not empty",
+            dir.display()
+        )));
+    }
+    let mut written = Vec::new();
+    for (file, content, executable) in SKELETON {
+        let path = dir.join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content)?;
+        if *executable {
+            set_mode(&path, 0o755)?;
+        }
+        written.push(path);
+    }
+    git::init(Some(&dir.display().to_string()))?;
+    Ok(written)
+}
+
+/// Checks the hook repo in `dir`, for its CI: its `hooks.yml` must only have known settings, valid
+/// events & regexes, and the scripts of the repo its hooks run must exist and be executable.
+/// Returns the problems found.
+pub fn lint(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let path = dir.join(MANIFEST);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::Error::msg(format!("could not read {}: {}", path.display(), e)))?;
+    let manifest: serde_yaml::Mapping = match serde_yaml::from_str(&content) {
+        Ok(manifest) => manifest,
+        Err(e) => return Ok(vec![format!("{}: {}", MANIFEST, e)]),
+    };
+    let mut problems = unknown_keys(&manifest, MANIFEST_KEYS, MANIFEST);
+    if let Some(setup) = manifest.get(&"setup".into()) {
+        match setup.as_str() {
+            Some(setup) => problems.extend(check_script(dir, setup, "setup")),
+            None => problems.push("setup: must be the path of a script".to_string()),
+        }
+    }
+    if let Some(tools) = manifest.get(&"tools".into()) {
+        if let Err(e) = serde_yaml::from_value::<crate::toolchain::Tools>(tools.clone()) {
+            problems.push(format!("tools: {}", e));
+        }
+    }
+    let hooks = match manifest.get(&"hooks".into()).map(|h| h.as_sequence()) {
+        Some(Some(hooks)) if !hooks.is_empty() => hooks,
+        _ => {
+            problems.push(format!("{}: defines no hooks", MANIFEST));
+            return Ok(problems);
+        }
+    };
+    let mut names: Vec<String> = Vec::new();
+    for (i, value) in hooks.iter().enumerate() {
+        let hook: Hook = match serde_yaml::from_value(value.clone()) {
+            Ok(hook) => hook,
+            Err(e) => {
+                problems.push(format!("hook #{}: {}", i + 1, e));
+                continue;
+            }
+        };
+        let name = if hook.name.is_empty() {
+            problems.push(format!("hook #{}: has no name", i + 1));
+            format!("hook #{}", i + 1)
+        } else {
+            hook.name.clone()
+        };
+        if names.contains(&name) {
+            problems.push(format!("{}: defined twice", name));
+        }
+        names.push(name.clone());
+        if let Some(mapping) = value.as_mapping() {
+            problems.extend(unknown_keys(mapping, HOOK_KEYS, &name));
+        }
+        problems.extend(lint_hook(dir, &hook, &name));
+    }
+    Ok(problems)
+}
+
+/// Checks the settings of the hook `name` of the repo in `dir`
+fn lint_hook(dir: &Path, hook: &Hook, name: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    if hook
+        .on_event
+        .as_ref()
+        .is_some_and(|events| events.is_empty())
+    {
+        problems.push(format!("{}: runs on no event", name));
+    }
+    for regex in hook.on_file_regex.iter().flatten() {
+        if let Err(e) = Regex::new(regex) {
+            problems.push(format!("{}: invalid regex {}: {}", name, regex, e));
+        }
+    }
+    for requirement in hook.requires.iter().flatten() {
+        if let Err(e) = requirement.parse::<Requirement>() {
+            problems.push(format!("{}: {}", name, e));
+        }
+    }
+    match (&hook.action, &hook.script) {
+        (None, None) => problems.push(format!("{}: has no action", name)),
+        (Some(_), Some(_)) => problems.push(format!("{}: has both an action and a script", name)),
+        _ => {}
+    }
+    for command in hook.action.iter().flat_map(|a| a.commands()) {
+        match Shlex::new(command).next() {
+            Some(program) => problems.extend(check_program(dir, hook, name, &program)),
+            None => problems.push(format!("{}: empty action", name)),
+        }
+    }
+    if let Some(setup_script) = &hook.setup_script {
+        problems.extend(check_script(dir, setup_script, name));
+    }
+    if let Some(wasm) = &hook.wasm {
+        if !dir.join(wasm).is_file() {
+            problems.push(format!("{}: {} is not in the repo", name, wasm));
+        }
+    }
+    if let Some(nix) = &hook.nix {
+        if nix.ends_with(".nix") && !dir.join(nix).is_file() {
+            problems.push(format!("{}: {} is not in the repo", name, nix));
+        }
+    }
+    if hook.image.is_some() && hook.nix.is_some() {
+        problems.push(format!("{}: image and nix cannot be used together", name));
+    }
+    problems
+}
+
+/// Checks that `program`, run by the action of the hook `name`, can be found: in the repo, where
+/// it must be executable, or else in the `PATH` unless the hook runs in an environment of its own
+fn check_program(dir: &Path, hook: &Hook, name: &str, program: &str) -> Vec<String> {
+    if dir.join(program).exists() || program.contains('/') {
+        return check_script(dir, program, name);
+    }
+    let own_environment = hook.image.is_some()
+        || hook.nix.is_some()
+        || hook.wasm.is_some()
+        || hook.tools.is_some()
+        || hook.language.unwrap_or_default() != Language::System;
+    if own_environment || is_program_in_path(program) {
+        vec![]
+    } else {
+        vec![format!(
+            "{}: {} is neither in the repo nor in the PATH",
+            name, program
+        )]
+    }
+}
+
+/// Checks that the script `script` of the repo in `dir`, used by `name`, exists and is executable
+fn check_script(dir: &Path, script: &str, name: &str) -> Vec<String> {
+    let path = dir.join(script);
+    if !path.is_file() {
+        return vec![format!("{}: {} is not in the repo", name, script)];
+    }
+    match is_executable(&path) {
+        Ok(true) => vec![],
+        Ok(false) => vec![format!("{}: {} is not executable", name, script)],
+        Err(e) => vec![format!("{}: could not check {}: {}", name, script, e)],
+    }
+}
+
+/// A test case of a hook repo: a hook, run on files staged in a new repository
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    /// Describes the case. Defaults to the hook and the files.
+    pub name: Option<String>,
+    pub hook: String,
+    /// The files to stage, copied from `tests/fixtures` at the same relative path
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// The event to run the hook for. Defaults to the first one it runs on.
+    pub event: Option<HookEvent>,
+    /// The commit message, given in a file to the commit message events
+    pub message: Option<String>,
+    pub expect: Expectation,
+}
+
+/// Whether a hook is expected to let the event go on
+#[derive(Deserialize, Debug, Eq, PartialEq, Copy, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum Expectation {
+    Pass,
+    Fail,
+}
+
+/// The outcome of a test case
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    /// What went wrong, if the case failed
+    pub detail: Option<String>,
+}
+
+/// Runs the test cases of the hook repo in `dir`, from its `tests/cases.yml`: each one stages its
+/// files in a new repository, then runs its hook there, which must pass or fail as expected.
+/// The repo is set up first, `force_setup` rerunning its setup scripts.
+pub fn test(dir: &Path, force_setup: bool) -> anyhow::Result<Vec<TestResult>> {
+    let dir = dir.canonicalize()?;
+    let repo_dir = dir.display().to_string();
+    let path = dir.join(TEST_CASES);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::Error::msg(format!("could not read {}: {}", path.display(), e)))?;
+    let cases: Vec<TestCase> = serde_yaml::from_str(&content)
+        .map_err(|e| anyhow::Error::msg(format!("{}: {}", TEST_CASES, e)))?;
+    let mut repo = ExternalHookRepo::default();
+    repo.read_manifest(&repo_dir)?;
+    repo.setup_in(&repo_dir, force_setup)?;
+    let old_dir = env::current_dir()?;
+    let mut results = Vec::new();
+    for (i, case) in cases.iter().enumerate() {
+        let name = case
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("{} on {}", case.hook, case.files.join(", ")));
+        let hook = match repo.hooks.iter().find(|h| h.name == case.hook) {
+            Some(hook) => hook,
+            None => {
+                results.push(TestResult {
+                    name,
+                    passed: false,
+                    detail: Some(format!("{} is not defined in {}", case.hook, MANIFEST)),
+                });
+                continue;
+            }
+        };
+        let work_dir =
+            env::temp_dir().join(format!("git-hooks-test-{}-{}", std::process::id(), i + 1));
+        let outcome = run_case(&dir, hook, case, &work_dir);
+        env::set_current_dir(&old_dir)?;
+        let _ = std::fs::remove_dir_all(&work_dir);
+        results.push(match (outcome?, case.expect) {
+            (Ok(_), Expectation::Pass) | (Err(_), Expectation::Fail) => TestResult {
+                name,
+                passed: true,
+                detail: None,
+            },
+            (Ok(output), Expectation::Fail) => TestResult {
+                name,
+                passed: false,
+                detail: Some(format!("expected to fail, but passed\n{}", output)),
+            },
+            (Err(e), Expectation::Pass) => TestResult {
+                name,
+                passed: false,
+                detail: Some(format!("expected to pass, but failed: {}", e)),
+            },
+        });
+    }
+    Ok(results)
+}
+
+/// Runs `hook` of the repo in `dir` for `case`, in a new repository in `work_dir`.
+/// Fails if the repository could not be prepared, and returns the outcome of the hook otherwise.
+fn run_case(
+    dir: &Path,
+    hook: &Hook,
+    case: &TestCase,
+    work_dir: &Path,
+) -> anyhow::Result<anyhow::Result<String>> {
+    std::fs::create_dir_all(work_dir)?;
+    let work_dir = work_dir.display().to_string();
+    git::init(Some(&work_dir))?;
+    for file in &case.files {
+        let target = Path::new(&work_dir).join(file);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(dir.join(TEST_FIXTURES).join(file), &target).map_err(|e| {
+            anyhow::Error::msg(format!("could not copy the fixture {}: {}", file, e))
+        })?;
+    }
+    env::set_current_dir(&work_dir)?;
+    if !case.files.is_empty() {
+        git::add(&case.files)?;
+    }
+    let event = case.event.unwrap_or_else(|| {
+        hook.on_event
+            .as_ref()
+            .and_then(|events| events.first().copied())
+            .unwrap_or(HookEvent::PreCommit)
+    });
+    let mut args = Vec::new();
+    if let Some(message) = &case.message {
+        let message_file = Path::new(&work_dir).join(".git").join("COMMIT_EDITMSG");
+        std::fs::write(&message_file, message)?;
+        args.push(message_file.display().to_string());
+    }
+    let ctx = RunContext {
+        event: Some(event),
+        args,
+        changed_files: git::changed_files(true)?,
+        ..Default::default()
+    };
+    Ok(run_hook(hook, &dir.display().to_string(), &ctx))
+}
+
+/// Returns the keys of `mapping` which are not among `known`, as problems of `name`
+fn unknown_keys(mapping: &serde_yaml::Mapping, known: &[&str], name: &str) -> Vec<String> {
+    mapping
+        .iter()
+        .filter_map(|(key, _)| match key.as_str() {
+            Some(key) if known.contains(&key) => None,
+            Some(key) => Some(format!("{}: unknown setting {}", name, key)),
+            None => Some(format!("{}: settings must be named by strings", name)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::repo::{lint, new, test, TEST_CASES};
+    use crate::utils::{execute_cmd, is_executable, set_mode};
+    use crate::ExternalHookRepo;
+
+    #[test]
+    fn test_new() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let repo = dir.path().join("my-hooks");
+        let written = new(&repo).unwrap();
+        assert_eq!(written.len(), 7);
+        assert!(repo.join(".git").is_dir());
+        let manifest: ExternalHookRepo =
+            serde_yaml::from_str(&std::fs::read_to_string(repo.join("hooks.yml")).unwrap())
+                .unwrap();
+        assert_eq!(manifest.hooks[0].name, "no-do-not-commit");
+        assert!(is_executable(&repo.join("check.sh")).unwrap());
+        let root = repo.display().to_string();
+        let smoke_test = repo.join("tests/smoke.sh").display().to_string();
+        let (status, _, _) = execute_cmd(&smoke_test, &[] as &[&str], Some(&root), None).unwrap();
+        assert!(status.success());
+        assert!(new(&repo).is_err());
+    }
+
+    #[test]
+    fn test_lint() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let repo = dir.path().join("my-hooks");
+        new(&repo).unwrap();
+        assert_eq!(lint(&repo).unwrap(), Vec::<String>::new());
+        std::fs::write(
+            repo.join("hooks.yml"),
+            r#"
+setup: setup.sh
+hooks:
+  - name: fmt
+    on_events: [pre-commit]
+    on_file_regex: ['(']
+    action: check.sh {files}
+    setup_script: missing.sh
+  - name: fmt
+    action: definitely-not-a-program-on-the-path
+  - name: typo
+    on_event: [pre-comit]
+"#,
+        )
+        .unwrap();
+        set_mode(&repo.join("setup.sh"), 0o644).unwrap();
+        let problems = lint(&repo).unwrap();
+        assert_eq!(problems.len(), 7);
+        assert_eq!(problems[0], "setup: setup.sh is not executable");
+        assert_eq!(problems[1], "fmt: unknown setting on_events");
+        assert!(problems[2].starts_with("fmt: invalid regex (:"));
+        assert_eq!(problems[3], "fmt: missing.sh is not in the repo");
+        assert_eq!(problems[4], "fmt: defined twice");
+        assert_eq!(
+            problems[5],
+            "fmt: definitely-not-a-program-on-the-path is neither in the repo nor in the PATH"
+        );
+        assert!(problems[6].starts_with("hook #3: unknown variant `pre-comit`"));
+    }
+
+    #[test]
+    fn test_test() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let repo = dir.path().join("my-hooks");
+        new(&repo).unwrap();
+        let results = test(&repo, false).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.passed), "{:?}", results);
+        std::fs::write(
+            repo.join(TEST_CASES),
+            r#"
+- hook: no-do-not-commit
+  files: [dirty.txt]
+  expect: pass
+- hook: missing
+  expect: pass
+"#,
+        )
+        .unwrap();
+        let results = test(&repo, false).unwrap();
+        assert_eq!(results[0].name, "no-do-not-commit on dirty.txt");
+        assert!(!results[0].passed);
+        assert_eq!(
+            results[1].detail.as_deref(),
+            Some("missing is not defined in hooks.yml")
+        );
+    }
+}
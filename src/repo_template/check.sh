@@ -0,0 +1,10 @@
+#!/bin/sh
+# Fails if any of the files given as arguments holds "DO NOT COMMIT"
+status=0
+for file in "$@"; do
+    if grep -n "DO NOT COMMIT" "$file"; then
+        echo "$file: holds DO NOT COMMIT" >&2
+        status=1
+    fi
+done
+exit $status
@@ -0,0 +1,4 @@
+#!/bin/sh
+# Runs once per revision of the repository, from its root, before the hooks run:
+# install what the hooks need here.
+set -e
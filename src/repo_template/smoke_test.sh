@@ -0,0 +1,15 @@
+#!/bin/sh
+# Checks that the hook passes on a clean file, and fails on a file holding the marker.
+# Run from the root of the repository: tests/smoke.sh
+set -e
+repo=$(pwd)
+dir=$(mktemp -d)
+trap 'rm -rf "$dir"' EXIT
+echo "all good" > "$dir/clean.txt"
+echo "DO NOT COMMIT" > "$dir/dirty.txt"
+"$repo/check.sh" "$dir/clean.txt" > /dev/null
+if "$repo/check.sh" "$dir/dirty.txt" > /dev/null 2>&1; then
+    echo "check.sh passed on a file holding DO NOT COMMIT" >&2
+    exit 1
+fi
+echo "smoke test passed"
@@ -0,0 +1,267 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommandFailed;
+use crate::{git, Hook, HookEvent};
+
+/// Where the reports of the last runs are kept, relative to the git directory
+const LAST_RUNS_LOCATION: &str = "git-hooks/last-runs";
+
+/// How a hook's run went
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", tag = "status", content = "reason")]
+pub enum HookStatus {
+    Passed,
+    /// The hook ran, and failed for the given reason
+    Failed(String),
+    /// The hook did not run, for the given reason
+    Skipped(String),
+}
+
+/// The result of one hook of a run
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct HookResult {
+    pub name: String,
+    #[serde(flatten)]
+    pub status: HookStatus,
+    pub duration: Duration,
+    /// What the hook printed, stdout then stderr
+    pub output: String,
+    /// What the hook checks, from its definition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The results of all the hooks run for an event, in the order they ran
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RunReport {
+    pub event: HookEvent,
+    pub results: Vec<HookResult>,
+}
+
+impl RunReport {
+    pub fn new(event: HookEvent) -> RunReport {
+        RunReport {
+            event,
+            results: Vec::new(),
+        }
+    }
+
+    /// Records the outcome of running `name`, `output` being what it printed if it succeeded, else
+    /// what the command failing printed, if any. The outcome of a hook run again, to fix files,
+    /// replaces the previous one.
+    pub fn record(&mut self, name: &str, duration: Duration, outcome: anyhow::Result<String>) {
+        let (status, output) = match outcome {
+            Ok(output) => (HookStatus::Passed, output),
+            Err(e) => {
+                let output = e
+                    .chain()
+                    .find_map(|cause| cause.downcast_ref::<CommandFailed>())
+                    .map(|failed| failed.stdout.clone() + &failed.stderr)
+                    .unwrap_or_default();
+                (HookStatus::Failed(e.to_string()), output)
+            }
+        };
+        let result = HookResult {
+            name: name.to_string(),
+            status,
+            duration,
+            output,
+            description: None,
+        };
+        match self.results.iter_mut().find(|r| r.name == name) {
+            Some(previous) => *previous = result,
+            None => self.results.push(result),
+        }
+    }
+
+    /// Records the outcome of running `hook`, with its description
+    pub fn record_hook(
+        &mut self,
+        hook: &Hook,
+        duration: Duration,
+        outcome: anyhow::Result<String>,
+    ) {
+        self.record(&hook.name, duration, outcome);
+        if let Some(result) = self.results.iter_mut().find(|r| r.name == hook.name) {
+            result.description = hook.description.clone();
+        }
+    }
+
+    pub fn skip(&mut self, name: &str, reason: &str) {
+        self.results.push(HookResult {
+            name: name.to_string(),
+            status: HookStatus::Skipped(reason.to_string()),
+            duration: Duration::default(),
+            output: String::new(),
+            description: None,
+        });
+    }
+
+    /// Returns the results of the hooks that failed
+    pub fn failures(&self) -> Vec<&HookResult> {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.status, HookStatus::Failed(_)))
+            .collect()
+    }
+
+    /// Tells whether no hook failed
+    pub fn success(&self) -> bool {
+        self.failures().is_empty()
+    }
+
+    /// Returns whether any hook actually ran
+    pub fn has_run(&self) -> bool {
+        self.results
+            .iter()
+            .any(|r| !matches!(r.status, HookStatus::Skipped(_)))
+    }
+
+    /// Keeps the report in the repository at `root`, as the last run of its event
+    pub fn save(&self, root: &str) -> anyhow::Result<()> {
+        let path = last_run_path(root, self.event)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the report of the last run of `event` in the repository at `root`, and when it
+    /// ran, if it ever did
+    pub fn last(root: &str, event: HookEvent) -> anyhow::Result<Option<(RunReport, SystemTime)>> {
+        let path = last_run_path(root, event)?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some((
+            serde_json::from_str(&content)?,
+            fs::metadata(&path)?.modified()?,
+        )))
+    }
+
+    /// Logs the failures & skipped hooks
+    pub fn log(&self) {
+        if !self.has_run() {
+            info!("Nothing to do.");
+        }
+        for result in &self.results {
+            match &result.status {
+                HookStatus::Passed => {}
+                HookStatus::Failed(reason) => {
+                    warn!(
+                        "An error occurred while executing {}: {}",
+                        result.name, reason
+                    );
+                    // the errors of the actions are the only ones shown by default
+                    if let Some(description) = &result.description {
+                        error!("{}: {}", result.name, description.trim_end());
+                    }
+                }
+                HookStatus::Skipped(reason) => info!("Skipped {}: {}", result.name, reason),
+            }
+        }
+    }
+}
+
+/// Returns where the report of the last run of `event` is kept
+fn last_run_path(root: &str, event: HookEvent) -> anyhow::Result<PathBuf> {
+    Ok(Path::new(&git::common_dir(root)?)
+        .join(LAST_RUNS_LOCATION)
+        .join(format!("{}.json", event.to_kebab_case())))
+}
+
+/// The results of the runs of several events, as `git-hooks ci` reports them
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CiReport {
+    pub success: bool,
+    pub runs: Vec<RunReport>,
+}
+
+impl CiReport {
+    pub fn new(runs: Vec<RunReport>) -> CiReport {
+        CiReport {
+            success: runs.iter().all(|r| r.success()),
+            runs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::CommandFailed;
+    use crate::report::{CiReport, HookStatus, RunReport};
+    use crate::{Hook, HookEvent};
+    use std::time::Duration;
+
+    #[test]
+    fn test_report() {
+        let mut report = RunReport::new(HookEvent::PreCommit);
+        assert!(!report.has_run());
+        report.skip("rustfmt", "not defined by any hook repo");
+        assert!(!report.has_run());
+        report.record("clippy", Duration::from_millis(3), Ok("ok\n".to_string()));
+        assert!(report.has_run());
+        assert!(report.success());
+        report.record(
+            "builtin:check-yaml",
+            Duration::from_millis(1),
+            Err(anyhow::Error::msg("invalid yaml")),
+        );
+        assert!(!report.success());
+        let hook = Hook {
+            name: "clippy-strict".to_string(),
+            description: Some("Denies the clippy warnings".to_string()),
+            ..Default::default()
+        };
+        report.record_hook(&hook, Duration::from_millis(2), Ok(String::new()));
+        assert_eq!(
+            report.results.last().unwrap().description.as_deref(),
+            Some("Denies the clippy warnings")
+        );
+        assert_eq!(report.failures().len(), 1);
+        assert_eq!(
+            report.failures()[0].status,
+            HookStatus::Failed("invalid yaml".to_string())
+        );
+        // what a failing command printed is kept
+        report.record(
+            "clippy",
+            Duration::from_millis(3),
+            Err(CommandFailed {
+                stdout: "warning: unused variable\n".to_string(),
+                stderr: "error: aborting\n".to_string(),
+            }
+            .into()),
+        );
+        // replacing its previous outcome
+        assert_eq!(report.failures()[0].name, "clippy");
+        assert_eq!(
+            report.failures()[0].output,
+            "warning: unused variable\nerror: aborting\n"
+        );
+    }
+
+    #[test]
+    fn test_ci_report() {
+        let mut report = RunReport::new(HookEvent::PrePush);
+        report.record(
+            "tests",
+            Duration::from_secs(2),
+            Err(anyhow::Error::msg("1 test failed")),
+        );
+        let ci = CiReport::new(vec![RunReport::new(HookEvent::PreCommit), report]);
+        assert!(!ci.success);
+        assert_eq!(
+            serde_json::to_string(&ci).unwrap(),
+            r#"{"success":false,"runs":[{"event":"pre-commit","results":[]},{"event":"pre-push","results":[{"name":"tests","status":"failed","reason":"1 test failed","duration":{"secs":2,"nanos":0},"output":""}]}]}"#
+        );
+    }
+}
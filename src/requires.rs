@@ -0,0 +1,170 @@
+//! The `requires` of the hooks: the programs they need, such as `docker` or `node>=18`, checked
+//! before running them, so that a missing one fails telling what to install rather than with a
+//! spawn error.
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::error::GitHooksError;
+use crate::utils::{execute_cmd, find_program, is_older, parse_version};
+
+/// A program a hook needs, at least at some version
+#[derive(Debug, PartialEq, Eq)]
+pub struct Requirement {
+    pub program: String,
+    pub min_version: Option<Vec<u64>>,
+}
+
+impl FromStr for Requirement {
+    type Err = GitHooksError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            GitHooksError::Config(format!(
+                "invalid requirement {}: expected a program, optionally followed by >= and a version",
+                s
+            ))
+        };
+        let (program, min_version) = match s.split_once(">=") {
+            Some((program, version)) => {
+                (program, Some(parse_version(version).ok_or_else(invalid)?))
+            }
+            None => (s, None),
+        };
+        let program = program.trim();
+        if program.is_empty() || program.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+        Ok(Requirement {
+            program: program.to_string(),
+            min_version,
+        })
+    }
+}
+
+/// Checks that the programs `requires` lists are in `path`, at the versions they need, for the
+/// hook `name`. Their version is the first one `<program> --version` prints.
+pub fn check(name: &str, requires: &[String], path: &OsStr) -> anyhow::Result<()> {
+    for requirement in requires {
+        let parsed: Requirement = requirement.parse()?;
+        let binary = find_program(&parsed.program, path).ok_or_else(|| {
+            anyhow::Error::msg(format!(
+                "install {} to use {}: {} is not in the PATH",
+                requirement, name, parsed.program
+            ))
+        })?;
+        let min_version = match &parsed.min_version {
+            Some(version) => version,
+            None => continue,
+        };
+        let mut env = HashMap::new();
+        env.insert("PATH".to_string(), path.to_string_lossy().into_owned());
+        let version = execute_cmd(
+            &binary.display().to_string(),
+            &["--version"],
+            None,
+            Some(&env),
+        )
+        .ok()
+        .and_then(|(_, stdout, stderr)| find_version(&format!("{}{}", stdout, stderr)));
+        match version {
+            Some(version) if !is_older(&version, min_version) => {}
+            Some(version) => {
+                return Err(anyhow::Error::msg(format!(
+                    "install {} to use {}: found {} {}",
+                    requirement,
+                    name,
+                    parsed.program,
+                    join_version(&version)
+                )))
+            }
+            None => {
+                return Err(anyhow::Error::msg(format!(
+                    "install {} to use {}: could not tell the version of {}",
+                    requirement, name, parsed.program
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the first version in what a program printed, eg. `24.0.5` in
+/// `Docker version 24.0.5, build ced0996`
+fn find_version(output: &str) -> Option<Vec<u64>> {
+    // compiled once, for all the requirements of all the hooks
+    static DOTTED: OnceLock<Regex> = OnceLock::new();
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let dotted = DOTTED.get_or_init(|| {
+        Regex::new(r"\d+(\.\d+)+").unwrap_or_else(|e| panic!("invalid regex: {}", e))
+    });
+    let number = NUMBER
+        .get_or_init(|| Regex::new(r"\d+").unwrap_or_else(|e| panic!("invalid regex: {}", e)));
+    dotted
+        .find(output)
+        .or_else(|| number.find(output))
+        .and_then(|m| parse_version(m.as_str()))
+}
+
+fn join_version(version: &[u64]) -> String {
+    version
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::requires::{check, find_version, Requirement};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "docker".parse::<Requirement>().unwrap(),
+            Requirement {
+                program: "docker".to_string(),
+                min_version: None
+            }
+        );
+        assert_eq!(
+            "node>=18".parse::<Requirement>().unwrap(),
+            Requirement {
+                program: "node".to_string(),
+                min_version: Some(vec![18])
+            }
+        );
+        assert!("node>=latest".parse::<Requirement>().is_err());
+        assert!(">=1".parse::<Requirement>().is_err());
+    }
+
+    #[test]
+    fn test_versions() {
+        assert_eq!(
+            find_version("Docker version 24.0.5, build ced0996"),
+            Some(vec![24, 0, 5])
+        );
+        assert_eq!(find_version("v18.17.0\n"), Some(vec![18, 17, 0]));
+        assert_eq!(find_version("jq-1.6"), Some(vec![1, 6]));
+        assert_eq!(find_version("no version"), None);
+    }
+
+    #[test]
+    fn test_check() {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        assert!(check("a", &["git".to_string(), "git>=1.0".to_string()], &path).is_ok());
+        let r = check("a", &["git>=999".to_string()], &path);
+        assert!(r
+            .unwrap_err()
+            .to_string()
+            .starts_with("install git>=999 to use a: found git "));
+        let r = check("a", &["not-a-program-anywhere".to_string()], &path);
+        assert_eq!(
+            r.unwrap_err().to_string(),
+            "install not-a-program-anywhere to use a: not-a-program-anywhere is not in the PATH"
+        );
+    }
+}
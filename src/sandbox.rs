@@ -0,0 +1,43 @@
+//! `no_network` hooks: running the actions without network access, so that the checks meant to be
+//! hermetic cannot depend on it.
+#[cfg(not(target_os = "linux"))]
+use log::warn;
+
+/// Wraps `cmd` & `args` so that they run in a network namespace of their own, without any
+/// interface but the loopback one, through `unshare`. It maps the user to root in a user
+/// namespace, which needs no privilege.
+#[cfg(target_os = "linux")]
+pub fn wrap_command(name: &str, cmd: String, args: Vec<String>) -> (String, Vec<String>) {
+    log::debug!("{}: running without network access", name);
+    let mut unshare_args = vec![
+        "--net".to_string(),
+        "--map-root-user".to_string(),
+        "--".to_string(),
+        cmd,
+    ];
+    unshare_args.extend(args);
+    ("unshare".to_string(), unshare_args)
+}
+
+/// Network namespaces only exist on Linux: elsewhere, the command runs as is
+#[cfg(not(target_os = "linux"))]
+pub fn wrap_command(name: &str, cmd: String, args: Vec<String>) -> (String, Vec<String>) {
+    warn!(
+        "{}: running with network access, it can only be cut off on Linux",
+        name
+    );
+    (cmd, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sandbox::wrap_command;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_wrap_command() {
+        let (cmd, args) = wrap_command("lint", "eslint".to_string(), vec!["a.js".to_string()]);
+        assert_eq!(cmd, "unshare");
+        assert_eq!(args, ["--net", "--map-root-user", "--", "eslint", "a.js"]);
+    }
+}
@@ -0,0 +1,123 @@
+//! `git-hooks run --select` & `--hook`: picking the hooks to run this time.
+use std::fs::File;
+use std::io::{stdout, BufRead, BufReader, Write};
+
+use crate::{HookConfig, HookEvent};
+
+impl HookConfig {
+    /// Lists the hooks reacting to `event` on the terminal, and lets the user uncheck the ones not
+    /// to run this time. The unchecked ones are removed from the configuration.
+    /// The answers are read from the terminal, as git gives no stdin to most hooks.
+    pub fn select(&mut self, event: HookEvent) -> anyhow::Result<()> {
+        let names: Vec<String> = self
+            .hooks
+            .iter()
+            .filter(|h| self.hook_events(h).contains(&event))
+            .map(|h| h.name.clone())
+            .collect();
+        if names.is_empty() {
+            return Ok(());
+        }
+        let tty = File::open("/dev/tty")
+            .map_err(|e| anyhow::Error::msg(format!("selecting hooks needs a terminal: {}", e)))?;
+        let mut answers = BufReader::new(tty).lines();
+        let mut checked = vec![true; names.len()];
+        loop {
+            println!("Hooks to run on {}:", event.to_kebab_case());
+            for (i, name) in names.iter().enumerate() {
+                println!(
+                    "  {}. [{}] {}",
+                    i + 1,
+                    if checked[i] { "x" } else { " " },
+                    name
+                );
+            }
+            print!("Numbers of the hooks to (un)check, or Enter to run the checked ones: ");
+            stdout().flush()?;
+            let answer = match answers.next() {
+                Some(answer) => answer?,
+                None => {
+                    return Err(anyhow::Error::msg(
+                        "no answer given: the terminal is closed",
+                    ))
+                }
+            };
+            if answer.trim().is_empty() {
+                break;
+            }
+            if let Err(e) = toggle(&mut checked, &answer) {
+                println!("{} Try again.", e);
+            }
+        }
+        self.hooks
+            .retain(|h| !names.iter().zip(&checked).any(|(n, c)| !c && *n == h.name));
+        Ok(())
+    }
+
+    /// Keeps only the enabled hooks named in `names`, eg. to run a manual one. Fails if one of
+    /// them is not enabled.
+    pub fn only(&mut self, names: &[String]) -> anyhow::Result<()> {
+        for name in names {
+            if !self.hooks.iter().any(|h| &h.name == name) {
+                return Err(anyhow::Error::msg(format!("{} is not enabled", name)));
+            }
+        }
+        self.hooks.retain(|h| names.contains(&h.name));
+        Ok(())
+    }
+}
+
+/// Flips the boxes whose 1-based numbers are listed in `answer`, separated by spaces or commas
+fn toggle(checked: &mut [bool], answer: &str) -> anyhow::Result<()> {
+    let mut numbers = Vec::new();
+    for word in answer.split(|c: char| c == ',' || c.is_whitespace()) {
+        if word.is_empty() {
+            continue;
+        }
+        match word.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= checked.len() => numbers.push(n - 1),
+            _ => {
+                return Err(anyhow::Error::msg(format!(
+                    "{} is not a hook number.",
+                    word
+                )))
+            }
+        }
+    }
+    for n in numbers {
+        checked[n] = !checked[n];
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::select::toggle;
+    use crate::HookConfig;
+
+    #[test]
+    fn test_toggle() {
+        let mut checked = vec![true, true, true];
+        toggle(&mut checked, "1, 3").unwrap();
+        assert_eq!(checked, vec![false, true, false]);
+        toggle(&mut checked, " 3 ").unwrap();
+        assert_eq!(checked, vec![false, true, true]);
+        // nothing changes on an invalid answer
+        assert!(toggle(&mut checked, "2 4").is_err());
+        assert!(toggle(&mut checked, "two").is_err());
+        assert_eq!(checked, vec![false, true, true]);
+    }
+
+    #[test]
+    fn test_only() {
+        let mut conf: HookConfig = serde_yaml::from_str(
+            "hooks:\n  - name: fmt\n  - name: tests\n    on_event: [manual]\n",
+        )
+        .unwrap();
+        assert!(conf.only(&["lint".to_string()]).is_err());
+        conf.only(&["tests".to_string()]).unwrap();
+        assert_eq!(conf.hooks.len(), 1);
+        assert_eq!(conf.hooks[0].name, "tests");
+        assert!(conf.events().is_empty());
+    }
+}
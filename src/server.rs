@@ -0,0 +1,424 @@
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::git;
+
+/// A ref update, as pushed to a repository: what `pre-receive` & `post-receive` read on stdin,
+/// and what `update` gets as arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefUpdate {
+    pub old: String,
+    pub new: String,
+    pub refname: String,
+}
+
+/// Tells whether `oid` is the null object id git uses for created & deleted refs
+fn is_null(oid: &str) -> bool {
+    oid.chars().all(|c| c == '0')
+}
+
+impl RefUpdate {
+    /// Parses the `<old> <new> <ref>` lines `pre-receive` & `post-receive` read on stdin
+    pub fn parse_lines(input: &str) -> Vec<RefUpdate> {
+        input
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                Some(RefUpdate {
+                    old: parts.next()?.to_string(),
+                    new: parts.next()?.to_string(),
+                    refname: parts.next()?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Parses the `<local ref> <local oid> <remote ref> <remote oid>` lines `pre-push` reads on
+    /// stdin: the remote refs are updated from their remote oid to the local one
+    pub fn parse_push_lines(input: &str) -> Vec<RefUpdate> {
+        input
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace().skip(1);
+                let new = parts.next()?.to_string();
+                let refname = parts.next()?.to_string();
+                Some(RefUpdate {
+                    old: parts.next()?.to_string(),
+                    new,
+                    refname,
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the `<ref> <old> <new>` arguments of the `update` hook
+    pub fn from_update_args(args: &[String]) -> Option<RefUpdate> {
+        match args {
+            [refname, old, new, ..] => Some(RefUpdate {
+                old: old.clone(),
+                new: new.clone(),
+                refname: refname.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn is_creation(&self) -> bool {
+        is_null(&self.old)
+    }
+
+    pub fn is_deletion(&self) -> bool {
+        is_null(&self.new)
+    }
+
+    /// Returns the revisions designating the commits the update brings
+    pub fn new_commits(&self) -> Vec<String> {
+        if self.is_deletion() {
+            vec![]
+        } else if self.is_creation() {
+            vec![self.new.clone(), "--not".to_string(), "--all".to_string()]
+        } else {
+            vec![format!("{}..{}", self.old, self.new)]
+        }
+    }
+
+    /// Returns the files the update brings
+    pub fn changed_files(&self) -> anyhow::Result<Vec<String>> {
+        if self.is_deletion() {
+            Ok(vec![])
+        } else if self.is_creation() {
+            git::new_commits_files(&self.new)
+        } else {
+            git::diff_files(&self.old, &self.new)
+        }
+    }
+}
+
+/// Rules the pushes to a repository must follow, checked on `pre-receive` & `update`.
+///
+/// ```yaml
+/// push_policy:
+///   protected_refs: [refs/heads/main, refs/tags/.*]
+///   require_signed_commits: true
+///   ref_patterns: ["refs/heads/(main|(feature|fix)/.+)", "refs/tags/v.+"]
+///   max_pack_size_kb: 10240
+///   bypass_audit:
+///     reject: true
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PushPolicy {
+    /// Refs, as regexps, that may neither be rewritten nor deleted
+    pub protected_refs: Vec<String>,
+    /// Refuse commits without a valid signature
+    pub require_signed_commits: bool,
+    /// Accept as well the good signatures made with keys of unknown validity, eg. keys the
+    /// server's keyring holds without trusting them
+    pub allow_unknown_validity: bool,
+    /// If not empty, the pushed refs must match one of these regexps
+    pub ref_patterns: Vec<String>,
+    /// Maximum size of the objects pushed at once
+    pub max_pack_size_kb: Option<u64>,
+    /// Report, or refuse, the commits made with the client-side hooks bypassed
+    pub bypass_audit: Option<BypassAudit>,
+}
+
+/// The trailer `builtin:mark-hooks-run` adds to the commit messages
+pub const HOOKS_RUN_TRAILER: &str = "Hooks-Run: git-hooks";
+
+/// Finds the pushed commits made with the client-side hooks bypassed, eg. with
+/// `git commit --no-verify`: their message lacks the marker the hooks add
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BypassAudit {
+    /// Regexp a line of the message of the commits the hooks ran for matches. Defaults to the
+    /// trailer of `builtin:mark-hooks-run`.
+    pub marker: Option<String>,
+    /// Refuse the pushes of such commits, instead of only warning about them
+    pub reject: bool,
+    /// File to append such commits to, one JSON object per line, relative to the git directory
+    pub log: Option<String>,
+}
+
+impl BypassAudit {
+    /// Returns the regexp matching the messages of the commits the hooks ran for
+    fn marker(&self) -> anyhow::Result<Regex> {
+        let marker = match &self.marker {
+            Some(marker) => marker.clone(),
+            None => format!("^{}$", regex::escape(HOOKS_RUN_TRAILER)),
+        };
+        Ok(Regex::new(&format!("(?m){}", marker))?)
+    }
+
+    /// Returns the hashes & author emails of the commits `update` brings without the marker
+    fn bypasses(&self, update: &RefUpdate) -> anyhow::Result<Vec<(String, String)>> {
+        let marker = self.marker()?;
+        Ok(git::commit_messages(&update.new_commits())?
+            .into_iter()
+            .filter(|(_, _, message)| !marker.is_match(message))
+            .map(|(hash, author, _)| (hash, author))
+            .collect())
+    }
+}
+
+/// Tells whether `refname` is entirely matched by one of `patterns`
+fn matches_any(refname: &str, patterns: &[String]) -> anyhow::Result<bool> {
+    for pattern in patterns {
+        if Regex::new(&format!("^(?:{})$", pattern))?.is_match(refname) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+impl PushPolicy {
+    /// Tells whether a commit with the signature status `status`, as git's `%G?` gives it, may be
+    /// pushed when signed commits are required: only a good signature, `G`, or one of unknown
+    /// validity, `U`, if allowed. Whatever else, eg. missing, bad, expired or revoked ones, and the
+    /// ones git could not check, is refused.
+    fn accepts_signature(&self, status: &str) -> bool {
+        status == "G" || (status == "U" && self.allow_unknown_validity)
+    }
+
+    /// Checks the pushed `updates`, failing with all the violations found
+    pub fn check(&self, updates: &[RefUpdate]) -> anyhow::Result<()> {
+        let mut violations = Vec::new();
+        for update in updates {
+            violations.extend(self.ref_violations(update)?);
+            if self.require_signed_commits {
+                for (hash, status) in git::signature_statuses(&update.new_commits())? {
+                    if !self.accepts_signature(&status) {
+                        violations.push(format!(
+                            "{}: commit {} has no valid signature (status {})",
+                            update.refname, hash, status
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(audit) = self.bypass_audit.as_ref().filter(|a| a.reject) {
+            for update in updates {
+                for (hash, _) in audit.bypasses(update)? {
+                    violations.push(format!(
+                        "{}: commit {} was made with the hooks bypassed",
+                        update.refname, hash
+                    ));
+                }
+            }
+        }
+        if let Some(max) = self.max_pack_size_kb {
+            let size = pushed_objects_size();
+            if size > max * 1024 {
+                violations.push(format!(
+                    "the push is {} KiB, more than the {} KiB allowed",
+                    size / 1024,
+                    max
+                ));
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::Error::msg(violations.join("\n")))
+        }
+    }
+
+    /// Warns about the commits of `updates` made with the hooks bypassed, unless they are refused,
+    /// and appends them to the log of the `bypass_audit`
+    pub fn audit(&self, updates: &[RefUpdate]) -> anyhow::Result<()> {
+        let audit = match &self.bypass_audit {
+            Some(audit) => audit,
+            None => return Ok(()),
+        };
+        let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut entries = Vec::new();
+        for update in updates {
+            for (hash, author) in audit.bypasses(update)? {
+                if !audit.reject {
+                    eprintln!(
+                        "warning: {}: commit {} by {} was made with the hooks bypassed",
+                        update.refname, hash, author
+                    );
+                }
+                entries.push(serde_json::json!({
+                    "time": time,
+                    "ref": update.refname,
+                    "commit": hash,
+                    "author": author,
+                }));
+            }
+        }
+        let log = match &audit.log {
+            Some(log) if !entries.is_empty() => log,
+            _ => return Ok(()),
+        };
+        let path = Path::new(&git::common_dir(&git::root()?)?).join(log);
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        for entry in entries {
+            writeln!(file, "{}", entry)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the rules `update` breaks by itself, regardless of the commits it brings
+    fn ref_violations(&self, update: &RefUpdate) -> anyhow::Result<Vec<String>> {
+        let mut violations = Vec::new();
+        if !self.ref_patterns.is_empty()
+            && !update.is_deletion()
+            && !matches_any(&update.refname, &self.ref_patterns)?
+        {
+            violations.push(format!("{} is not an allowed ref name", update.refname));
+        }
+        if matches_any(&update.refname, &self.protected_refs)? {
+            if update.is_deletion() {
+                violations.push(format!(
+                    "{} is protected: it cannot be deleted",
+                    update.refname
+                ));
+            } else if !update.is_creation() && !git::is_ancestor(&update.old, &update.new)? {
+                violations.push(format!(
+                    "{} is protected: it cannot be force-pushed",
+                    update.refname
+                ));
+            }
+        }
+        Ok(violations)
+    }
+}
+
+/// Returns the size, in bytes, of the objects being received. Git keeps them in a quarantine
+/// directory until `pre-receive` accepts them.
+fn pushed_objects_size() -> u64 {
+    match env::var("GIT_QUARANTINE_PATH") {
+        Ok(dir) => WalkDir::new(Path::new(&dir))
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{BypassAudit, PushPolicy, RefUpdate};
+
+    #[test]
+    fn test_parse() {
+        let zero = "0".repeat(40);
+        let a = "a".repeat(40);
+        let b = "b".repeat(40);
+        let updates = RefUpdate::parse_lines(&format!(
+            "{} {} refs/heads/main\n{} {} refs/heads/new\n\n",
+            a, b, zero, b
+        ));
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].refname, "refs/heads/main");
+        assert!(!updates[0].is_creation());
+        assert!(updates[1].is_creation());
+        assert!(!updates[1].is_deletion());
+
+        let update =
+            RefUpdate::from_update_args(&["refs/heads/main".to_string(), b.clone(), zero.clone()]);
+        assert_eq!(
+            update,
+            Some(RefUpdate {
+                old: b,
+                new: zero.clone(),
+                refname: "refs/heads/main".to_string()
+            })
+        );
+        assert!(update.unwrap().is_deletion());
+        assert_eq!(RefUpdate::from_update_args(&[]), None);
+
+        let updates = RefUpdate::parse_push_lines(&format!(
+            "refs/heads/feature {} refs/heads/feature {}\n",
+            a, zero
+        ));
+        assert_eq!(
+            updates,
+            vec![RefUpdate {
+                old: zero,
+                new: a,
+                refname: "refs/heads/feature".to_string()
+            }]
+        );
+        assert!(updates[0].is_creation());
+    }
+
+    #[test]
+    fn test_ref_violations() {
+        let policy: PushPolicy = serde_yaml::from_str(
+            "protected_refs: [refs/heads/main]\nref_patterns: ['refs/heads/(main|feature/.+)']",
+        )
+        .unwrap();
+        let zero = "0".repeat(40);
+        let a = "a".repeat(40);
+        let update = |refname: &str, old: &str, new: &str| RefUpdate {
+            old: old.to_string(),
+            new: new.to_string(),
+            refname: refname.to_string(),
+        };
+        assert!(policy
+            .ref_violations(&update("refs/heads/feature/x", &zero, &a))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            policy
+                .ref_violations(&update("refs/heads/wip", &zero, &a))
+                .unwrap()
+                .len(),
+            1
+        );
+        // patterns are anchored
+        assert_eq!(
+            policy
+                .ref_violations(&update("refs/heads/mainline", &zero, &a))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            policy
+                .ref_violations(&update("refs/heads/main", &a, &zero))
+                .unwrap(),
+            vec!["refs/heads/main is protected: it cannot be deleted"]
+        );
+    }
+
+    #[test]
+    fn test_accepts_signature() {
+        let mut policy: PushPolicy = serde_yaml::from_str("require_signed_commits: true").unwrap();
+        assert!(policy.accepts_signature("G"));
+        for status in ["U", "N", "B", "X", "Y", "R", "E", ""] {
+            assert!(!policy.accepts_signature(status), "{} accepted", status);
+        }
+        policy.allow_unknown_validity = true;
+        assert!(policy.accepts_signature("U"));
+        assert!(!policy.accepts_signature("E"));
+    }
+
+    #[test]
+    fn test_bypass_marker() {
+        let marker = BypassAudit::default().marker().unwrap();
+        assert!(marker.is_match("feat: a\n\nHooks-Run: git-hooks\n"));
+        assert!(!marker.is_match("feat: a\n"));
+        assert!(!marker.is_match("feat: a\n\n    Hooks-Run: git-hooks quoted\n"));
+        let marker = BypassAudit {
+            marker: Some("^Checked-by: ".to_string()),
+            ..Default::default()
+        }
+        .marker()
+        .unwrap();
+        assert!(marker.is_match("feat: a\n\nChecked-by: ci\n"));
+    }
+}
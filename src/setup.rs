@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::git;
+use crate::utils::{is_project_hooks_dir, state_dir};
+
+/// Where the marker is stored, relative to the state directory of the hook repo, its `.git`, so
+/// that it does not dirty the clone's worktree.
+const SETUP_MARKER_LOCATION: &str = "git-hooks-setup.yml";
+
+/// Remembers which setup scripts already ran successfully for a given hook repo version,
+/// so that they are not rerun on every config load.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct SetupMarker {
+    done: Vec<String>,
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(skip)]
+    repo_hash: String,
+}
+
+impl SetupMarker {
+    /// Reads the marker of the hook repo cloned in `clone_dir`.
+    /// Setups recorded for another revision of the repo are forgotten: for the repository's own
+    /// hook repo, the last commit changing it.
+    pub fn load(clone_dir: &str) -> anyhow::Result<SetupMarker> {
+        let path = state_dir(clone_dir).join(SETUP_MARKER_LOCATION);
+        let mut marker: SetupMarker = if path.exists() {
+            serde_yaml::from_str(&fs::read_to_string(&path)?).unwrap_or_default()
+        } else {
+            SetupMarker::default()
+        };
+        let revision = if is_project_hooks_dir(clone_dir) {
+            git::last_commit_of(clone_dir)?
+        } else {
+            git::head_if_any(clone_dir)?
+        };
+        marker.repo_hash = revision.unwrap_or_default();
+        let prefix = format!("{} ", marker.repo_hash);
+        marker.done.retain(|k| k.starts_with(&prefix));
+        marker.path = path;
+        Ok(marker)
+    }
+
+    /// Returns the key identifying a setup script at the current repo revision.
+    /// If the script is a file, its content is part of the key, so local edits trigger a rerun.
+    pub fn key(&self, clone_dir: &str, setup_script: &str) -> anyhow::Result<String> {
+        let script_path = Path::new(clone_dir).join(setup_script);
+        let script_hash = if script_path.is_file() {
+            git::hash_object(&script_path.display().to_string())?
+        } else {
+            "-".to_string()
+        };
+        Ok(format!(
+            "{} {} {}",
+            self.repo_hash, script_hash, setup_script
+        ))
+    }
+
+    pub fn is_done(&self, key: &str) -> bool {
+        self.done.iter().any(|k| k == key)
+    }
+
+    pub fn mark_done(&mut self, key: String) {
+        if !self.is_done(&key) {
+            debug!("marking setup {} as done", key);
+            self.done.push(key);
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::git;
+    use crate::setup::SetupMarker;
+    use std::fs;
+    use std::process::Command;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_marker() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let _ = git::clone(".", dir.path().display().to_string());
+        let clone_dir = dir.path().display().to_string();
+        let mut marker = SetupMarker::load(&clone_dir).expect("could not load marker");
+        fs::write(dir.path().join("setup.sh"), "#!/bin/sh\n").expect("could not write script");
+        let key = marker
+            .key(&clone_dir, "setup.sh")
+            .expect("could not compute key");
+        assert!(!marker.is_done(&key));
+        marker.mark_done(key.clone());
+        marker.save().expect("could not save marker");
+
+        let marker = SetupMarker::load(&clone_dir).expect("could not reload marker");
+        assert!(marker.is_done(&key));
+        fs::write(dir.path().join("setup.sh"), "#!/bin/sh\necho changed\n")
+            .expect("could not write script");
+        let new_key = marker
+            .key(&clone_dir, "setup.sh")
+            .expect("could not compute key");
+        assert!(!marker.is_done(&new_key));
+    }
+
+    #[test]
+    fn test_project_marker() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let root = dir.path().display().to_string();
+        git::init(Some(&root)).expect("could not init repo");
+        fs::create_dir(dir.path().join(".git-hooks")).expect("could not create dir");
+        fs::write(dir.path().join(".git-hooks/hooks.yml"), "hooks: []\n")
+            .expect("could not write manifest");
+        let hooks_dir = dir.path().join(".git-hooks").display().to_string();
+        let commit = |file: &str| {
+            let status = Command::new("sh")
+                .args(["-c", &format!("echo >> {} && git add -A && git -c user.name=test -c user.email=test@example.com commit -qm {}", file, file)])
+                .current_dir(&root)
+                .status()
+                .expect("could not commit");
+            assert!(status.success());
+        };
+
+        let mut marker = SetupMarker::load(&hooks_dir).expect("could not load marker");
+        marker.mark_done("setup".to_string());
+        marker.save().expect("could not save marker");
+        // kept out of the worktree
+        assert!(dir
+            .path()
+            .join(".git/git-hooks/project-hooks/git-hooks-setup.yml")
+            .is_file());
+        assert!(!dir.path().join(".git-hooks/.git").exists());
+
+        commit(".git-hooks/setup.sh");
+        let key = SetupMarker::load(&hooks_dir)
+            .and_then(|m| m.key(&hooks_dir, "setup.sh"))
+            .expect("could not compute key");
+        // the commits not changing the hook repo do not set it up again
+        commit("README");
+        let same = SetupMarker::load(&hooks_dir)
+            .and_then(|m| m.key(&hooks_dir, "setup.sh"))
+            .expect("could not compute key");
+        assert_eq!(key, same);
+        commit(".git-hooks/hooks.yml");
+        let changed = SetupMarker::load(&hooks_dir)
+            .and_then(|m| m.key(&hooks_dir, "setup.sh"))
+            .expect("could not compute key");
+        assert_ne!(key, changed);
+    }
+}
@@ -0,0 +1,14 @@
+# installed by git-hooks {version}
+git_hooks={binary}
+[ -x "$git_hooks" ] || git_hooks=git-hooks
+if ! command -v "$git_hooks" > /dev/null; then
+    echo "git-hooks is not installed, the {event} hooks cannot run." >&2
+    echo "Install it from https://github.com/paulollivier/git-hooks/releases/latest or with \`cargo install git-hooks-manager\`." >&2
+    if [ "$(git config --bool git-hooks.allowMissing)" = true ]; then
+        echo "Going on without them, as git-hooks.allowMissing is set." >&2
+        exit 0
+    fi
+    echo "To go on without them, run \`git config git-hooks.allowMissing true\`, or pass --no-verify to git." >&2
+    exit 1
+fi
+"$git_hooks" run {event} "$@"
@@ -0,0 +1,2 @@
+# installed by git-hooks {version}
+exec {binary} run {event} "$@"
@@ -0,0 +1,187 @@
+//! `git-hooks status`: whether `git-hooks` is healthy in a repository, at a glance. Nothing is
+//! fetched nor set up.
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use walkdir::WalkDir;
+
+use crate::install::shim_problems;
+use crate::report::{HookStatus, RunReport};
+use crate::{git, HookConfig, HookEvent, ALL_HOOK_EVENTS};
+
+/// Describes the state of `git-hooks` in the repository at `root`, configured by `config`: the
+/// configuration, the shims of its events, the hook repos' clones, and the last runs
+pub fn status(root: &str, config: &Path) -> anyhow::Result<String> {
+    let mut out = String::new();
+    let conf = if config.exists() {
+        match HookConfig::read(config, false) {
+            Ok(conf) => {
+                writeln!(
+                    out,
+                    "Configuration: {}: {} hooks, {} hook repos",
+                    config.display(),
+                    conf.hooks.len(),
+                    conf.repos.len()
+                )?;
+                Some(conf)
+            }
+            Err(e) => {
+                writeln!(out, "Configuration: {}: invalid: {:#}", config.display(), e)?;
+                None
+            }
+        }
+    } else {
+        writeln!(out, "Configuration: {}: missing", config.display())?;
+        None
+    };
+
+    writeln!(out, "Git: {}", git::version()?)?;
+    writeln!(out, "Shims:")?;
+    let events = match &conf {
+        Some(conf) => conf.events(),
+        None => vec![HookEvent::PreCommit],
+    };
+    for event in events {
+        let mut problems = shim_problems(root, event)?;
+        if event.min_git_version() > Some(git::version()?) {
+            problems.push("never run by this git");
+        }
+        let state = if problems.is_empty() {
+            "installed".to_string()
+        } else {
+            problems.join(", ")
+        };
+        writeln!(out, "  {}: {}", event.to_kebab_case(), state)?;
+    }
+
+    if let Some(conf) = &conf {
+        if !conf.repos.is_empty() {
+            writeln!(out, "Hook repos:")?;
+        }
+        for repo in &conf.repos {
+            if repo.is_project() {
+                writeln!(out, "  {}: in the repository", repo.url)?;
+                continue;
+            }
+            let pin = repo.version.as_deref().unwrap_or("latest");
+            let dir = repo.local_path()?;
+            if !Path::new(&dir).is_dir() {
+                writeln!(out, "  {} ({}): not cloned", repo.url, pin)?;
+                continue;
+            }
+            let head = git::head(&dir)?;
+            let short = &head[..head.len().min(8)];
+            let checkout = match &repo.version {
+                Some(version) if git::resolve(version, &dir).as_deref() == Some(head.as_str()) => {
+                    format!("at {}", short)
+                }
+                Some(_) => format!("at {}, not the pinned version", short),
+                None => format!("at {}", short),
+            };
+            writeln!(
+                out,
+                "  {} ({}): {}, {}",
+                repo.url,
+                pin,
+                checkout,
+                human_size(dir_size(Path::new(&dir)))
+            )?;
+        }
+    }
+
+    let mut last_runs = Vec::new();
+    for event in ALL_HOOK_EVENTS
+        .iter()
+        .chain(std::iter::once(&HookEvent::Manual))
+    {
+        if let Some(last) = RunReport::last(root, *event)? {
+            last_runs.push(last);
+        }
+    }
+    if !last_runs.is_empty() {
+        writeln!(out, "Last runs:")?;
+    }
+    for (report, time) in last_runs {
+        let count =
+            |f: fn(&HookStatus) -> bool| report.results.iter().filter(|r| f(&r.status)).count();
+        let failed: Vec<&str> = report.failures().iter().map(|r| r.name.as_str()).collect();
+        writeln!(
+            out,
+            "  {}, {}: {} passed, {} failed{}, {} skipped",
+            report.event.to_kebab_case(),
+            ago(SystemTime::now().duration_since(time).unwrap_or_default()),
+            count(|s| *s == HookStatus::Passed),
+            failed.len(),
+            if failed.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", failed.join(", "))
+            },
+            count(|s| matches!(s, HookStatus::Skipped(_)))
+        )?;
+    }
+    Ok(out)
+}
+
+/// Returns the size of the files under `dir`, in bytes
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Writes `bytes` in the largest unit it makes at least one of
+fn human_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB"] {
+        if size < 1024.0 {
+            return match unit {
+                "B" => format!("{} {}", bytes, unit),
+                _ => format!("{:.1} {}", size, unit),
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GiB", size)
+}
+
+/// Writes `elapsed` in the largest unit it makes at least one of
+fn ago(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (count, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::status::{ago, human_size};
+
+    #[test]
+    fn test_human_units() {
+        assert_eq!(human_size(512), "512 B");
+        assert_eq!(human_size(1536), "1.5 KiB");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.0 MiB");
+        assert_eq!(human_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+        assert_eq!(ago(Duration::from_secs(10)), "just now");
+        assert_eq!(ago(Duration::from_secs(60)), "1 minute ago");
+        assert_eq!(ago(Duration::from_secs(7200)), "2 hours ago");
+        assert_eq!(ago(Duration::from_secs(3 * 86400)), "3 days ago");
+    }
+}
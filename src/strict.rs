@@ -0,0 +1,233 @@
+//! Strict parsing of the configuration: the settings are optional, so that a misspelled one, such
+//! as `on_events`, would otherwise be ignored, the hook running with its default.
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+
+use crate::config_format::ConfigFormat;
+use crate::server::{BypassAudit, PushPolicy};
+use crate::{ExternalHookRepo, Hook, HookConfig};
+
+/// A setting of the configuration that no field stands for
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownKey {
+    pub key: String,
+    /// Where it is, eg. `hooks[1]`, empty at the top level
+    pub path: String,
+    /// Line it is on, from 1, when it could be found
+    pub line: Option<usize>,
+    /// The known key it is closest to, for the typos
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        write!(f, "unknown key {}", self.key)?;
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path)?;
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean {}?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the keys of the configuration `content`, written in `format`, that no setting of the
+/// configuration stands for, in the order they are written
+pub fn unknown_keys(content: &str, format: ConfigFormat) -> anyhow::Result<Vec<UnknownKey>> {
+    let conf: Value = if content.trim().is_empty() {
+        Value::Null
+    } else {
+        format.parse(content)?
+    };
+    let mut found = Vec::new();
+    if let Value::Mapping(conf) = &conf {
+        check(conf, &fields::<HookConfig>()?, "", &mut found);
+        let repo_fields = fields::<ExternalHookRepo>()?;
+        let hook_fields = fields::<Hook>()?;
+        for (i, repo) in mappings(conf, "repos") {
+            let path = format!("repos[{}]", i);
+            check(repo, &repo_fields, &path, &mut found);
+            for (j, hook) in mappings(repo, "hooks") {
+                check(
+                    hook,
+                    &hook_fields,
+                    &format!("{}.hooks[{}]", path, j),
+                    &mut found,
+                );
+            }
+        }
+        for (i, hook) in mappings(conf, "hooks") {
+            check(hook, &hook_fields, &format!("hooks[{}]", i), &mut found);
+        }
+        for (i, include) in mappings(conf, "include") {
+            check(
+                include,
+                &["url", "sha256"],
+                &format!("include[{}]", i),
+                &mut found,
+            );
+        }
+        if let Some(Value::Mapping(policy)) = conf.get(&Value::from("push_policy")) {
+            check(policy, &fields::<PushPolicy>()?, "push_policy", &mut found);
+            if let Some(Value::Mapping(audit)) = policy.get(&Value::from("bypass_audit")) {
+                check(
+                    audit,
+                    &fields::<BypassAudit>()?,
+                    "push_policy.bypass_audit",
+                    &mut found,
+                );
+            }
+        }
+    }
+    locate(content, format, &mut found);
+    Ok(found)
+}
+
+/// Returns the names of the fields of `T`, as it is written
+fn fields<T: Serialize + Default>() -> anyhow::Result<Vec<String>> {
+    Ok(match serde_yaml::to_value(T::default())? {
+        Value::Mapping(mapping) => mapping
+            .iter()
+            .filter_map(|(key, _)| key.as_str().map(String::from))
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// Returns the mappings of the list `key` of `mapping`, with their index
+fn mappings<'a>(mapping: &'a Mapping, key: &str) -> Vec<(usize, &'a Mapping)> {
+    match mapping.get(&Value::from(key)) {
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| match item {
+                Value::Mapping(m) => Some((i, m)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Adds the keys of `mapping`, at `path`, that are not in `known` to `found`
+fn check<T: AsRef<str>>(mapping: &Mapping, known: &[T], path: &str, found: &mut Vec<UnknownKey>) {
+    for (key, _) in mapping {
+        let key = match key.as_str() {
+            Some(key) => key,
+            None => continue,
+        };
+        if known.iter().any(|k| k.as_ref() == key) {
+            continue;
+        }
+        let suggestion = known
+            .iter()
+            .map(|k| (distance(k.as_ref(), key), k.as_ref()))
+            .filter(|(d, _)| *d <= 2)
+            .min()
+            .map(|(_, k)| k.to_string());
+        found.push(UnknownKey {
+            key: key.to_string(),
+            path: path.to_string(),
+            line: None,
+            suggestion,
+        });
+    }
+}
+
+/// Finds the lines of the `unknown` keys of `content`, looking for each after the previous one,
+/// as they are in the order they are written
+fn locate(content: &str, format: ConfigFormat, unknown: &mut [UnknownKey]) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut from = 0;
+    for key in unknown.iter_mut() {
+        let defines = |line: &&str| {
+            let line = line.trim_start().trim_start_matches("- ").trim_start();
+            match format {
+                ConfigFormat::Yaml => line
+                    .strip_prefix(key.key.as_str())
+                    .is_some_and(|rest| rest.trim_start().starts_with(':')),
+                ConfigFormat::Toml => line
+                    .strip_prefix(key.key.as_str())
+                    .is_some_and(|rest| rest.trim_start().starts_with('=')),
+                ConfigFormat::Json => line.contains(&format!("\"{}\"", key.key)),
+            }
+        };
+        if let Some(i) = lines.iter().skip(from).position(defines) {
+            key.line = Some(from + i + 1);
+            from += i + 1;
+        }
+    }
+}
+
+/// The number of characters to insert, delete or replace to turn `a` into `b`
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(ca != *cb);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config_format::ConfigFormat;
+    use crate::strict::unknown_keys;
+
+    #[test]
+    fn test_unknown_keys() {
+        let yaml = r#"
+version: 1
+repos:
+  - url: https://example.com/hooks
+    verison: v1
+hooks:
+  - name: fmt
+    on_events: [pre-push]
+  - name: lint
+    script: ./lint.sh
+    colour: true
+push_policy:
+  protected_refs: [main]
+"#;
+        let unknown: Vec<String> = unknown_keys(yaml, ConfigFormat::Yaml)
+            .unwrap()
+            .iter()
+            .map(|k| k.to_string())
+            .collect();
+        assert_eq!(
+            unknown,
+            [
+                "line 5: unknown key verison in repos[0], did you mean version?",
+                "line 8: unknown key on_events in hooks[0], did you mean on_event?",
+                "line 11: unknown key colour in hooks[1]",
+            ]
+        );
+        let toml = "verbose = true\n[[hooks]]\nname = \"fmt\"\non_file_regexes = [\".*\"]\n";
+        let unknown: Vec<String> = unknown_keys(toml, ConfigFormat::Toml)
+            .unwrap()
+            .iter()
+            .map(|k| k.to_string())
+            .collect();
+        assert_eq!(
+            unknown,
+            [
+                "line 1: unknown key verbose",
+                "line 4: unknown key on_file_regexes in hooks[0], did you mean on_file_regex?",
+            ]
+        );
+        assert!(unknown_keys(crate::edit::SAMPLE_CONFIG, ConfigFormat::Yaml)
+            .unwrap()
+            .is_empty());
+        assert!(unknown_keys("", ConfigFormat::Yaml).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{BTreeMap, HashMap};
+
+use log::{debug, warn};
+
+use crate::utils::is_program_in_path;
+
+/// Tool versions a hook needs, eg. `node: "20"`, resolved through mise or asdf.
+pub type Tools = BTreeMap<String, String>;
+
+/// The tool version managers git-hooks knows how to drive
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+enum Manager {
+    Mise,
+    Asdf,
+}
+
+impl Manager {
+    /// Returns the first manager installed on this machine, mise being preferred
+    fn detect() -> Option<Manager> {
+        if is_program_in_path("mise") {
+            Some(Manager::Mise)
+        } else if is_program_in_path("asdf") {
+            Some(Manager::Asdf)
+        } else {
+            None
+        }
+    }
+}
+
+/// Wraps `cmd` & `args` so that they run with the given tool versions.
+///
+/// With mise, the command is run through `mise exec`, which installs missing versions.
+/// With asdf, the versions are selected through `ASDF_<TOOL>_VERSION` variables added to `env`,
+/// which asdf's shims honor.
+/// Without any of them, the command is left untouched and a warning is emitted.
+pub fn wrap_command(
+    tools: &Tools,
+    cmd: String,
+    args: Vec<String>,
+    env: &mut HashMap<String, String>,
+) -> (String, Vec<String>) {
+    wrap_command_with(Manager::detect(), tools, cmd, args, env)
+}
+
+fn wrap_command_with(
+    manager: Option<Manager>,
+    tools: &Tools,
+    cmd: String,
+    args: Vec<String>,
+    env: &mut HashMap<String, String>,
+) -> (String, Vec<String>) {
+    if tools.is_empty() {
+        return (cmd, args);
+    }
+    debug!("running {} with tools {:?} via {:?}", cmd, tools, manager);
+    match manager {
+        Some(Manager::Mise) => {
+            let mut mise_args = vec!["exec".to_string()];
+            mise_args.extend(tools.iter().map(|(tool, v)| format!("{}@{}", tool, v)));
+            mise_args.push("--".to_string());
+            mise_args.push(cmd);
+            mise_args.extend(args);
+            ("mise".to_string(), mise_args)
+        }
+        Some(Manager::Asdf) => {
+            for (tool, version) in tools {
+                env.insert(
+                    format!("ASDF_{}_VERSION", tool.to_uppercase().replace('-', "_")),
+                    version.clone(),
+                );
+            }
+            (cmd, args)
+        }
+        None => {
+            warn!(
+                "neither mise nor asdf is installed, running {} with the tools found in $PATH instead of {:?}",
+                cmd, tools
+            );
+            (cmd, args)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::toolchain::{wrap_command_with, Manager, Tools};
+    use std::collections::HashMap;
+
+    fn tools() -> Tools {
+        let mut tools = Tools::new();
+        tools.insert("node".to_string(), "20".to_string());
+        tools.insert("python".to_string(), "3.12".to_string());
+        tools
+    }
+
+    #[test]
+    fn test_mise() {
+        let mut env = HashMap::new();
+        let (cmd, args) = wrap_command_with(
+            Some(Manager::Mise),
+            &tools(),
+            "eslint".to_string(),
+            vec!["a.js".to_string()],
+            &mut env,
+        );
+        assert_eq!(cmd, "mise");
+        assert_eq!(
+            args,
+            vec!["exec", "node@20", "python@3.12", "--", "eslint", "a.js"]
+        );
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn test_asdf() {
+        let mut env = HashMap::new();
+        let (cmd, args) = wrap_command_with(
+            Some(Manager::Asdf),
+            &tools(),
+            "eslint".to_string(),
+            vec!["a.js".to_string()],
+            &mut env,
+        );
+        assert_eq!(cmd, "eslint");
+        assert_eq!(args, vec!["a.js"]);
+        assert_eq!(env.get("ASDF_NODE_VERSION"), Some(&"20".to_string()));
+        assert_eq!(env.get("ASDF_PYTHON_VERSION"), Some(&"3.12".to_string()));
+    }
+}
@@ -0,0 +1,158 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::error::GitHooksError;
+use crate::utils::{ask_for_user_confirmation, user_config_dir};
+
+const TRUST_STORE_FILENAME: &str = "trust.yml";
+
+/// A decision the user made about an external hook repository, at a given version.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+struct TrustEntry {
+    url: String,
+    version: Option<String>,
+    trusted: bool,
+}
+
+/// Per-user record of which external hook repositories may be cloned and have their code executed.
+///
+/// Entries are keyed on the repo URL *and* its pinned version: changing the pinned revision of a
+/// repo asks for consent again, since it means running different code.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct TrustStore {
+    repos: Vec<TrustEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl TrustStore {
+    /// Loads the trust store from the user's configuration directory
+    pub fn load() -> anyhow::Result<TrustStore> {
+        TrustStore::from_path(user_config_dir()?.join(TRUST_STORE_FILENAME))
+    }
+
+    /// Loads the trust store from `path`. A missing file is an empty store.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> anyhow::Result<TrustStore> {
+        let path = path.as_ref();
+        let mut store: TrustStore = if path.exists() {
+            serde_yaml::from_str(&fs::read_to_string(path)?)?
+        } else {
+            TrustStore::default()
+        };
+        store.path = path.to_path_buf();
+        Ok(store)
+    }
+
+    /// Writes the trust store back to where it was loaded from
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the recorded decision for the given repo, if any
+    pub fn decision(&self, url: &str, version: Option<&str>) -> Option<bool> {
+        self.repos
+            .iter()
+            .find(|e| e.url == url && e.version.as_deref() == version)
+            .map(|e| e.trusted)
+    }
+
+    /// Records a decision for the given repo, replacing any previous one
+    pub fn record(&mut self, url: &str, version: Option<&str>, trusted: bool) {
+        self.repos
+            .retain(|e| !(e.url == url && e.version.as_deref() == version));
+        self.repos.push(TrustEntry {
+            url: url.to_string(),
+            version: version.map(|v| v.to_string()),
+            trusted,
+        });
+    }
+
+    /// Returns whether the given repo may be cloned & executed.
+    /// If the repo was never seen before, the user is asked on the terminal, and the decision is
+    /// saved. Fails if there is no one to ask.
+    /// `trust_all` trusts everything without asking nor recording anything, for non-interactive
+    /// environments such as CI.
+    pub fn ensure_trusted(
+        &mut self,
+        url: &str,
+        version: Option<&str>,
+        trust_all: bool,
+    ) -> anyhow::Result<bool> {
+        if trust_all {
+            info!("trusting {} because of --trust-all", url);
+            return Ok(true);
+        }
+        if let Some(trusted) = self.decision(url, version) {
+            debug!("found trust decision for {}: {}", url, trusted);
+            return Ok(trusted);
+        }
+        let prompt = format!(
+            "{} ({}) is not known yet. Cloning it will run its setup scripts & hooks on this machine. Trust it? [Y/N]",
+            url,
+            version.map_or("latest version".to_string(), |v| format!("version {}", v))
+        );
+        // nothing is recorded when no answer is given: we will ask again next time
+        let trusted = ask_for_user_confirmation(&prompt).map_err(|e| {
+            GitHooksError::Config(format!(
+                "could not ask whether to trust {} ({}), rerun with --trust-all to trust it",
+                url, e
+            ))
+        })?;
+        self.record(url, version, trusted);
+        self.save()?;
+        Ok(trusted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trust::TrustStore;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_record_and_reload() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let path = dir.path().join("git-hooks").join("trust.yml");
+        let mut store = TrustStore::from_path(&path).expect("could not load empty store");
+        assert_eq!(store.decision("https://example.com/hooks", None), None);
+        store.record("https://example.com/hooks", None, true);
+        store.record("https://example.com/hooks", Some("v1"), false);
+        store.save().expect("could not save store");
+
+        let store = TrustStore::from_path(&path).expect("could not reload store");
+        assert_eq!(
+            store.decision("https://example.com/hooks", None),
+            Some(true)
+        );
+        assert_eq!(
+            store.decision("https://example.com/hooks", Some("v1")),
+            Some(false)
+        );
+        assert_eq!(
+            store.decision("https://example.com/hooks", Some("v2")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_trust_all() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let mut store =
+            TrustStore::from_path(dir.path().join("trust.yml")).expect("could not load store");
+        let r = store.ensure_trusted("https://example.com/hooks", Some("v1"), true);
+        assert!(r.is_ok());
+        assert!(r.unwrap());
+        assert_eq!(
+            store.decision("https://example.com/hooks", Some("v1")),
+            None
+        );
+    }
+}
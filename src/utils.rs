@@ -1,24 +1,48 @@
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt::{Debug, Display};
-use std::io::Read;
-use std::path::Path;
+use std::io::{stderr, stdin, stdout, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
 use std::{env, fs};
 
 use log::{debug, error};
 use regex::Regex;
 use walkdir::WalkDir;
 
+use crate::error::{CommandFailed, GitHooksError};
 use crate::git;
 
-const HOOK_REPOS_SAVE_LOCATION: &str = ".git/hook-repos";
+/// Where the hook repos are cloned, relative to the git common directory, so that all the
+/// worktrees of a repository share them
+const HOOK_REPOS_SAVE_LOCATION: &str = "hook-repos";
+
+/// The repository's own hook repo, relative to its root: a `hooks.yml` and the scripts of its
+/// hooks, versioned with it rather than cloned. The configuration lists it by this URL.
+pub const PROJECT_HOOKS_DIR: &str = ".git-hooks";
+
+/// Where git-hooks keeps what it prepares for the repository's own hook repo, relative to the git
+/// common directory
+const PROJECT_HOOKS_STATE_LOCATION: &str = "git-hooks/project-hooks";
 
 pub fn execute_cmd<T: AsRef<str> + AsRef<OsStr> + Debug>(
     bin: &str,
     args: &[T],
     cwd: Option<&str>,
     env: Option<&HashMap<String, String>>,
+) -> anyhow::Result<(ExitStatus, String, String)> {
+    execute_cmd_with_input(bin, args, cwd, env, None)
+}
+
+/// Like `execute_cmd`, but writes `input` to the command's stdin instead of letting it inherit
+/// ours
+pub fn execute_cmd_with_input<T: AsRef<str> + AsRef<OsStr> + Debug>(
+    bin: &str,
+    args: &[T],
+    cwd: Option<&str>,
+    env: Option<&HashMap<String, String>>,
+    input: Option<&str>,
 ) -> anyhow::Result<(ExitStatus, String, String)> {
     debug!(
         "called \"{} {:?}\" in {:?} with env expanded with {:?}",
@@ -26,55 +50,127 @@ pub fn execute_cmd<T: AsRef<str> + AsRef<OsStr> + Debug>(
     );
     let empty_map = HashMap::new();
     let env = env.unwrap_or(&empty_map);
-    let mut cmd = match cwd {
-        Some(path) => Command::new(bin)
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .current_dir(path)
-            .envs(env)
-            .spawn()?,
-        None => Command::new(bin)
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .envs(env)
-            .spawn()?,
+    let mut command = Command::new(bin);
+    command
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .envs(env);
+    if let Some(path) = cwd {
+        command.current_dir(path);
+    }
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut cmd = command.spawn()?;
+    // written while what the command prints is read, for neither to block on a full pipe
+    let writer = match (input, cmd.stdin.take()) {
+        (Some(input), Some(mut child_stdin)) => {
+            let input = input.to_string();
+            Some(thread::spawn(move || {
+                // the command may not read it all
+                let _ = child_stdin.write_all(input.as_bytes());
+            }))
+        }
+        _ => None,
     };
-    let (mut stderr, mut stdout) = (String::new(), String::new());
-    if let Some(mut output) = cmd.stderr.take() {
-        output.read_to_string(&mut stderr)?;
+    let res = cmd.wait_with_output();
+    if let Some(writer) = writer {
+        let _ = writer.join();
     }
+    let output = match res {
+        Ok(output) => output,
+        Err(e) => {
+            error!("Error on \"{} {:?}\" invocation: {}", bin, args, e);
+            return Err(anyhow::Error::new(e));
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
     debug!("cmd stdout: {}", stdout);
-    if let Some(mut output) = cmd.stdout.take() {
-        output.read_to_string(&mut stdout)?;
-    }
-    debug!("cmd stderr: {}", stdout);
-    let res = cmd.wait();
-    if let Err(r) = res {
+    debug!("cmd stderr: {}", stderr);
+    let status = output.status;
+    if !status.success() {
         error!(
             "Error on \"{} {:?}\" invocation, here's the output:\nstdout: {}\nstderr: {}",
             bin, args, stdout, stderr
         );
-        return Err(anyhow::Error::new(r));
+        return Err(CommandFailed { stdout, stderr }.into());
+    }
+    Ok((status, stdout, stderr))
+}
+
+/// Like `execute_cmd_with_input`, but lets the command print on our stdout & stderr as it runs
+/// instead of capturing what it prints
+pub fn execute_cmd_live<T: AsRef<str> + AsRef<OsStr> + Debug>(
+    bin: &str,
+    args: &[T],
+    cwd: Option<&str>,
+    env: Option<&HashMap<String, String>>,
+    input: Option<&str>,
+) -> anyhow::Result<ExitStatus> {
+    debug!(
+        "called \"{} {:?}\" in {:?} with env expanded with {:?}, streaming its output",
+        bin, args, cwd, env
+    );
+    let mut command = Command::new(bin);
+    command
+        .args(args)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    if let Some(env) = env {
+        command.envs(env);
+    }
+    if let Some(path) = cwd {
+        command.current_dir(path);
+    }
+    if input.is_some() {
+        command.stdin(Stdio::piped());
     }
-    let status = res.unwrap();
+    let mut cmd = command.spawn()?;
+    if let (Some(input), Some(mut child_stdin)) = (input, cmd.stdin.take()) {
+        // the command may not read it all
+        let _ = child_stdin.write_all(input.as_bytes());
+    }
+    let status = cmd.wait()?;
     if !status.success() {
-        error!(
-            "Error on \"{} {:?}\" invocation, here's the output:\nstdout: {}\nstderr: {}",
-            bin, args, stdout, stderr
-        );
         return Err(anyhow::Error::msg("command invoked with errors"));
     }
-    Ok((status, stdout, stderr))
+    Ok(status)
+}
+
+/// Tells whether the hook repo checked out in `dir` is the `PROJECT_HOOKS_DIR` of a repository,
+/// rather than a clone
+pub fn is_project_hooks_dir(dir: &str) -> bool {
+    let dir = Path::new(dir);
+    dir.file_name() == Some(OsStr::new(PROJECT_HOOKS_DIR)) && !dir.join(".git").exists()
+}
+
+/// Returns where git-hooks keeps what it prepares for the hook repo checked out in `dir`, such as
+/// its language environments, out of its worktree: its `.git` directory, or for the repository's
+/// own hook repo, a directory of the repository's
+pub fn state_dir(dir: &str) -> PathBuf {
+    if is_project_hooks_dir(dir) {
+        if let Some(root) = Path::new(dir).parent() {
+            if let Ok(common_dir) = git::common_dir(&root.display().to_string()) {
+                return Path::new(&common_dir).join(PROJECT_HOOKS_STATE_LOCATION);
+            }
+        }
+    }
+    Path::new(dir).join(".git")
 }
 
 pub fn get_local_repo_path(url: &str) -> anyhow::Result<String> {
+    let name = url
+        .split('/')
+        .next_back()
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| GitHooksError::Config(format!("incomplete repo URL {}", url)))?;
     Ok(format!(
         "{}/{}/{}",
-        git::root()?,
+        git::common_dir(&git::root()?)?,
         HOOK_REPOS_SAVE_LOCATION,
-        url.split('/').last().expect("incomplete repo URL?")
+        name
     ))
 }
 
@@ -90,8 +186,14 @@ pub fn matches<T: AsRef<str> + Display>(e: &Path, regexps: &[T]) -> bool {
         return false;
     }
     for regex in regexps {
-        let r =
-            Regex::new(regex.as_ref()).unwrap_or_else(|regex| panic!("invalid regex: {}", regex));
+        // the configurations are checked when loaded
+        let r = match Regex::new(regex.as_ref()) {
+            Ok(r) => r,
+            Err(err) => {
+                error!("invalid regex {}: {}", regex, err);
+                continue;
+            }
+        };
         if r.is_match(&e.display().to_string()) {
             debug!("Found matching file {}", e.display());
             return true;
@@ -123,22 +225,239 @@ pub fn get_files<T: AsRef<str> + Display>(
 }
 
 /// Returns true if the given program name can be found in $PATH
-pub fn _is_program_in_path(program: &str) -> bool {
-    if let Ok(path) = env::var("PATH") {
-        for p in path.split(':') {
-            let p_str = format!("{}/{}", p, program);
-            if fs::metadata(p_str).is_ok() {
-                return true;
-            }
-        }
+pub fn is_program_in_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| find_program(program, &path).is_some())
+}
+
+/// Returns where `program` is among the directories of `path`, joined like $PATH
+pub fn find_program(program: &str, path: &OsStr) -> Option<PathBuf> {
+    env::split_paths(path).find_map(|dir| {
+        vec![dir.join(program), dir.join(format!("{}.exe", program))]
+            .into_iter()
+            .find(|p| p.exists())
+    })
+}
+
+/// Reads the numbers of a version, such as `18`, `1.2.0` or the tag `v1.2.0`. Pre-releases, such
+/// as `v2.0.0-rc.1`, are none.
+pub(crate) fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map(|n| n.parse().ok())
+        .collect()
+}
+
+/// Tells whether `version` is older than `min`, the missing numbers being zeros, so that `2.0`
+/// is no older than `2.0.0`
+pub(crate) fn is_older(version: &[u64], min: &[u64]) -> bool {
+    let len = version.len().max(min.len());
+    let pad = |v: &[u64]| {
+        let mut v = v.to_vec();
+        v.resize(len, 0);
+        v
+    };
+    pad(version) < pad(min)
+}
+
+/// Quotes `arg` for sh, unless it needs no quoting
+pub fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_=./:,@%+".contains(c))
+    {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
     }
-    false
 }
 
-pub fn prefix_path(p: &str) -> String {
-    // expand PATH
-    let mut bin_path = env::var("PATH").expect("PATH is not set in the env.");
-    bin_path.insert_str(0, &format!("{}:", p));
+/// Returns $PATH with `first` before its directories, and `last` after them, joined the way the
+/// platform does: with `:`, or `;` on Windows
+pub fn expand_path<P: AsRef<Path>>(first: &[P], last: &[P]) -> anyhow::Result<String> {
+    // hooks may run without any, eg. from a GUI client
+    let path = env::var_os("PATH").unwrap_or_default();
+    let dirs: Vec<PathBuf> = first
+        .iter()
+        .map(|p| p.as_ref().to_path_buf())
+        .chain(env::split_paths(&path))
+        .chain(last.iter().map(|p| p.as_ref().to_path_buf()))
+        .collect();
+    let bin_path = env::join_paths(dirs)?.to_string_lossy().into_owned();
     debug!("New $PATH: {}", &bin_path);
-    bin_path
+    Ok(bin_path)
+}
+
+/// Sets the unix permissions of `path` to `mode`. Windows has none: git for Windows runs the
+/// hooks whatever they are.
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+pub fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Creates the file `path` holding `content`, with the unix permissions `mode`, set before it is
+/// written. What is there already, eg. left by a crashed run, is removed first, and links are not
+/// followed: others cannot have it written elsewhere, nor read or replace it as it is.
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn create_private_file(path: &Path, content: &[u8], mode: u32) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    options.open(path)?.write_all(content)
+}
+
+/// Tells whether `path` may be executed
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> std::io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(path)?.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &Path) -> std::io::Result<bool> {
+    fs::metadata(path).map(|_| true)
+}
+
+/// Lets the owner of the file `path` write it, or makes it read-only for everyone. Windows files
+/// are left as they are, for git & the setup scripts to keep replacing them.
+#[cfg(unix)]
+pub fn set_writable(path: &Path, writable: bool) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(path)?.permissions().mode();
+    set_mode(
+        path,
+        if writable {
+            mode | 0o200
+        } else {
+            mode & !0o222
+        },
+    )
+}
+
+#[cfg(not(unix))]
+pub fn set_writable(_path: &Path, _writable: bool) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Tells whether anyone may write `path`
+#[cfg(unix)]
+pub fn is_writable(path: &Path) -> std::io::Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(fs::metadata(path)?.permissions().mode() & 0o222 != 0)
+}
+
+#[cfg(not(unix))]
+pub fn is_writable(_path: &Path) -> std::io::Result<bool> {
+    Ok(false)
+}
+
+/// Returns the system-wide configuration directory of git-hooks
+#[cfg(not(windows))]
+pub fn system_config_dir() -> PathBuf {
+    PathBuf::from("/etc/git-hooks")
+}
+
+/// Returns the system-wide configuration directory of git-hooks, in `%ProgramData%`
+#[cfg(windows)]
+pub fn system_config_dir() -> PathBuf {
+    let program_data = env::var("ProgramData").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+    Path::new(&program_data).join("git-hooks")
+}
+
+/// Returns the per-user configuration directory of git-hooks, following the XDG base directory
+/// spec: `$XDG_CONFIG_HOME/git-hooks`, falling back to `$HOME/.config/git-hooks`.
+pub fn user_config_dir() -> anyhow::Result<PathBuf> {
+    if let Ok(xdg) = env::var("XDG_CONFIG_HOME") {
+        if !xdg.is_empty() {
+            return Ok(Path::new(&xdg).join("git-hooks"));
+        }
+    }
+    // git for Windows sets $HOME, but not cmd or PowerShell
+    let home = env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .map_err(|_| anyhow::Error::msg("$HOME is not set in the env."))?;
+    Ok(Path::new(&home).join(".config").join("git-hooks"))
+}
+
+/// Asks the user a free-form question on the terminal, returning the answer without its line end
+pub fn ask_for_input(prompt: &str) -> anyhow::Result<String> {
+    print!("{}: ", prompt);
+    stdout().flush()?;
+    let mut input = String::new();
+    if stdin().read_line(&mut input)? == 0 {
+        return Err(anyhow::Error::msg("no answer given: stdin is closed"));
+    }
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Asks the user a yes/no question on the terminal, the question on stderr.
+/// The answer is read from the terminal, as git gives no stdin to most hooks and the updated refs
+/// to the others. Fails if there is no terminal, as when git is driven by a non-interactive tool.
+pub fn ask_for_user_confirmation(prompt: &str) -> anyhow::Result<bool> {
+    let tty = fs::File::open("/dev/tty")
+        .map_err(|e| anyhow::Error::msg(format!("no terminal to answer on: {}", e)))?;
+    let mut answers = BufReader::new(tty);
+    loop {
+        eprint!("{}: ", prompt);
+        stderr().flush()?;
+        let mut input = String::new();
+        if answers.read_line(&mut input)? == 0 {
+            return Err(anyhow::Error::msg(
+                "no answer given: the terminal is closed",
+            ));
+        }
+        match input.trim() {
+            "Y" | "y" => return Ok(true),
+            "N" | "n" => return Ok(false),
+            _ => eprintln!("Incorrect input. Try again."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{execute_cmd_with_input, is_older, parse_version};
+
+    #[test]
+    fn test_is_older() {
+        assert!(!is_older(&[18, 17, 0], &[18]));
+        assert!(!is_older(&[18], &[18, 0]));
+        assert!(is_older(&[16, 20, 2], &[18]));
+        let (short, long) = (
+            parse_version("v2.0").unwrap(),
+            parse_version("v2.0.0").unwrap(),
+        );
+        assert!(!is_older(&short, &long));
+        assert!(!is_older(&long, &short));
+    }
+
+    #[test]
+    fn test_execute_cmd_with_input() {
+        // more than the pipes hold, both ways
+        let input = "git-hooks\n".repeat(100_000);
+        let (status, stdout, _stderr) =
+            execute_cmd_with_input("cat", &[] as &[&str], None, None, Some(&input)).unwrap();
+        assert!(status.success());
+        assert_eq!(stdout, input);
+        // a command not reading its input
+        let (_status, stdout, _stderr) =
+            execute_cmd_with_input("echo", &["done"], None, None, Some(&input)).unwrap();
+        assert_eq!(stdout, "done\n");
+    }
 }
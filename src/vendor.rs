@@ -0,0 +1,242 @@
+//! `git-hooks vendor`: copies of the hook repos committed in the repository, used instead of
+//! cloning them, for air-gapped & reproducible setups.
+use std::fs;
+use std::path::Path;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::error::GitHooksError;
+use crate::git;
+use crate::utils::get_local_repo_path;
+use crate::ExternalHookRepo;
+
+/// Where the hook repos are vendored, relative to the repository root
+pub const VENDOR_DIR: &str = ".git-hooks/vendor";
+
+/// Lists the vendored repos, in `VENDOR_DIR`
+const VENDOR_MANIFEST: &str = "vendor.yml";
+
+/// Remembers which commit the working copy of a vendored repo holds, relative to the copy
+const VENDORED_COMMIT_MARKER: &str = ".git/git-hooks-vendored";
+
+/// A hook repo copied in `VENDOR_DIR`
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct VendoredRepo {
+    pub url: String,
+    pub version: Option<String>,
+    /// The commit the files were copied from
+    pub commit: String,
+    /// Where the files are, relative to `VENDOR_DIR`
+    pub dir: String,
+}
+
+/// The `vendor.yml` of `VENDOR_DIR`
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+struct VendorManifest {
+    repos: Vec<VendoredRepo>,
+}
+
+impl VendorManifest {
+    /// Reads the manifest of the repository at `root`. Without any, nothing is vendored.
+    fn load(root: &Path) -> anyhow::Result<VendorManifest> {
+        let path = root.join(VENDOR_DIR).join(VENDOR_MANIFEST);
+        if !path.exists() {
+            return Ok(VendorManifest::default());
+        }
+        serde_yaml::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| GitHooksError::Config(format!("invalid {}: {}", path.display(), e)).into())
+    }
+
+    fn save(&self, root: &Path) -> anyhow::Result<()> {
+        let dir = root.join(VENDOR_DIR);
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(VENDOR_MANIFEST), serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Copies the files of `repos`, at their pinned version, in the `VENDOR_DIR` of the repository
+/// at `root`, replacing the copies vendored before. The repos are fetched first. Returns the
+/// vendored repos.
+pub fn vendor(root: &Path, repos: &[ExternalHookRepo]) -> anyhow::Result<Vec<VendoredRepo>> {
+    let vendor_dir = root.join(VENDOR_DIR);
+    let previous = VendorManifest::load(root)?;
+    let mut manifest = VendorManifest::default();
+    for repo in repos {
+        let _lock = repo.lock()?;
+        let clone_dir = repo.fetch()?;
+        let dir = repo_dir_name(&repo.url);
+        let target = vendor_dir.join(&dir);
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        copy_files(Path::new(&clone_dir), &git::ls_files(&clone_dir)?, &target)?;
+        debug!("vendored {} in {}", repo.url, target.display());
+        manifest.repos.push(VendoredRepo {
+            url: repo.url.clone(),
+            version: repo.version.clone(),
+            commit: git::head(&clone_dir)?,
+            dir,
+        });
+    }
+    // the repos no longer used go away
+    for old in previous.repos {
+        if !manifest.repos.iter().any(|r| r.dir == old.dir) {
+            let _ = fs::remove_dir_all(vendor_dir.join(&old.dir));
+        }
+    }
+    manifest.save(root)?;
+    Ok(manifest.repos)
+}
+
+/// Returns the copy of the hook repo `url`, at `version`, vendored in the repository at `root`
+pub fn find(root: &Path, url: &str, version: Option<&str>) -> anyhow::Result<Option<VendoredRepo>> {
+    Ok(VendorManifest::load(root)?
+        .repos
+        .into_iter()
+        .find(|r| r.url == url && r.version.as_deref() == version))
+}
+
+/// Returns where the hooks of the vendored repo `url` run & are set up: a working copy of the
+/// vendored files next to the clones, so that the setup does not write in the repository
+pub fn run_dir(url: &str) -> anyhow::Result<String> {
+    Ok(format!("{}-vendored", get_local_repo_path(url)?))
+}
+
+/// Copies the files of `vendored`, from the repository at `root`, to its working copy `run_dir`,
+/// unless it holds them already. The working copy of another commit is thrown away, with the
+/// environments its setup prepared.
+pub fn checkout(root: &Path, vendored: &VendoredRepo, run_dir: &str) -> anyhow::Result<()> {
+    let marker = Path::new(run_dir).join(VENDORED_COMMIT_MARKER);
+    if fs::read_to_string(&marker).ok().as_deref() == Some(vendored.commit.as_str()) {
+        return Ok(());
+    }
+    debug!("copying the vendored {} to {}", vendored.url, run_dir);
+    if Path::new(run_dir).exists() {
+        fs::remove_dir_all(run_dir)?;
+    }
+    fs::create_dir_all(run_dir)?;
+    git::init(Some(run_dir))?;
+    let source = root.join(VENDOR_DIR).join(&vendored.dir);
+    let files = files(root, vendored);
+    if files.is_empty() {
+        return Err(anyhow::Error::msg(format!(
+            "{} is vendored, but {} is empty",
+            vendored.url,
+            source.display()
+        )));
+    }
+    copy_files(&source, &files, Path::new(run_dir))?;
+    fs::write(marker, &vendored.commit)?;
+    Ok(())
+}
+
+/// Returns the commit of the vendored repo whose files the working copy `run_dir` holds, if any
+pub fn checked_out_commit(run_dir: &str) -> Option<String> {
+    fs::read_to_string(Path::new(run_dir).join(VENDORED_COMMIT_MARKER)).ok()
+}
+
+/// Returns the files of `vendored`, vendored in the repository at `root`, relative to its copy
+pub fn files(root: &Path, vendored: &VendoredRepo) -> Vec<String> {
+    let source = root.join(VENDOR_DIR).join(&vendored.dir);
+    WalkDir::new(&source)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            e.path()
+                .strip_prefix(&source)
+                .ok()
+                .map(|p| p.display().to_string())
+        })
+        .collect()
+}
+
+/// Returns the directory a repo is vendored in, named like its clone
+fn repo_dir_name(url: &str) -> String {
+    url.trim_end_matches('/')
+        .split('/')
+        .next_back()
+        .unwrap_or(url)
+        .to_string()
+}
+
+/// Copies `files`, relative to `from`, to the same paths under `to`, with their permissions.
+/// Directories, such as the submodules, are left out.
+fn copy_files(from: &Path, files: &[String], to: &Path) -> anyhow::Result<()> {
+    for file in files {
+        let source = from.join(file);
+        if !source.is_file() {
+            continue;
+        }
+        let target = to.join(file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&source, &target)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempdir::TempDir;
+
+    use crate::utils::{is_executable, set_mode};
+    use crate::vendor::{checkout, find, VendorManifest, VendoredRepo, VENDOR_DIR};
+
+    #[test]
+    fn test_checkout() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create tempdir");
+        let root = dir.path().join("project");
+        let vendored = root.join(VENDOR_DIR).join("hooks");
+        fs::create_dir_all(vendored.join("scripts")).unwrap();
+        fs::write(vendored.join("hooks.yml"), "hooks: []\n").unwrap();
+        fs::write(vendored.join("scripts/check.sh"), "#!/bin/sh\n").unwrap();
+        set_mode(&vendored.join("scripts/check.sh"), 0o755).unwrap();
+        let repo = VendoredRepo {
+            url: "https://example.com/hooks".to_string(),
+            version: Some("v1".to_string()),
+            commit: "0123abcd".to_string(),
+            dir: "hooks".to_string(),
+        };
+        VendorManifest {
+            repos: vec![repo.clone()],
+        }
+        .save(&root)
+        .unwrap();
+        assert_eq!(
+            find(&root, "https://example.com/hooks", Some("v1")).unwrap(),
+            Some(repo.clone())
+        );
+        assert_eq!(
+            find(&root, "https://example.com/hooks", None).unwrap(),
+            None
+        );
+        assert_eq!(
+            find(&root, "https://example.com/other", Some("v1")).unwrap(),
+            None
+        );
+
+        let run_dir = dir.path().join("run").display().to_string();
+        checkout(&root, &repo, &run_dir).unwrap();
+        let copied = dir.path().join("run/scripts/check.sh");
+        assert!(is_executable(&copied).unwrap());
+        // the working copy of the same commit is kept, with what the setup wrote
+        fs::write(dir.path().join("run/node_modules"), "").unwrap();
+        checkout(&root, &repo, &run_dir).unwrap();
+        assert!(dir.path().join("run/node_modules").exists());
+        let newer = VendoredRepo {
+            commit: "4567ef01".to_string(),
+            ..repo
+        };
+        checkout(&root, &newer, &run_dir).unwrap();
+        assert!(!dir.path().join("run/node_modules").exists());
+        assert!(copied.exists());
+    }
+}
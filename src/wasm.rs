@@ -0,0 +1,71 @@
+use std::path::Path;
+
+/// Runs the WASI module at `module_path`, with `args` as its command line.
+///
+/// The module only sees the repository at `root`, mounted in `/src`, which is also its working
+/// directory. Returns the exit code of the module.
+#[cfg(feature = "wasm")]
+pub fn run(module_path: &Path, root: &str, args: &[String]) -> anyhow::Result<i32> {
+    use wasmtime::{Engine, Linker, Module, Store};
+    use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+    use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+    use crate::container::translate_arg;
+
+    let engine = Engine::default();
+    let module = Module::from_file(&engine, module_path)?;
+    let mut linker: Linker<WasiP1Ctx> = Linker::new(&engine);
+    preview1::add_to_linker_sync(&mut linker, |ctx| ctx)?;
+    let args: Vec<String> = args.iter().map(|a| translate_arg(a, root)).collect();
+    let wasi = WasiCtxBuilder::new()
+        .inherit_stdio()
+        .args(&args)
+        .preopened_dir(root, "/src", DirPerms::all(), FilePerms::all())?
+        .preopened_dir(root, ".", DirPerms::all(), FilePerms::all())?
+        .build_p1();
+    let mut store = Store::new(&engine, wasi);
+    let instance = linker.instantiate(&mut store, &module)?;
+    let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+    match start.call(&mut store, ()) {
+        Ok(()) => Ok(0),
+        Err(e) => match e.downcast_ref::<I32Exit>() {
+            Some(exit) => Ok(exit.0),
+            None => Err(e),
+        },
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+pub fn run(module_path: &Path, _root: &str, _args: &[String]) -> anyhow::Result<i32> {
+    Err(anyhow::Error::msg(format!(
+        "cannot run {}: git-hooks was built without the \"wasm\" feature",
+        module_path.display()
+    )))
+}
+
+#[cfg(all(test, feature = "wasm"))]
+mod tests {
+    use crate::wasm::run;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_run_exit_code() {
+        let dir = TempDir::new("git-hooks-tests").expect("could not create temp dir");
+        let module = dir.path().join("exit.wat");
+        std::fs::write(
+            &module,
+            r#"(module
+                (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+                (memory (export "memory") 1)
+                (func (export "_start") (call $exit (i32.const 3))))"#,
+        )
+        .expect("could not write module");
+        let r = run(
+            &module,
+            &dir.path().display().to_string(),
+            &["exit".to_string()],
+        );
+        assert!(r.is_ok());
+        assert_eq!(r.unwrap(), 3);
+    }
+}